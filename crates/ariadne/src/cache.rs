@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    hash::Hash,
+};
+
+use ariadne::{Cache, Source};
+
+/// Holds the text of every source a program was loaded from, keyed by source id.
+///
+/// Reports that point into several sources fetch each one from here,
+/// so errors in imported modules render with the contents of their own file.
+pub struct SourceCache<Id> {
+    sources: HashMap<Id, Source<String>>,
+}
+
+impl<Id> Default for SourceCache<Id> {
+    fn default() -> Self {
+        Self {
+            sources: HashMap::new(),
+        }
+    }
+}
+
+impl<Id: Eq + Hash> SourceCache<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the text for `id`, replacing any text it already had.
+    pub fn insert(&mut self, id: Id, text: impl Into<String>) {
+        self.sources.insert(id, Source::from(text.into()));
+    }
+
+    pub fn get(&self, id: &Id) -> Option<&str> {
+        Some(self.sources.get(id)?.text())
+    }
+
+    pub fn contains(&self, id: &Id) -> bool {
+        self.sources.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+impl<Id: Eq + Hash + Display> Cache<Id> for SourceCache<Id> {
+    type Storage = String;
+
+    fn fetch(&mut self, id: &Id) -> Result<&Source<String>, Box<dyn Debug + '_>> {
+        match self.sources.get(id) {
+            Some(source) => Ok(source),
+            None => Err(Box::new(format!("no source was loaded for '{id}'"))),
+        }
+    }
+
+    fn display<'a>(&self, id: &'a Id) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use boba_script_core::engine::EvalError;
+
+    use crate::{Diagnostics, ReportStyle};
+
+    use super::*;
+
+    #[test]
+    fn renders_each_source() {
+        let mut cache = SourceCache::new();
+        cache.insert("main", "let c = 1\nlet a = b");
+        cache.insert("util", "1 % 0");
+        assert_eq!(cache.get(&"util"), Some("1 % 0"));
+        assert_eq!(cache.len(), 2);
+
+        let mut diagnostics = Diagnostics::new(ReportStyle::default().with_color(false));
+        diagnostics.push(EvalError::UnknownVariable {
+            name: "b".into(),
+            source: ("main", 18..19),
+        });
+        diagnostics.push(EvalError::DivideByZero {
+            source: ("util", 0..5),
+        });
+
+        let output = diagnostics.render(&mut cache);
+        assert!(output.contains("[main:2:9]"));
+        assert!(output.contains("let a = b"));
+        assert!(output.contains("[util:1:1]"));
+        assert!(output.contains("1 % 0"));
+    }
+}
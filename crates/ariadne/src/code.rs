@@ -0,0 +1,316 @@
+use std::fmt::Display;
+
+/// The code attached to every report.
+///
+/// Runtime errors use `R` codes and parse errors use `P` codes.
+/// Codes are stable, so new errors are only ever appended to the end of their group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    UnknownVariable,
+    InvalidUnaryOp,
+    InvalidBinaryOp,
+    InvalidAssign,
+    InvalidTupleSize,
+    InvalidTupleDestructure,
+    UnexpectedType,
+    InvalidParameters,
+    NativeCall,
+    UnknownFunction,
+    NotAFunction,
+    UnknownField,
+    StringAllocError,
+    AllocationLimit,
+    ReassignConst,
+    TypeMismatch,
+    ReturnTypeMismatch,
+    CapabilityDenied,
+    InvalidSplat,
+    InvalidPattern,
+    DuplicateRest,
+    DivideByZero,
+    ImmutableParam,
+    ImportFailed,
+    CircularImport,
+    PrivateAccess,
+    ImportConflict,
+    TokenError,
+    UnexpectedInput,
+    UnclosedBrace,
+    InlineError,
+    EmptyBlock,
+}
+
+impl ErrorCode {
+    pub const ALL: [Self; 32] = [
+        Self::UnknownVariable,
+        Self::InvalidUnaryOp,
+        Self::InvalidBinaryOp,
+        Self::InvalidAssign,
+        Self::InvalidTupleSize,
+        Self::InvalidTupleDestructure,
+        Self::UnexpectedType,
+        Self::InvalidParameters,
+        Self::NativeCall,
+        Self::UnknownFunction,
+        Self::NotAFunction,
+        Self::UnknownField,
+        Self::StringAllocError,
+        Self::AllocationLimit,
+        Self::ReassignConst,
+        Self::TypeMismatch,
+        Self::ReturnTypeMismatch,
+        Self::CapabilityDenied,
+        Self::InvalidSplat,
+        Self::InvalidPattern,
+        Self::DuplicateRest,
+        Self::DivideByZero,
+        Self::ImmutableParam,
+        Self::ImportFailed,
+        Self::CircularImport,
+        Self::PrivateAccess,
+        Self::ImportConflict,
+        Self::TokenError,
+        Self::UnexpectedInput,
+        Self::UnclosedBrace,
+        Self::InlineError,
+        Self::EmptyBlock,
+    ];
+
+    /// Finds the error with a code like `R-003`, ignoring case.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|error| error.code().eq_ignore_ascii_case(code))
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::UnknownVariable => "R-001",
+            Self::InvalidUnaryOp => "R-002",
+            Self::InvalidBinaryOp => "R-003",
+            Self::InvalidAssign => "R-004",
+            Self::InvalidTupleSize => "R-005",
+            Self::InvalidTupleDestructure => "R-006",
+            Self::UnexpectedType => "R-007",
+            Self::InvalidParameters => "R-008",
+            Self::NativeCall => "R-009",
+            Self::UnknownFunction => "R-010",
+            Self::NotAFunction => "R-011",
+            Self::UnknownField => "R-012",
+            Self::StringAllocError => "R-013",
+            Self::AllocationLimit => "R-014",
+            Self::ReassignConst => "R-015",
+            Self::TypeMismatch => "R-016",
+            Self::ReturnTypeMismatch => "R-017",
+            Self::CapabilityDenied => "R-018",
+            Self::InvalidSplat => "R-019",
+            Self::InvalidPattern => "R-020",
+            Self::DuplicateRest => "R-021",
+            Self::DivideByZero => "R-022",
+            Self::ImmutableParam => "R-023",
+            Self::ImportFailed => "R-024",
+            Self::CircularImport => "R-025",
+            Self::PrivateAccess => "R-026",
+            Self::ImportConflict => "R-027",
+            Self::TokenError => "P-001",
+            Self::UnexpectedInput => "P-002",
+            Self::UnclosedBrace => "P-003",
+            Self::InlineError => "P-004",
+            Self::EmptyBlock => "P-005",
+        }
+    }
+
+    /// The short title shown at the top of a report.
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::UnknownVariable => "Unknown Variable",
+            Self::InvalidUnaryOp => "Invalid Unary Operator",
+            Self::InvalidBinaryOp => "Invalid Binary Operator",
+            Self::InvalidAssign => "Invalid Assignment",
+            Self::InvalidTupleSize => "Invalid Tuple Destructure",
+            Self::InvalidTupleDestructure => "Invalid Tuple Destructure",
+            Self::UnexpectedType => "Unexpected Type",
+            Self::InvalidParameters => "Parameter Count",
+            Self::NativeCall => "Native Error",
+            Self::UnknownFunction => "Unknown Function",
+            Self::NotAFunction => "Not A Function",
+            Self::UnknownField => "Unknown Field",
+            Self::StringAllocError => "String Allocation",
+            Self::AllocationLimit => "Allocation Limit",
+            Self::ReassignConst => "Constant Reassignment",
+            Self::TypeMismatch => "Type Mismatch",
+            Self::ReturnTypeMismatch => "Return Type Mismatch",
+            Self::CapabilityDenied => "Capability Denied",
+            Self::InvalidSplat => "Invalid Splat",
+            Self::InvalidPattern => "Invalid Pattern",
+            Self::DuplicateRest => "Duplicate Rest",
+            Self::DivideByZero => "Divide By Zero",
+            Self::ImmutableParam => "Immutable Param",
+            Self::ImportFailed => "Import Failed",
+            Self::CircularImport => "Circular Import",
+            Self::PrivateAccess => "Private Access",
+            Self::ImportConflict => "Import Conflict",
+            Self::TokenError => "Token Error",
+            Self::UnexpectedInput => "Unexpected Input",
+            Self::UnclosedBrace => "Unclosed Brace",
+            Self::InlineError => "Inline Error",
+            Self::EmptyBlock => "Empty Block",
+        }
+    }
+
+    /// A longer description of what causes the error and how it is usually fixed.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Self::UnknownVariable => {
+                "A variable was used before it was declared with 'let' or 'const', \
+                or after the block that declared it had ended."
+            }
+            Self::InvalidUnaryOp => {
+                "A unary operator such as '-' or 'not' was applied to a type that does not support it, \
+                like negating a string."
+            }
+            Self::InvalidBinaryOp => {
+                "A binary operator was used with two types it does not support, \
+                like subtracting a string from an int. Convert one of the values first."
+            }
+            Self::InvalidAssign => {
+                "The left side of an assignment is not something that can hold a value. \
+                Only variables, tuples of variables and patterns can be assigned to."
+            }
+            Self::InvalidTupleSize => {
+                "A tuple was destructured into a different number of variables than it holds. \
+                Match the number of variables to the tuple, or capture the rest with '*name'."
+            }
+            Self::InvalidTupleDestructure => {
+                "A single value was destructured as if it were a tuple. \
+                Only tuples can be assigned to a tuple of variables."
+            }
+            Self::UnexpectedType => {
+                "An expression produced a type that cannot be used where it appears, \
+                such as a non bool condition in an 'if'."
+            }
+            Self::InvalidParameters => {
+                "A function was called with a different number of params than it declares. \
+                Splatted tuples count as one param for each of their items."
+            }
+            Self::NativeCall => {
+                "A builtin function failed, and the message describes why. \
+                These errors can be caught with 'try'."
+            }
+            Self::UnknownFunction => {
+                "A function was called that is not declared in any visible scope. \
+                Check the spelling, or declare it with 'fn' before calling it."
+            }
+            Self::NotAFunction => {
+                "A call was made on a variable that holds a value other than a function."
+            }
+            Self::UnknownField => {
+                "A field was accessed that does not exist on the value. \
+                Use '?.' to produce none instead of an error when a field may be missing."
+            }
+            Self::StringAllocError => {
+                "A string operation would produce a string too large to allocate, \
+                usually from repeating a string too many times."
+            }
+            Self::AllocationLimit => {
+                "A value grew past the allocation limit set on the engine. \
+                This limit protects hosts from runaway scripts and cannot be caught with 'try'."
+            }
+            Self::ReassignConst => {
+                "A variable declared with 'const' was assigned a new value. \
+                Declare it with 'let' if it needs to change."
+            }
+            Self::TypeMismatch => {
+                "A value does not match the type annotation of the variable or param it was given to."
+            }
+            Self::ReturnTypeMismatch => {
+                "A function returned a value that does not match its declared output type."
+            }
+            Self::CapabilityDenied => {
+                "A builtin needed a capability, such as reading files, that the host did not grant. \
+                Hosts choose which capabilities their scripts may use."
+            }
+            Self::InvalidSplat => {
+                "A '*' splat was used on a value that is not a tuple or list. \
+                Only tuples and lists can be spread into a tuple or call params."
+            }
+            Self::InvalidPattern => {
+                "A pattern was used as a value. Patterns can only appear on the left of an assignment."
+            }
+            Self::DuplicateRest => {
+                "A tuple pattern captured the rest of its items more than once. \
+                Only one '*name' item is allowed in each pattern."
+            }
+            Self::DivideByZero => {
+                "The remainder of an int divided by zero was taken with '%'. \
+                Division with '/' always produces a float, which becomes infinity instead."
+            }
+            Self::ImmutableParam => {
+                "A builtin that changes its first param, such as 'push', was given something other \
+                than a variable. Store the value in a variable first, then pass the variable."
+            }
+            Self::ImportFailed => {
+                "A module named by 'use' could not be read or parsed. \
+                Paths are relative to the file containing the 'use', and importing needs the 'io' capability."
+            }
+            Self::CircularImport => {
+                "A module was imported while it was still being imported, \
+                usually because two modules 'use' each other. Move what they share into a third module."
+            }
+            Self::PrivateAccess => {
+                "A function was imported by name from a module that did not declare it with 'pub fn'. \
+                Mark the function 'pub' in its module to allow importing it."
+            }
+            Self::ImportConflict => {
+                "A value was imported under a name that is already defined, \
+                either by name or as one of the values of a whole module. \
+                Import it by name under another name with 'as', like 'use \"math.boba\"::max as max2'."
+            }
+            Self::TokenError => {
+                "The source contains text that is not a valid token, \
+                such as an unclosed string, an invalid escape, an invalid number or mixed indentation."
+            }
+            Self::UnexpectedInput => {
+                "The parser found a token it did not expect at this point, \
+                or the line ended before the statement was complete."
+            }
+            Self::UnclosedBrace => {
+                "An opening brace was never closed. Every '(' needs a matching ')' before the line ends."
+            }
+            Self::InlineError => {
+                "A statement that must stay on one line, like one that follows '=>', \
+                was given a multi-line block."
+            }
+            Self::EmptyBlock => {
+                "A block header, like an 'if' or 'fn' ending with ':', was not followed by \
+                an indented statement. Put a 'none' in the block to leave it empty."
+            }
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique() {
+        for (index, error) in ErrorCode::ALL.into_iter().enumerate() {
+            assert_eq!(ErrorCode::from_code(error.code()), Some(error));
+            assert!(!ErrorCode::ALL[index + 1..].contains(&error));
+        }
+
+        assert_eq!(
+            ErrorCode::from_code("r-003"),
+            Some(ErrorCode::InvalidBinaryOp)
+        );
+        assert_eq!(ErrorCode::from_code("R-999"), None);
+        assert_eq!(ErrorCode::EmptyBlock.to_string(), "P-005");
+    }
+}
@@ -0,0 +1,104 @@
+use ariadne::{Cache, Report, Span};
+
+use crate::{ReportStyle, ToAriadne};
+
+/// Collects reports without writing them, so a host can decide where they go.
+pub struct Diagnostics<'a, S: Span> {
+    style: ReportStyle,
+    reports: Vec<Report<'a, S>>,
+}
+
+impl<S: Span> Default for Diagnostics<'_, S> {
+    fn default() -> Self {
+        Self::new(ReportStyle::default())
+    }
+}
+
+impl<'a, S: Span> Diagnostics<'a, S> {
+    /// Creates an empty buffer that builds its reports with `style`.
+    pub fn new(style: ReportStyle) -> Self {
+        Self {
+            style,
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn style(&self) -> ReportStyle {
+        self.style
+    }
+
+    pub fn push(&mut self, error: impl ToAriadne<S>) {
+        self.reports.push(error.to_ariadne_with(self.style));
+    }
+
+    pub fn len(&self) -> usize {
+        self.reports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reports.is_empty()
+    }
+
+    pub fn reports(&self) -> &[Report<'a, S>] {
+        &self.reports
+    }
+
+    pub fn into_reports(self) -> Vec<Report<'a, S>> {
+        self.reports
+    }
+
+    /// Renders every collected report in order, fetching source text from `cache`.
+    pub fn render(&self, cache: impl Cache<S::SourceId>) -> String {
+        render_to_string(&self.reports, cache)
+    }
+}
+
+impl<S: Span, E: ToAriadne<S>> Extend<E> for Diagnostics<'_, S> {
+    fn extend<T: IntoIterator<Item = E>>(&mut self, errors: T) {
+        for error in errors {
+            self.push(error);
+        }
+    }
+}
+
+/// Renders `reports` into a string in the same format they would be printed in.
+///
+/// Colors are only included if the style used to build the reports enables them.
+pub fn render_to_string<'r, 'a: 'r, S: Span + 'r>(
+    reports: impl IntoIterator<Item = &'r Report<'a, S>>,
+    mut cache: impl Cache<S::SourceId>,
+) -> String {
+    let mut output = Vec::new();
+    for report in reports {
+        report
+            .write(&mut cache, &mut output)
+            .expect("writing to a vec cannot fail");
+    }
+    String::from_utf8(output).expect("reports are written as utf8")
+}
+
+#[cfg(test)]
+mod tests {
+    use ariadne::Source;
+    use boba_script_core::engine::EvalError;
+
+    use super::*;
+
+    #[test]
+    fn collects_without_printing() {
+        let mut diagnostics = Diagnostics::new(ReportStyle::default().with_color(false));
+        assert!(diagnostics.is_empty());
+        diagnostics.push(EvalError::DivideByZero { source: 4..9 });
+        diagnostics.extend([EvalError::UnknownVariable {
+            name: "b".into(),
+            source: 0..1,
+        }]);
+        assert_eq!(diagnostics.len(), 2);
+
+        let output = diagnostics.render(Source::from("b + (1 % 0)"));
+        let zero = output.find("[R-022] Error: Divide By Zero").unwrap();
+        let unknown = output.find("[R-001] Error: Unknown Variable").unwrap();
+        assert!(zero < unknown);
+        assert!(output.contains("unknown variable b"));
+    }
+}
@@ -1,285 +1,473 @@
 use std::fmt::Display;
 
-use ariadne::{Color, Label, Report, ReportKind, Span};
+use ariadne::{Color, Config, Label, Report, ReportBuilder, ReportKind, Span};
 use boba_script_core::engine::EvalError;
 use boba_script_parser::error::ParseError;
 
-pub trait ToAriadne<S: Span> {
-    fn to_ariadne<'a>(self) -> Report<'a, S>;
+use crate::ErrorCode;
+
+/// The width tabs are expanded to by the default [`ReportStyle`].
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// The colors used for report labels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Labels pointing at the cause of a problem.
+    pub error: Color,
+    /// Labels pointing at related code.
+    pub context: Color,
 }
 
-impl<S: Span> ToAriadne<S> for EvalError<S> {
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            error: Color::Red,
+            context: Color::Cyan,
+        }
+    }
+}
+
+/// Controls how reports are built and drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReportStyle {
+    config: Config,
+    palette: Palette,
+    tab_width: usize,
+    color: bool,
+}
+
+impl Default for ReportStyle {
+    fn default() -> Self {
+        Self {
+            config: Config::default().with_tab_width(DEFAULT_TAB_WIDTH),
+            palette: Palette::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            color: true,
+        }
+    }
+}
+
+impl ReportStyle {
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    pub fn is_colored(&self) -> bool {
+        self.color
+    }
+
+    /// Sets if reports are drawn with color, which is useful when output is not a terminal.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.config = self.config.with_color(color);
+        self.color = color;
+        self
+    }
+
+    /// Sets the width tabs expand to, so underlines on tab indented lines match the editor.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.config = self.config.with_tab_width(tab_width);
+        self.tab_width = tab_width;
+        self
+    }
+
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    // ariadne draws label colors even when its config disables color
+    fn label_color(&self, color: Color) -> Option<Color> {
+        Some(color).filter(|_| self.color)
+    }
+}
+
+trait Paint {
+    fn paint(self, color: Option<Color>) -> Self;
+}
+
+impl<S: Span> Paint for Label<S> {
+    fn paint(self, color: Option<Color>) -> Self {
+        match color {
+            Some(color) => self.with_color(color),
+            None => self,
+        }
+    }
+}
+
+pub trait ToAriadne<S: Span>: Sized {
+    /// Builds a report with the default [`ReportStyle`].
     fn to_ariadne<'a>(self) -> Report<'a, S> {
+        self.to_ariadne_with(ReportStyle::default())
+    }
+
+    /// Builds a report drawn with `style`.
+    fn to_ariadne_with<'a>(self, style: ReportStyle) -> Report<'a, S>;
+}
+
+/// Returns the zero-based display column at the end of `prefix`.
+///
+/// Tabs move to the next multiple of `tab_width`,
+/// and carriage returns take no space so CRLF endings never shift a column.
+pub fn display_column(prefix: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    prefix.chars().fold(0, |column, c| match c {
+        '\t' => (column / tab_width + 1) * tab_width,
+        '\r' => column,
+        _ => column + 1,
+    })
+}
+
+/// Starts an error report at `source` with the code and title of `code`.
+fn report<'a, S: Span>(code: ErrorCode, source: &S) -> ReportBuilder<'a, S> {
+    Report::build(
+        ReportKind::Error,
+        source.source().to_owned(),
+        source.start(),
+    )
+    .with_code(code)
+    .with_message(code.title())
+}
+
+impl<S: Span> ToAriadne<S> for EvalError<S> {
+    fn to_ariadne_with<'a>(self, style: ReportStyle) -> Report<'a, S> {
+        let error_color = style.label_color(style.palette.error);
+        let context_color = style.label_color(style.palette.context);
         match self {
-            EvalError::UnknownVariable { name, source } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-001")
-            .with_message("Unknown Variable")
-            .with_label(
-                Label::new(source)
-                    .with_message(format!("unknown variable {}", name))
-                    .with_color(Color::Red),
-            ),
-            EvalError::InvalidUnaryOp { ty, op, source } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-002")
-            .with_message("Invalid Unary Operator")
-            .with_label(
-                Label::new(source)
-                    .with_message(format!("'{}' operator is not valid for '{}' types", op, ty))
-                    .with_color(Color::Red),
-            ),
+            EvalError::UnknownVariable { name, source } => {
+                report(ErrorCode::UnknownVariable, &source).with_label(
+                    Label::new(source)
+                        .with_message(format!("unknown variable {}", name))
+                        .paint(error_color),
+                )
+            }
+            EvalError::InvalidUnaryOp { ty, op, source } => {
+                report(ErrorCode::InvalidUnaryOp, &source).with_label(
+                    Label::new(source)
+                        .with_message(format!("'{}' operator is not valid for '{}' types", op, ty))
+                        .paint(error_color),
+                )
+            }
             EvalError::InvalidBinaryOp {
                 ty1,
                 ty2,
                 op,
                 source,
-            } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-003")
-            .with_message("Invalid Binary Operator")
-            .with_label(
+            } => report(ErrorCode::InvalidBinaryOp, &source).with_label(
                 Label::new(source)
                     .with_message(format!(
                         "'{}' does not have a valid '{}' operator for '{}' types",
                         ty1, op, ty2
                     ))
-                    .with_color(Color::Red),
-            ),
-            EvalError::InvalidAssign { source } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-004")
-            .with_message("Invalid Assignment")
-            .with_label(
-                Label::new(source)
-                    .with_message(format!("cannot assign to this expression"))
-                    .with_color(Color::Red),
+                    .paint(error_color),
             ),
+            EvalError::InvalidAssign { source } => report(ErrorCode::InvalidAssign, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message("cannot assign to this expression")
+                        .paint(error_color),
+                ),
             EvalError::InvalidTupleSize {
                 lhs_count,
                 rhs_count,
+                rest,
                 lhs_source,
                 rhs_source,
-            } => Report::build(
-                ReportKind::Error,
-                rhs_source.source().to_owned(),
-                rhs_source.start(),
-            )
-            .with_code("R-005")
-            .with_message("Invalid Tuple Destructure")
-            .with_label(
-                Label::new(lhs_source)
-                    .with_message(format!(
-                        "expected tuple with {} parameters, found {}",
-                        rhs_count, lhs_count
-                    ))
-                    .with_color(Color::Red),
-            )
-            .with_label(
-                Label::new(rhs_source)
-                    .with_message(format!("this is a tuple with {} parameters", rhs_count))
-                    .with_color(Color::Cyan),
-            ),
+            } => report(ErrorCode::InvalidTupleSize, &rhs_source)
+                .with_label(
+                    Label::new(lhs_source)
+                        .with_message(match rest {
+                            false => format!(
+                                "expected tuple with {} parameters, found {}",
+                                rhs_count, lhs_count
+                            ),
+                            true => format!(
+                                "expected tuple with at least {} items, found {}",
+                                lhs_count, rhs_count
+                            ),
+                        })
+                        .paint(error_color),
+                )
+                .with_label(
+                    Label::new(rhs_source)
+                        .with_message(format!("this is a tuple with {} parameters", rhs_count))
+                        .paint(context_color),
+                ),
             EvalError::InvalidTupleDestructure {
                 lhs_count,
                 lhs_source,
                 rhs_source,
-            } => Report::build(
-                ReportKind::Error,
-                rhs_source.source().to_owned(),
-                rhs_source.start(),
-            )
-            .with_code("R-006")
-            .with_message("Invalid Tuple Destructure")
-            .with_label(
-                Label::new(lhs_source)
-                    .with_message(format!(
-                        "cannot destructure into tuple with {} params",
-                        lhs_count
-                    ))
-                    .with_color(Color::Red),
-            )
-            .with_label(
-                Label::new(rhs_source)
-                    .with_message(format!("this expression produces a single value"))
-                    .with_color(Color::Cyan),
-            ),
+            } => report(ErrorCode::InvalidTupleDestructure, &rhs_source)
+                .with_label(
+                    Label::new(lhs_source)
+                        .with_message(format!(
+                            "cannot destructure into tuple with {} params",
+                            lhs_count
+                        ))
+                        .paint(error_color),
+                )
+                .with_label(
+                    Label::new(rhs_source)
+                        .with_message("this expression produces a single value")
+                        .paint(context_color),
+                ),
             EvalError::UnexpectedType {
                 expect,
                 found,
                 source,
-            } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-007")
-            .with_message("Unexpected Type")
-            .with_label(
+            } => report(ErrorCode::UnexpectedType, &source).with_label(
                 Label::new(source)
                     .with_message(format!("expected '{}', found '{}'", expect, found))
-                    .with_color(Color::Red),
+                    .paint(error_color),
             ),
             EvalError::InvalidParameters {
                 found,
                 expect,
                 source,
-            } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-008")
-            .with_message("Parameter Count")
-            .with_label(
+            } => report(ErrorCode::InvalidParameters, &source).with_label(
                 Label::new(source)
                     .with_message(format!(
                         "function expects {expect} param(s). only {found} were provided"
                     ))
-                    .with_color(Color::Red),
-            ),
-            EvalError::NativeCall { message, source } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-009")
-            .with_message("Native Error")
-            .with_label(
-                Label::new(source)
-                    .with_message(format!("{message}"))
-                    .with_color(Color::Red),
-            ),
-            EvalError::UnknownFunction { name, source } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-010")
-            .with_message("Unknown Function")
-            .with_label(
-                Label::new(source)
-                    .with_message(format!("unknown function {name}"))
-                    .with_color(Color::Red),
+                    .paint(error_color),
             ),
+            EvalError::NativeCall { message, source } => report(ErrorCode::NativeCall, &source)
+                .with_label(Label::new(source).with_message(message).paint(error_color)),
+            EvalError::UnknownFunction { name, source } => {
+                report(ErrorCode::UnknownFunction, &source).with_label(
+                    Label::new(source)
+                        .with_message(format!("unknown function {name}"))
+                        .paint(error_color),
+                )
+            }
             EvalError::NotAFunction {
                 name,
                 found,
                 source,
-            } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("R-011")
-            .with_message("Not A Function")
-            .with_label(
+            } => report(ErrorCode::NotAFunction, &source).with_label(
                 Label::new(source)
                     .with_message(format!(
                         "'{name}' is not a function, it is a value with type '{found}'"
                     ))
-                    .with_color(Color::Red),
+                    .paint(error_color),
+            ),
+            EvalError::UnknownField { field, ty, source } => {
+                report(ErrorCode::UnknownField, &source).with_label(
+                    Label::new(source)
+                        .with_message(format!("'{ty}' does not have a field '{field}'"))
+                        .paint(error_color),
+                )
+            }
+            EvalError::StringAllocError { source } => report(ErrorCode::StringAllocError, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message("resulting string is too large to allocate")
+                        .paint(error_color),
+                ),
+            EvalError::AllocationLimit {
+                size,
+                limit,
+                source,
+            } => report(ErrorCode::AllocationLimit, &source).with_label(
+                Label::new(source)
+                    .with_message(format!(
+                        "value of size {size} exceeds the allocation limit of {limit}"
+                    ))
+                    .paint(error_color),
+            ),
+            EvalError::ReassignConst { name, source } => report(ErrorCode::ReassignConst, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message(format!("cannot reassign constant '{name}'"))
+                        .paint(error_color),
+                ),
+            EvalError::TypeMismatch {
+                expect,
+                found,
+                source,
+            } => report(ErrorCode::TypeMismatch, &source).with_label(
+                Label::new(source)
+                    .with_message(format!("expected '{expect}', found '{found}'"))
+                    .paint(error_color),
+            ),
+            EvalError::ReturnTypeMismatch {
+                expect,
+                found,
+                source,
+                output_source,
+            } => report(ErrorCode::ReturnTypeMismatch, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message(format!("function returned '{found}'"))
+                        .paint(error_color),
+                )
+                .with_label(
+                    Label::new(output_source)
+                        .with_message(format!("function is declared to return '{expect}'"))
+                        .paint(context_color),
+                ),
+            EvalError::CapabilityDenied { capability, source } => {
+                report(ErrorCode::CapabilityDenied, &source).with_label(
+                    Label::new(source)
+                        .with_message(format!("this call requires the '{capability}' capability"))
+                        .paint(error_color),
+                )
+            }
+            EvalError::InvalidSplat { found, source } => report(ErrorCode::InvalidSplat, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message(format!("cannot splat '{found}', expected a tuple or list"))
+                        .paint(error_color),
+                ),
+            EvalError::InvalidPattern { source } => report(ErrorCode::InvalidPattern, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message("patterns can only be assigned to")
+                        .paint(error_color),
+                ),
+            EvalError::DuplicateRest { source } => report(ErrorCode::DuplicateRest, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message("a pattern can only capture one rest item")
+                        .paint(error_color),
+                ),
+            EvalError::DivideByZero { source } => report(ErrorCode::DivideByZero, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message("cannot divide an int by zero")
+                        .paint(error_color),
+                ),
+            EvalError::ImmutableParam { source } => report(ErrorCode::ImmutableParam, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message("this call mutates its first param, which must be a variable")
+                        .paint(error_color),
+                ),
+            EvalError::ImportFailed {
+                path,
+                message,
+                source,
+            } => report(ErrorCode::ImportFailed, &source).with_label(
+                Label::new(source)
+                    .with_message(format!("could not import '{path}': {message}"))
+                    .paint(error_color),
             ),
+            EvalError::CircularImport { path, source } => {
+                report(ErrorCode::CircularImport, &source).with_label(
+                    Label::new(source)
+                        .with_message(format!("'{path}' is already being imported"))
+                        .paint(error_color),
+                )
+            }
+            EvalError::PrivateAccess { name, source } => report(ErrorCode::PrivateAccess, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message(format!("'{name}' is private to its module"))
+                        .paint(error_color),
+                ),
+            EvalError::ImportConflict { name, source } => {
+                report(ErrorCode::ImportConflict, &source).with_label(
+                    Label::new(source)
+                        .with_message(format!("'{name}' is already defined"))
+                        .paint(error_color),
+                )
+            }
         }
+        .with_config(style.config)
         .finish()
     }
 }
 
 impl<S: Span, T: Display> ToAriadne<S> for ParseError<S, T> {
-    fn to_ariadne<'a>(self) -> Report<'a, S> {
+    fn to_ariadne_with<'a>(self, style: ReportStyle) -> Report<'a, S> {
+        let error_color = style.label_color(style.palette.error);
+        let context_color = style.label_color(style.palette.context);
         match self {
-            ParseError::TokenError { error, source } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("P-001")
-            .with_message("Token Error")
-            .with_label(
-                Label::new(source)
-                    .with_message(format!("{error}"))
-                    .with_color(Color::Red),
-            ),
+            ParseError::TokenError { error, source } => report(ErrorCode::TokenError, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message(format!("{error}"))
+                        .paint(error_color),
+                ),
             ParseError::UnexpectedInput {
                 expect,
                 found,
                 source,
-            } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("P-002")
-            .with_message("Unexpected Input")
-            .with_label(
+            } => report(ErrorCode::UnexpectedInput, &source).with_label(
                 Label::new(source)
                     .with_message(match found {
                         Some(found) => format!("expected {expect}, found {found}"),
                         None => format!("expected {expect}, found end of line"),
                     })
-                    .with_color(Color::Red),
+                    .paint(error_color),
             ),
-            ParseError::UnclosedBrace { open, end } => {
-                Report::build(ReportKind::Error, open.source().to_owned(), open.start())
-                    .with_code("P-003")
-                    .with_message("Unclosed Brace")
-                    .with_label(
-                        Label::new(open)
-                            .with_message(format!("unclosed opening brace found here"))
-                            .with_color(Color::Red),
-                    )
-                    .with_label(
-                        Label::new(end)
-                            .with_message(format!("expected closing brace by this point"))
-                            .with_color(Color::Cyan),
-                    )
-            }
+            ParseError::UnclosedBrace { open, end } => report(ErrorCode::UnclosedBrace, &open)
+                .with_label(
+                    Label::new(open)
+                        .with_message("unclosed opening brace found here")
+                        .paint(error_color),
+                )
+                .with_label(
+                    Label::new(end)
+                        .with_message("expected closing brace by this point")
+                        .paint(context_color),
+                ),
             ParseError::InlineError {
                 block_source,
                 inline_source,
-            } => Report::build(
-                ReportKind::Error,
-                inline_source.source().to_owned(),
-                inline_source.start(),
-            )
-            .with_code("P-004")
-            .with_message("Inline Error")
-            .with_label(
-                Label::new(block_source)
-                    .with_message("multi-line block not allowed here, use '=>' instead")
-                    .with_color(Color::Red),
-            )
-            .with_label(
-                Label::new(inline_source)
-                    .with_message("the '=>' token forces its statement to be inline")
-                    .with_color(Color::Cyan),
-            ),
-            ParseError::EmptyBlock { source } => Report::build(
-                ReportKind::Error,
-                source.source().to_owned(),
-                source.start(),
-            )
-            .with_code("P-005")
-            .with_message("Empty Block")
-            .with_label(
-                Label::new(source)
-                    .with_message("expected statement, found an empty block")
-                    .with_color(Color::Red),
-            )
-            .with_note("try putting a temporary 'none' on the next line"),
+            } => report(ErrorCode::InlineError, &inline_source)
+                .with_label(
+                    Label::new(block_source)
+                        .with_message("multi-line block not allowed here, use '=>' instead")
+                        .paint(error_color),
+                )
+                .with_label(
+                    Label::new(inline_source)
+                        .with_message("the '=>' token forces its statement to be inline")
+                        .paint(context_color),
+                ),
+            ParseError::EmptyBlock { source } => report(ErrorCode::EmptyBlock, &source)
+                .with_label(
+                    Label::new(source)
+                        .with_message("expected statement, found an empty block")
+                        .paint(error_color),
+                )
+                .with_note("try putting a temporary 'none' on the next line"),
         }
+        .with_config(style.config)
         .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_without_color() {
+        // label colors are skipped along with the header and margin colors
+        let error = EvalError::DivideByZero { source: 0..1 };
+        let mut plain = Vec::new();
+        let report = error.to_ariadne_with(ReportStyle::default().with_color(false));
+        report
+            .write(ariadne::Source::from("1"), &mut plain)
+            .unwrap();
+        assert!(!plain.contains(&b'\x1b'));
+    }
+
+    #[test]
+    fn display_columns() {
+        assert_eq!(display_column("let a", 4), 5);
+        assert_eq!(display_column("\tlet a =\t", 4), 12);
+        assert_eq!(display_column("  \t", 8), 8);
+        assert_eq!(display_column("a\r", 4), 1);
+        assert_eq!(display_column("\t", 0), 1);
+    }
+}
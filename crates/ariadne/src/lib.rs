@@ -1,5 +1,11 @@
+mod cache;
+mod code;
+mod diagnostics;
 mod error;
 
+pub use cache::*;
+pub use code::*;
+pub use diagnostics::*;
 pub use error::*;
 
 // re-export
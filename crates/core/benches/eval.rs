@@ -0,0 +1,190 @@
+use boba_script_core::{
+    ast::{func::Func, node::Builder, Expr, ExprNode, Statement, StatementNode, Visibility},
+    vm::Chunk,
+    Engine,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+type BinaryExpr = fn(Box<ExprNode<()>>, Box<ExprNode<()>>) -> Expr<()>;
+
+fn var(name: &str) -> ExprNode<()> {
+    Expr::Var(name.into()).build_node(())
+}
+
+fn int(value: i32) -> ExprNode<()> {
+    Expr::Int(value.into()).build_node(())
+}
+
+fn binary(op: BinaryExpr, lhs: ExprNode<()>, rhs: ExprNode<()>) -> ExprNode<()> {
+    op(Box::new(lhs), Box::new(rhs)).build_node(())
+}
+
+fn assign(init: bool, name: &str, rhs: ExprNode<()>) -> StatementNode<()> {
+    Statement::Assign {
+        init,
+        lhs: var(name),
+        ty: None,
+        rhs,
+    }
+    .build_node(())
+}
+
+fn count_loop(name: &str, limit: i32, mut body: Vec<StatementNode<()>>) -> StatementNode<()> {
+    body.push(assign(false, name, binary(Expr::Add, var(name), int(1))));
+    Statement::While {
+        cond: binary(Expr::Lt, var(name), int(limit)),
+        body,
+    }
+    .build_node(())
+}
+
+/// Builds a script equivalent to:
+/// ```text
+/// let total = 0
+/// let i = 0
+/// while i < 100:
+///     let j = 0
+///     while j < 100:
+///         total = total + i * j % 7
+///         j = j + 1
+///     i = i + 1
+/// ```
+fn nested_loops() -> Vec<StatementNode<()>> {
+    let product = binary(Expr::Mul, var("i"), var("j"));
+    let total = binary(
+        Expr::Add,
+        var("total"),
+        binary(Expr::Modulo, product, int(7)),
+    );
+    let inner = count_loop("j", 100, vec![assign(false, "total", total)]);
+    let outer = count_loop("i", 100, vec![assign(true, "j", int(0)), inner]);
+    vec![
+        assign(true, "total", int(0)),
+        assign(true, "i", int(0)),
+        outer,
+    ]
+}
+
+/// Builds a script that defines many variables and repeatedly reads them:
+/// ```text
+/// let total = 0
+/// let i = 0
+/// let v0 = 0
+/// ...
+/// let v31 = 31
+/// while i < 1000:
+///     total = total + v0 + v8 + v16 + v24 + v31
+///     i = i + 1
+/// ```
+fn many_variables() -> Vec<StatementNode<()>> {
+    let mut script = vec![assign(true, "total", int(0)), assign(true, "i", int(0))];
+    for index in 0..32 {
+        script.push(assign(true, &format!("v{index}"), int(index)));
+    }
+
+    let total = ["v0", "v8", "v16", "v24", "v31"]
+        .into_iter()
+        .fold(var("total"), |total, name| {
+            binary(Expr::Add, total, var(name))
+        });
+    script.push(count_loop("i", 1000, vec![assign(false, "total", total)]));
+    script
+}
+
+/// Builds a script that evaluates the same expensive pure expression in a loop:
+/// ```text
+/// let i = 0
+/// while i < 100:
+///     let s = "ab" * 1000 + "cd" * 1000
+///     i = i + 1
+/// ```
+fn repeated_expr() -> Vec<StatementNode<()>> {
+    let string = |s: &str| Expr::String(s.into()).build_node(());
+    let ab = binary(Expr::Mul, string("ab"), int(1000));
+    let cd = binary(Expr::Mul, string("cd"), int(1000));
+    let body = vec![assign(true, "s", binary(Expr::Add, ab, cd))];
+    vec![assign(true, "i", int(0)), count_loop("i", 100, body)]
+}
+
+/// Builds a script that calls a custom function in a loop:
+/// ```text
+/// fn square(n):
+///     n * n
+/// let total = 0
+/// let i = 0
+/// while i < 1000:
+///     total = total + square(i)
+///     i = i + 1
+/// ```
+fn function_calls() -> Vec<StatementNode<()>> {
+    let square = Func {
+        vis: Visibility::Private.build_node(()),
+        params: vec!["n".into()],
+        output: None,
+        body: vec![Statement::Expr {
+            expr: binary(Expr::Mul, var("n"), var("n")),
+            closed: false,
+        }
+        .build_node(())],
+    };
+    let call = Expr::Call {
+        name: "square".into(),
+        params: vec![var("i")],
+    };
+    let total = binary(Expr::Add, var("total"), call.build_node(()));
+    vec![
+        assign(
+            true,
+            "square",
+            Expr::Func(square.build_node(())).build_node(()),
+        ),
+        assign(true, "total", int(0)),
+        assign(true, "i", int(0)),
+        count_loop("i", 1000, vec![assign(false, "total", total)]),
+    ]
+}
+
+fn run(script: &[StatementNode<()>]) {
+    run_with(script, Engine::new())
+}
+
+fn run_with(script: &[StatementNode<()>], mut engine: Engine<()>) {
+    for statement in script.iter() {
+        engine.eval(black_box(statement)).unwrap();
+    }
+}
+
+fn run_chunk(chunk: &Chunk<()>) {
+    let mut engine = Engine::new();
+    black_box(chunk).run(&mut engine).unwrap();
+}
+
+fn eval(c: &mut Criterion) {
+    let script = nested_loops();
+    c.bench_function("nested loops", |b| b.iter(|| run(&script)));
+    let chunk = Chunk::compile(&script);
+    c.bench_function("nested loops (vm)", |b| b.iter(|| run_chunk(&chunk)));
+
+    let script = many_variables();
+    c.bench_function("many variables", |b| b.iter(|| run(&script)));
+    let chunk = Chunk::compile(&script);
+    c.bench_function("many variables (vm)", |b| b.iter(|| run_chunk(&chunk)));
+
+    let script = function_calls();
+    c.bench_function("function calls", |b| b.iter(|| run(&script)));
+    let chunk = Chunk::compile(&script);
+    c.bench_function("function calls (vm)", |b| b.iter(|| run_chunk(&chunk)));
+
+    let script = repeated_expr();
+    c.bench_function("repeated expr", |b| b.iter(|| run(&script)));
+    c.bench_function("repeated expr (cached)", |b| {
+        b.iter(|| {
+            let mut engine = Engine::new();
+            engine.cache_pure_exprs(true);
+            run_with(&script, engine)
+        })
+    });
+}
+
+criterion_group!(benches, eval);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+use super::Node;
+
+/// A source comment holding the text after its `#`.
+pub type CommentNode<Source> = Node<String, Source>;
+
+/// Comments attached to a statement so tools like formatters can reinsert them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comments<Source> {
+    /// Comments on the lines directly before the statement.
+    pub leading: Vec<CommentNode<Source>>,
+    /// Comments at the end of the statement's own line.
+    pub trailing: Vec<CommentNode<Source>>,
+}
+
+impl<Source> Default for Comments<Source> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Source> Comments<Source> {
+    pub fn new() -> Self {
+        Self {
+            leading: Vec::new(),
+            trailing: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty()
+    }
+}
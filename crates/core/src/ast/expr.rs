@@ -4,13 +4,15 @@ use dashu::integer::IBig;
 
 use crate::{
     engine::{
+        cache,
+        ops::{BinaryOp, UnaryOp},
         value::{FuncPtr, ValueKind},
         EvalError, Value,
     },
     Engine,
 };
 
-use super::{func::NodeFunc, node::EvalNode, Node};
+use super::{func::NodeFunc, node::EvalNode, Node, Symbol};
 
 pub type ExprNode<Source> = Node<Expr<Source>, Source>;
 
@@ -22,10 +24,25 @@ pub enum Expr<Source> {
     Int(IBig),
     Float(f64),
     String(String),
-    Var(String),
+    Var(Symbol),
     Tuple(Vec<ExprNode<Source>>),
     Func(NodeFunc<Source>),
 
+    // SPLAT
+    /// Expands a tuple or list into the items of a surrounding tuple or call.
+    ///
+    /// Outside of a tuple or call it builds a tuple of the expanded items.
+    Splat(Box<ExprNode<Source>>),
+
+    // PATTERNS
+    /// Binds the whole value to `name` while also destructuring it with `pattern`.
+    ///
+    /// Only valid on the left side of an assignment.
+    At {
+        name: Symbol,
+        pattern: Box<ExprNode<Source>>,
+    },
+
     // UNARY OPS
     Pos(Box<ExprNode<Source>>),
     Neg(Box<ExprNode<Source>>),
@@ -55,11 +72,35 @@ pub enum Expr<Source> {
         fail: Box<ExprNode<Source>>,
     },
 
+    // ERROR HANDLING
+    /// Evaluates `body`, or `handler` with the error message bound to `name`
+    /// if `body` fails with a recoverable error.
+    Try {
+        body: Box<ExprNode<Source>>,
+        name: Symbol,
+        handler: Box<ExprNode<Source>>,
+    },
+
     // FUNCTION CALL
     Call {
-        name: String,
+        name: Symbol,
         params: Vec<ExprNode<Source>>,
     },
+
+    // FIELD ACCESS
+    Field {
+        target: Box<ExprNode<Source>>,
+        field: Node<String, Source>,
+    },
+    OptField {
+        target: Box<ExprNode<Source>>,
+        field: Node<String, Source>,
+    },
+}
+
+/// Returns true if any of the items of a tuple or call are splatted.
+pub fn has_splat<Source>(exprs: &[ExprNode<Source>]) -> bool {
+    exprs.iter().any(|expr| matches!(expr.item, Expr::Splat(_)))
 }
 
 impl<Source: Clone> EvalNode<Source> for Expr<Source> {
@@ -67,300 +108,381 @@ impl<Source: Clone> EvalNode<Source> for Expr<Source> {
         node: &Node<Self, Source>,
         engine: &mut Engine<Source>,
     ) -> Result<Value<Source>, EvalError<Source>> {
-        match &node.item {
-            // SIMPLE VALUES
-            Expr::None => Ok(Value::None),
-            Expr::Bool(value) => Ok(Value::Bool(*value)),
-            Expr::Int(value) => Ok(Value::Int(value.clone())),
-            Expr::Float(value) => Ok(Value::Float(*value)),
-            Expr::String(value) => Ok(Value::String(value.clone())),
-            Expr::Func(func) => Ok(Value::Func(FuncPtr::custom(func.deref().clone()))),
-            Expr::Tuple(exprs) => {
-                let mut values = Vec::with_capacity(exprs.len());
-                for expr in exprs {
-                    values.push(engine.eval(expr)?);
-                }
-                Ok(Value::Tuple(values.into_iter().collect()))
+        if engine.caches_pure_exprs() && !cache::is_literal(&node.item) {
+            if let Some(hash) = cache::pure_hash(node) {
+                return engine.eval_cached(hash, node, |engine| eval_expr(node, engine));
             }
+        }
 
-            // VARIABLES
-            Expr::Var(id) => match engine.vars().get(id) {
-                Some(value) => Ok(value.clone()),
-                None => Err(EvalError::UnknownVariable {
-                    source: node.source.clone(),
-                    name: id.clone(),
-                }),
-            },
+        eval_expr(node, engine)
+    }
+}
 
-            // FUNCTION CALL
-            Expr::Call { name, params } => match engine.vars().get(name.deref()) {
-                Some(Value::Func(func)) => {
-                    let func = func.clone();
-                    let mut values = Vec::new();
-                    for expr in params.iter() {
-                        values.push(engine.eval(expr)?)
-                    }
-                    func.call(&node.source, values, engine)
-                }
-                Some(value) => Err(EvalError::NotAFunction {
-                    name: name.clone(),
-                    found: value.kind(),
-                    source: node.source.clone(),
-                }),
-                None => Err(EvalError::UnknownFunction {
-                    source: node.source.clone(),
-                    name: name.to_string(),
-                }),
-            },
+fn eval_expr<Source: Clone>(
+    node: &ExprNode<Source>,
+    engine: &mut Engine<Source>,
+) -> Result<Value<Source>, EvalError<Source>> {
+    match &node.item {
+        // SIMPLE VALUES
+        Expr::None => Ok(Value::None),
+        Expr::Bool(value) => Ok(Value::Bool(*value)),
+        Expr::Int(value) => Ok(Value::Int(value.clone())),
+        Expr::Float(value) => Ok(Value::Float(*value)),
+        Expr::String(value) => Ok(Value::String(value.clone())),
+        Expr::Func(func) => {
+            let func = FuncPtr::custom(func.deref().clone()).within(engine.vars().module());
+            Ok(Value::Func(func))
+        }
+        Expr::Tuple(exprs) => {
+            engine.check_alloc(exprs.len(), &node.source)?;
+            let values = eval_items(engine, exprs)?;
+            engine.check_alloc(values.len(), &node.source)?;
+            Ok(Value::Tuple(values.into_iter().collect()))
+        }
 
-            // WALRUS
-            Expr::Walrus(lhs, rhs) => {
-                let value = engine.eval(rhs)?;
-                match &lhs.item {
-                    Expr::Var(id) => match engine.vars_mut().set(id, value.clone()) {
-                        Ok(_) => Ok(value),
-                        Err(_) => Err(EvalError::UnknownVariable {
-                            source: lhs.source.clone(),
-                            name: id.clone(),
-                        }),
-                    },
-                    _ => Err(EvalError::InvalidAssign {
-                        source: lhs.source.clone(),
-                    }),
-                }
-            }
+        // SPLAT
+        Expr::Splat(inner) => {
+            let value = engine.eval(inner)?;
+            let values = splat(value, &node.source)?;
+            Ok(Value::Tuple(values.into_iter().collect()))
+        }
 
-            // TERNARY
-            Expr::Ternary { cond, pass, fail } => match engine.eval(cond)? {
-                Value::Bool(bool) => match bool {
-                    true => engine.eval(pass),
-                    false => engine.eval(fail),
-                },
-                value => Err(EvalError::UnexpectedType {
-                    expect: ValueKind::Bool,
-                    found: value.kind(),
-                    source: cond.source.clone(),
-                }),
-            },
+        // PATTERNS
+        Expr::At { .. } => Err(EvalError::InvalidPattern {
+            source: node.source.clone(),
+        }),
 
-            // UNARY OPS
-            Expr::Pos(expr) => {
-                let inner = engine.eval(expr)?;
-                match engine.ops().pos(&inner) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidUnaryOp {
-                        ty: inner.kind(),
-                        op: "+",
-                        source: node.source.clone(),
-                    }),
-                }
-            }
-            Expr::Neg(expr) => {
-                let inner = engine.eval(expr)?;
-                match engine.ops().neg(&inner) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidUnaryOp {
-                        ty: inner.kind(),
-                        op: "-",
-                        source: node.source.clone(),
-                    }),
-                }
-            }
-            Expr::Not(expr) => {
-                let inner = engine.eval(expr)?;
-                match engine.ops().not(&inner) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidUnaryOp {
-                        ty: inner.kind(),
-                        op: "not",
-                        source: node.source.clone(),
-                    }),
-                }
-            }
+        // VARIABLES
+        Expr::Var(id) => match engine.vars().get(*id) {
+            Some(value) => Ok(value.clone()),
+            None => Err(EvalError::UnknownVariable {
+                source: node.source.clone(),
+                name: id.to_string(),
+            }),
+        },
 
-            // BINARY OPS
-            Expr::Add(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().add(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "+",
-                        source: node.source.clone(),
-                    }),
-                }
+        // FUNCTION CALL
+        Expr::Call { name, params } => match engine.vars().get_callable(*name) {
+            Some(Value::Func(func)) if func.mutates() => {
+                let func = func.clone();
+                let target = match params.first().map(|param| &param.item) {
+                    Some(Expr::Var(id)) => Some(*id),
+                    _ => None,
+                };
+                let values = eval_items(engine, params.get(1..).unwrap_or_default())?;
+                call_mut(engine, &func, target, values, &node.source)
             }
-            Expr::Sub(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().sub(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "-",
-                        source: node.source.clone(),
-                    }),
-                }
-            }
-            Expr::Mul(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().mul(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "*",
-                        source: node.source.clone(),
-                    }),
-                }
+            Some(Value::Func(func)) => {
+                let func = func.clone();
+                let values = eval_items(engine, params)?;
+                func.call(&node.source, values, engine)
             }
-            Expr::Div(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().div(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "/",
-                        source: node.source.clone(),
+            Some(value) => Err(EvalError::NotAFunction {
+                name: name.to_string(),
+                found: value.kind(),
+                source: node.source.clone(),
+            }),
+            None => Err(EvalError::UnknownFunction {
+                source: node.source.clone(),
+                name: name.to_string(),
+            }),
+        },
+
+        // FIELD ACCESS
+        Expr::Field { target, field } => {
+            let value = engine.eval(target)?;
+            get_field(&value, field)
+        }
+        Expr::OptField { target, field } => match engine.eval(target)? {
+            Value::None => Ok(Value::None),
+            value => get_field(&value, field),
+        },
+
+        // WALRUS
+        Expr::Walrus(lhs, rhs) => {
+            let value = engine.eval(rhs)?;
+            match &lhs.item {
+                Expr::Var(id) if engine.vars().is_const(*id) => Err(EvalError::ReassignConst {
+                    name: id.to_string(),
+                    source: lhs.source.clone(),
+                }),
+                Expr::Var(id) => match engine.vars_mut().set(*id, value.clone()) {
+                    Ok(_) => Ok(value),
+                    Err(_) => Err(EvalError::UnknownVariable {
+                        source: lhs.source.clone(),
+                        name: id.to_string(),
                     }),
-                }
+                },
+                _ => Err(EvalError::InvalidAssign {
+                    source: lhs.source.clone(),
+                }),
             }
-            Expr::Modulo(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().modulo(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "%",
-                        source: node.source.clone(),
-                    }),
-                }
+        }
+
+        // TERNARY
+        Expr::Ternary { cond, pass, fail } => match engine.eval(cond)? {
+            Value::Bool(bool) => match bool {
+                true => engine.eval(pass),
+                false => engine.eval(fail),
+            },
+            value => Err(EvalError::UnexpectedType {
+                expect: ValueKind::Bool,
+                found: value.kind(),
+                source: cond.source.clone(),
+            }),
+        },
+
+        // ERROR HANDLING
+        Expr::Try {
+            body,
+            name,
+            handler,
+        } => match engine.eval(body) {
+            Err(error) if error.is_recoverable() => {
+                // the error is only bound while the handler runs
+                let message = Value::String(error.message());
+                engine.vars_mut().push_scope();
+                engine.vars_mut().init_local(*name, message);
+                let result = engine.eval(handler);
+                engine.vars_mut().pop_scope();
+                result
             }
-            Expr::Pow(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().pow(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "**",
-                        source: node.source.clone(),
-                    }),
-                }
+            result => result,
+        },
+
+        // UNARY OPS
+        Expr::Pos(inner) => unary(engine, UnaryOp::Pos, inner, &node.source),
+        Expr::Neg(inner) => unary(engine, UnaryOp::Neg, inner, &node.source),
+        Expr::Not(inner) => unary(engine, UnaryOp::Not, inner, &node.source),
+
+        // BINARY OPS
+        Expr::Add(lhs, rhs) => binary(engine, BinaryOp::Add, lhs, rhs, &node.source),
+        Expr::Sub(lhs, rhs) => binary(engine, BinaryOp::Sub, lhs, rhs, &node.source),
+        Expr::Mul(lhs, rhs) => binary(engine, BinaryOp::Mul, lhs, rhs, &node.source),
+        Expr::Div(lhs, rhs) => binary(engine, BinaryOp::Div, lhs, rhs, &node.source),
+        Expr::Modulo(lhs, rhs) => binary(engine, BinaryOp::Modulo, lhs, rhs, &node.source),
+        Expr::Pow(lhs, rhs) => binary(engine, BinaryOp::Pow, lhs, rhs, &node.source),
+        Expr::Eq(lhs, rhs) => binary(engine, BinaryOp::Eq, lhs, rhs, &node.source),
+        Expr::Lt(lhs, rhs) => binary(engine, BinaryOp::Lt, lhs, rhs, &node.source),
+        Expr::Gt(lhs, rhs) => binary(engine, BinaryOp::Gt, lhs, rhs, &node.source),
+        Expr::NEq(lhs, rhs) => binary(engine, BinaryOp::NEq, lhs, rhs, &node.source),
+        Expr::LtEq(lhs, rhs) => binary(engine, BinaryOp::LtEq, lhs, rhs, &node.source),
+        Expr::GtEq(lhs, rhs) => binary(engine, BinaryOp::GtEq, lhs, rhs, &node.source),
+        Expr::And(lhs, rhs) => binary(engine, BinaryOp::And, lhs, rhs, &node.source),
+        Expr::Or(lhs, rhs) => binary(engine, BinaryOp::Or, lhs, rhs, &node.source),
+    }
+}
+
+/// Evaluates the items of a tuple or call, expanding any splatted items.
+fn eval_items<Source: Clone>(
+    engine: &mut Engine<Source>,
+    exprs: &[ExprNode<Source>],
+) -> Result<Vec<Value<Source>>, EvalError<Source>> {
+    let mut values = Vec::with_capacity(exprs.len());
+    for expr in exprs {
+        match &expr.item {
+            Expr::Splat(inner) => {
+                let value = engine.eval(inner)?;
+                values.extend(splat(value, &expr.source)?);
             }
-            Expr::Eq(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().eq(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "==",
-                        source: node.source.clone(),
-                    }),
-                }
+            _ => values.push(engine.eval(expr)?),
+        }
+    }
+    Ok(values)
+}
+
+/// Expands a tuple or list into its items, erroring for any other value.
+pub(crate) fn splat<Source: Clone>(
+    value: Value<Source>,
+    source: &Source,
+) -> Result<Vec<Value<Source>>, EvalError<Source>> {
+    match value {
+        Value::Tuple(items) => Ok(items.to_vec()),
+        Value::List(items) => Ok(items.to_vec()),
+        value => Err(EvalError::InvalidSplat {
+            found: value.kind(),
+            source: source.clone(),
+        }),
+    }
+}
+
+/// Calls a function that mutates its first param,
+/// updating the `target` variable that param was read from.
+pub(crate) fn call_mut<Source: Clone>(
+    engine: &mut Engine<Source>,
+    func: &FuncPtr<Source>,
+    target: Option<Symbol>,
+    values: Vec<Value<Source>>,
+    source: &Source,
+) -> Result<Value<Source>, EvalError<Source>> {
+    let Some(id) = target else {
+        return Err(EvalError::ImmutableParam {
+            source: source.clone(),
+        });
+    };
+
+    // move the value out of the variable so it is not cloned
+    let mut value = engine.take_var(id, source)?;
+    let output = func.call_mut(source, &mut value, values, engine);
+    engine.set_var(id, value, source)?;
+    output
+}
+
+/// Collects the variables bound by a pattern in the order they are destructured.
+pub(crate) fn pattern_ids<Source>(pattern: &ExprNode<Source>, ids: &mut Vec<Symbol>) {
+    match &pattern.item {
+        Expr::Var(id) => ids.push(*id),
+        Expr::At { name, pattern } => {
+            ids.push(*name);
+            pattern_ids(pattern, ids);
+        }
+        Expr::Splat(rest) => pattern_ids(rest, ids),
+        Expr::Tuple(items) => {
+            for item in items {
+                pattern_ids(item, ids);
             }
-            Expr::Lt(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().lt(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "<",
-                        source: node.source.clone(),
-                    }),
-                }
+        }
+        _ => (),
+    }
+}
+
+/// Destructures a value with a pattern, pushing the bound values
+/// in the same order as [`pattern_ids`].
+pub(crate) fn destructure_value<Source: Clone>(
+    pattern: &ExprNode<Source>,
+    value: Value<Source>,
+    source: &Source,
+    values: &mut Vec<Value<Source>>,
+) -> Result<(), EvalError<Source>> {
+    match &pattern.item {
+        Expr::Var(_) => values.push(value),
+        Expr::At { pattern, .. } => {
+            values.push(value.clone());
+            destructure_value(pattern, value, source, values)?;
+        }
+        Expr::Tuple(items) if has_splat(items) => {
+            let rest = items
+                .iter()
+                .position(|item| matches!(item.item, Expr::Splat(_)));
+            let Some((rest, Expr::Splat(inner))) = rest.map(|i| (i, &items[i].item)) else {
+                unreachable!("has_splat guarantees a rest item");
+            };
+
+            // only one rest item can be captured
+            let (head, tail) = (&items[..rest], &items[rest + 1..]);
+            if let Some(item) = tail.iter().find(|item| matches!(item.item, Expr::Splat(_))) {
+                return Err(EvalError::DuplicateRest {
+                    source: item.source.clone(),
+                });
             }
-            Expr::Gt(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().gt(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: ">",
-                        source: node.source.clone(),
-                    }),
+
+            let tuple = match value {
+                Value::Tuple(tuple) if tuple.len() >= head.len() + tail.len() => tuple,
+                Value::Tuple(tuple) => {
+                    return Err(EvalError::InvalidTupleSize {
+                        lhs_count: head.len() + tail.len(),
+                        rhs_count: tuple.len(),
+                        rest: true,
+                        lhs_source: pattern.source.clone(),
+                        rhs_source: source.clone(),
+                    })
                 }
-            }
-            Expr::NEq(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().neq(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "!=",
-                        source: node.source.clone(),
-                    }),
+                _ => {
+                    return Err(EvalError::InvalidTupleDestructure {
+                        lhs_count: head.len() + tail.len(),
+                        lhs_source: pattern.source.clone(),
+                        rhs_source: source.clone(),
+                    })
                 }
+            };
+
+            let end = tuple.len() - tail.len();
+            for (item, value) in head.iter().zip(tuple.iter()) {
+                destructure_value(item, value.clone(), source, values)?;
             }
-            Expr::LtEq(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().lteq(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "<=",
-                        source: node.source.clone(),
-                    }),
-                }
+            let remainder = tuple[head.len()..end].iter().cloned().collect();
+            destructure_value(inner, Value::Tuple(remainder), source, values)?;
+            for (item, value) in tail.iter().zip(tuple[end..].iter()) {
+                destructure_value(item, value.clone(), source, values)?;
             }
-            Expr::GtEq(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().gteq(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: ">=",
-                        source: node.source.clone(),
-                    }),
+        }
+        Expr::Tuple(items) => match value {
+            Value::Tuple(tuple) if tuple.len() == items.len() => {
+                for (item, value) in items.iter().zip(tuple.iter()) {
+                    destructure_value(item, value.clone(), source, values)?;
                 }
             }
-            Expr::And(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().and(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "and",
-                        source: node.source.clone(),
-                    }),
-                }
+            Value::Tuple(tuple) => {
+                return Err(EvalError::InvalidTupleSize {
+                    lhs_count: items.len(),
+                    rhs_count: tuple.len(),
+                    rest: false,
+                    lhs_source: pattern.source.clone(),
+                    rhs_source: source.clone(),
+                })
             }
-            Expr::Or(lhs, rhs) => {
-                let v1 = engine.eval(lhs)?;
-                let v2 = engine.eval(rhs)?;
-                match engine.ops().or(&v1, &v2) {
-                    Some(value) => Ok(value),
-                    None => Err(EvalError::InvalidBinaryOp {
-                        ty1: v1.kind(),
-                        ty2: v2.kind(),
-                        op: "or",
-                        source: node.source.clone(),
-                    }),
-                }
+            // a single item pattern binds non tuple values whole
+            value if items.len() == 1 => destructure_value(&items[0], value, source, values)?,
+            _ => {
+                return Err(EvalError::InvalidTupleDestructure {
+                    lhs_count: items.len(),
+                    lhs_source: pattern.source.clone(),
+                    rhs_source: source.clone(),
+                })
             }
+        },
+        _ => {
+            return Err(EvalError::InvalidAssign {
+                source: pattern.source.clone(),
+            })
         }
     }
+
+    Ok(())
+}
+
+fn unary<Source: Clone>(
+    engine: &mut Engine<Source>,
+    op: UnaryOp,
+    inner: &ExprNode<Source>,
+    source: &Source,
+) -> Result<Value<Source>, EvalError<Source>> {
+    let value = engine.eval(inner)?;
+    engine.apply_unary(op, &value, source)
+}
+
+fn binary<Source: Clone>(
+    engine: &mut Engine<Source>,
+    op: BinaryOp,
+    lhs: &ExprNode<Source>,
+    rhs: &ExprNode<Source>,
+    source: &Source,
+) -> Result<Value<Source>, EvalError<Source>> {
+    let v1 = engine.eval(lhs)?;
+    let v2 = engine.eval(rhs)?;
+    engine.apply_binary(op, &v1, &v2, source)
+}
+
+/// Reads a field of a value, which is an index into a tuple or a string key of a map.
+pub(crate) fn get_field<Source: Clone>(
+    value: &Value<Source>,
+    field: &Node<String, Source>,
+) -> Result<Value<Source>, EvalError<Source>> {
+    let item = match value {
+        Value::Tuple(tuple) => match field.parse::<usize>() {
+            Ok(index) => tuple.get(index),
+            Err(_) => None,
+        },
+        Value::Map(map) => map.get(&Value::String(field.item.clone())),
+        _ => None,
+    };
+
+    match item {
+        Some(item) => Ok(item.clone()),
+        None => Err(EvalError::UnknownField {
+            field: field.item.clone(),
+            ty: value.kind(),
+            source: field.source.clone(),
+        }),
+    }
 }
@@ -1,12 +1,14 @@
 use std::fmt::Display;
 
-use super::{Node, StatementNode};
+use super::{Node, StatementNode, Symbol, Type, Visibility};
 
 pub type NodeFunc<Source> = Node<Func<Source>, Source>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Func<Source> {
-    pub params: Vec<String>,
+    pub vis: Node<Visibility, Source>,
+    pub params: Vec<Symbol>,
+    pub output: Option<Node<Type, Source>>,
     pub body: Vec<StatementNode<Source>>,
 }
 
@@ -15,10 +17,13 @@ impl<Source> Display for Func<Source> {
         let params = self
             .params
             .iter()
-            .map(|p| format!("{p}"))
+            .map(|p| p.to_string())
             .collect::<Vec<_>>()
             .join(", ");
 
-        write!(f, "fn({params})")
+        match &self.output {
+            Some(output) => write!(f, "fn({params}) -> {}", output.item),
+            None => write!(f, "fn({params})"),
+        }
     }
 }
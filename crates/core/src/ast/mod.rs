@@ -1,8 +1,16 @@
+pub mod comment;
 pub mod expr;
 pub mod func;
 pub mod node;
 pub mod statement;
+pub mod symbol;
+pub mod ty;
+pub mod vis;
 
+pub use comment::{CommentNode, Comments};
 pub use expr::{Expr, ExprNode};
 pub use node::Node;
-pub use statement::{Statement, StatementNode};
+pub use statement::{Import, Statement, StatementNode};
+pub use symbol::{Symbol, SymbolQuery};
+pub use ty::Type;
+pub use vis::Visibility;
@@ -1,4 +1,10 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    cell::OnceCell,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
 
 use derive_more::Display;
 
@@ -7,15 +13,61 @@ use crate::{
     Engine,
 };
 
-#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Display, Clone)]
 #[display(fmt = "{}", item)]
 pub struct Node<Item, Source> {
     pub item: Item,
     pub source: Source,
+    /// The structural hash of the item if it is a pure expression,
+    /// computed the first time it is needed. See [`cache::pure_hash`](crate::engine::cache::pure_hash).
+    pub(crate) pure: OnceCell<Option<u64>>,
+}
+
+impl<Item: fmt::Debug, Source: fmt::Debug> fmt::Debug for Node<Item, Source> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("item", &self.item)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl<Item: PartialEq, Source: PartialEq> PartialEq for Node<Item, Source> {
+    fn eq(&self, other: &Self) -> bool {
+        self.item == other.item && self.source == other.source
+    }
+}
+
+impl<Item: Eq, Source: Eq> Eq for Node<Item, Source> {}
+
+impl<Item: PartialOrd, Source: PartialOrd> PartialOrd for Node<Item, Source> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.item.partial_cmp(&other.item)? {
+            Ordering::Equal => self.source.partial_cmp(&other.source),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl<Item: Ord, Source: Ord> Ord for Node<Item, Source> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item
+            .cmp(&other.item)
+            .then_with(|| self.source.cmp(&other.source))
+    }
+}
+
+impl<Item: Hash, Source: Hash> Hash for Node<Item, Source> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.item.hash(state);
+        self.source.hash(state);
+    }
 }
 
 impl<Item, Source> DerefMut for Node<Item, Source> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        // the item may change, so anything computed from it is forgotten
+        self.pure.take();
         &mut self.item
     }
 }
@@ -36,7 +88,11 @@ impl<Item, Source> AsRef<Node<Item, Source>> for Node<Item, Source> {
 
 impl<Item, Source> Node<Item, Source> {
     pub fn new(item: Item, source: Source) -> Self {
-        Self { item, source }
+        Self {
+            item,
+            source,
+            pure: OnceCell::new(),
+        }
     }
 }
 
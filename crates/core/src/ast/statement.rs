@@ -3,7 +3,7 @@ use crate::{
     Engine,
 };
 
-use super::{expr::ExprNode, node::EvalNode, Node};
+use super::{comment::Comments, expr::ExprNode, node::EvalNode, Node, Symbol, Type};
 
 pub type StatementNode<Source> = Node<Statement<Source>, Source>;
 
@@ -16,17 +16,52 @@ pub enum Statement<Source> {
     Assign {
         init: bool,
         lhs: ExprNode<Source>,
+        ty: Option<Node<Type, Source>>,
+        rhs: ExprNode<Source>,
+    },
+    Const {
+        lhs: ExprNode<Source>,
+        ty: Option<Node<Type, Source>>,
         rhs: ExprNode<Source>,
     },
     While {
         cond: ExprNode<Source>,
         body: Vec<StatementNode<Source>>,
     },
+    /// Runs the body, then repeats it until the condition is true.
+    Repeat {
+        body: Vec<StatementNode<Source>>,
+        cond: ExprNode<Source>,
+    },
     If {
         cond: ExprNode<Source>,
         pass: Vec<StatementNode<Source>>,
         fail: Vec<StatementNode<Source>>,
     },
+    /// Imports the top level values of the module at a relative path,
+    /// or only one of them if an import is given.
+    Use {
+        path: Node<String, Source>,
+        import: Option<Import<Source>>,
+    },
+    Commented {
+        comments: Comments<Source>,
+        statement: Box<StatementNode<Source>>,
+    },
+}
+
+/// Brings one value of a module into scope, optionally under another name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import<Source> {
+    pub name: Node<Symbol, Source>,
+    pub alias: Option<Node<Symbol, Source>>,
+}
+
+impl<Source> Import<Source> {
+    /// Returns the name the value is bound to in the importing scope.
+    pub fn binding(&self) -> &Node<Symbol, Source> {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
 }
 
 impl<Source: Clone> EvalNode<Source> for Statement<Source> {
@@ -42,14 +77,18 @@ impl<Source: Clone> EvalNode<Source> for Statement<Source> {
                     false => Ok(value),
                 }
             }
-            Statement::Assign { init, lhs, rhs } => {
+            Statement::Assign { init, lhs, ty, rhs } => {
                 match init {
-                    false => engine.assign(lhs, rhs)?,
-                    true => engine.init_assign(lhs, rhs)?,
+                    false => engine.assign(lhs, ty.as_ref(), rhs)?,
+                    true => engine.init_assign(lhs, ty.as_ref(), rhs)?,
                 }
 
                 Ok(Value::None)
             }
+            Statement::Const { lhs, ty, rhs } => {
+                engine.init_const(lhs, ty.as_ref(), rhs)?;
+                Ok(Value::None)
+            }
             Statement::While { cond, body } => {
                 let mut output = Value::None;
                 loop {
@@ -70,6 +109,26 @@ impl<Source: Clone> EvalNode<Source> for Statement<Source> {
                     }
                 }
             }
+            Statement::Repeat { body, cond } => {
+                let mut output = Value::None;
+                loop {
+                    for statement in body {
+                        output = engine.eval(statement)?;
+                    }
+
+                    match engine.eval(cond)? {
+                        Value::Bool(true) => break Ok(output),
+                        Value::Bool(false) => (),
+                        value => {
+                            break Err(EvalError::UnexpectedType {
+                                expect: ValueKind::Bool,
+                                found: value.kind(),
+                                source: cond.source.clone(),
+                            })
+                        }
+                    }
+                }
+            }
             Statement::If { cond, pass, fail } => {
                 let mut output = Value::None;
                 let statements = match engine.eval(cond)? {
@@ -90,6 +149,11 @@ impl<Source: Clone> EvalNode<Source> for Statement<Source> {
 
                 Ok(output)
             }
+            Statement::Use { path, import } => {
+                engine.use_module(path, import.as_ref())?;
+                Ok(Value::None)
+            }
+            Statement::Commented { statement, .. } => engine.eval(statement.as_ref()),
         }
     }
 }
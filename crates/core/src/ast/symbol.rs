@@ -0,0 +1,138 @@
+use std::{
+    fmt,
+    sync::{OnceLock, RwLock},
+};
+
+use fxhash::FxHashMap;
+
+/// An interned identifier.
+///
+/// Symbols are small handles that are cheap to copy and compare,
+/// so variable lookups never have to compare whole strings.
+/// The original name can be recovered with [`Symbol::resolve`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    symbols: FxHashMap<&'static str, Symbol>,
+    names: Vec<&'static str>,
+}
+
+/// Identifiers are interned for the whole process so that symbols from
+/// any parser or engine can be shared and resolved for diagnostics.
+///
+/// Only interning a new name needs exclusive access, so resolving
+/// and looking up symbols never wait on each other.
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        if let Some(symbol) = Self::lookup(name) {
+            return symbol;
+        }
+
+        // another thread may have interned the name while the lock was released
+        let mut interner = interner().write().unwrap();
+        if let Some(symbol) = interner.symbols.get(name) {
+            return *symbol;
+        }
+
+        // names are never freed, as the set of identifiers in a program is small
+        let name: &'static str = Box::leak(name.into());
+        let symbol = Symbol(interner.names.len() as u32);
+        interner.names.push(name);
+        interner.symbols.insert(name, symbol);
+        symbol
+    }
+
+    /// Gets the symbol of a name that has already been interned.
+    ///
+    /// Use this for queries, since a name that was never interned
+    /// cannot be the name of anything, and interning it would leak it.
+    pub fn lookup(name: &str) -> Option<Self> {
+        interner().read().unwrap().symbols.get(name).copied()
+    }
+
+    pub fn resolve(self) -> &'static str {
+        interner().read().unwrap().names[self.0 as usize]
+    }
+}
+
+/// A name that values can be looked up by.
+///
+/// Strings are only looked up with [`Symbol::lookup`], so asking about
+/// names that do not exist never grows the interner.
+pub trait SymbolQuery {
+    fn symbol(self) -> Option<Symbol>;
+}
+
+impl SymbolQuery for Symbol {
+    fn symbol(self) -> Option<Symbol> {
+        Some(self)
+    }
+}
+
+impl SymbolQuery for &str {
+    fn symbol(self) -> Option<Symbol> {
+        Symbol::lookup(self)
+    }
+}
+
+impl SymbolQuery for &String {
+    fn symbol(self) -> Option<Symbol> {
+        Symbol::lookup(self)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Self::intern(&name)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.resolve())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.resolve())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interned_names() {
+        let a = Symbol::intern("alpha");
+        assert_eq!(a, Symbol::from("alpha".to_string()));
+        assert_ne!(a, Symbol::intern("beta"));
+        assert_eq!(a.resolve(), "alpha");
+        assert_eq!(format!("{a} {a:?}"), "alpha \"alpha\"");
+    }
+
+    #[test]
+    fn lookups_do_not_intern() {
+        assert_eq!(Symbol::lookup("never interned"), None);
+        assert_eq!("never interned".symbol(), None);
+        assert_eq!(Symbol::lookup("never interned"), None);
+
+        let gamma = Symbol::intern("gamma");
+        assert_eq!(Symbol::lookup("gamma"), Some(gamma));
+        assert_eq!(gamma.symbol(), Some(gamma));
+    }
+}
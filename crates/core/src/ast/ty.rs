@@ -0,0 +1,63 @@
+use derive_more::Display;
+
+use crate::engine::value::ValueKind;
+
+/// A type that a binding can be annotated with.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    #[display(fmt = "none")]
+    None,
+    #[display(fmt = "bool")]
+    Bool,
+    #[display(fmt = "int")]
+    Int,
+    #[display(fmt = "float")]
+    Float,
+    #[display(fmt = "string")]
+    String,
+    #[display(fmt = "tuple")]
+    Tuple,
+    #[display(fmt = "list")]
+    List,
+    #[display(fmt = "map")]
+    Map,
+    #[display(fmt = "result")]
+    Result,
+    #[display(fmt = "fn")]
+    Func,
+}
+
+impl Type {
+    /// Parses a type from its name.
+    ///
+    /// The `none` and `fn` types are keywords and are not parsed from names.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bool" => Some(Self::Bool),
+            "int" => Some(Self::Int),
+            "float" => Some(Self::Float),
+            "string" => Some(Self::String),
+            "tuple" => Some(Self::Tuple),
+            "list" => Some(Self::List),
+            "map" => Some(Self::Map),
+            "result" => Some(Self::Result),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, kind: &ValueKind) -> bool {
+        matches!(
+            (self, kind),
+            (Self::None, ValueKind::None)
+                | (Self::Bool, ValueKind::Bool)
+                | (Self::Int, ValueKind::Int)
+                | (Self::Float, ValueKind::Float)
+                | (Self::String, ValueKind::String)
+                | (Self::Tuple, ValueKind::Tuple(_))
+                | (Self::List, ValueKind::List)
+                | (Self::Map, ValueKind::Map)
+                | (Self::Result, ValueKind::Result)
+                | (Self::Func, ValueKind::Func(_))
+        )
+    }
+}
@@ -0,0 +1,10 @@
+use derive_more::Display;
+
+#[derive(Debug, Display, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    #[default]
+    #[display(fmt = "priv")]
+    Private,
+    #[display(fmt = "pub")]
+    Public,
+}
@@ -1,13 +1,1421 @@
+use std::{cmp::Ordering, env, fs, time::SystemTime};
+
+use dashu::integer::IBig;
+use unicode_segmentation::UnicodeSegmentation;
+
 use crate::Engine;
 
-use super::{value::FuncPtr, Value};
+use super::{
+    prelude::Prelude,
+    value::{func::NativeFn, FuncPtr, Map, MapKey, ValueResult},
+    Capability, Value,
+};
 
-pub fn load_into<Source>(engine: &mut Engine<Source>) {
-    engine.vars_mut().init_global(
+/// Assembles the standard prelude, grouping every builtin into a module.
+pub fn prelude<Source>() -> Prelude<Source> {
+    let mut prelude = Prelude::new();
+
+    // CORE
+    prelude.insert(
+        "core",
         "print",
         Value::Func(FuncPtr::native(1, |values| {
             println!("{}", values[0]);
             Ok(Value::None)
         })),
     );
+    prelude.insert(
+        "core",
+        "format",
+        Value::Func(FuncPtr::native_variadic(1, format)),
+    );
+    prelude.insert(
+        "core",
+        "dbg",
+        Value::Func(FuncPtr::native_source(1, |engine, source, mut values| {
+            let value = values.remove(0);
+            engine.debug(source, &value);
+            Ok(value)
+        })),
+    );
+    // collections have value semantics, so every value passed to a function
+    // is already independent of its variable and both copies are the identity
+    prelude.insert(
+        "core",
+        "copy",
+        Value::Func(FuncPtr::native(1, |mut values| Ok(values.remove(0)))),
+    );
+    prelude.insert(
+        "core",
+        "deepcopy",
+        Value::Func(FuncPtr::native(1, |mut values| Ok(values.remove(0)))),
+    );
+    prelude.insert(
+        "core",
+        "repr",
+        Value::Func(FuncPtr::native(1, |values| {
+            Ok(Value::String(values[0].repr()))
+        })),
+    );
+    prelude.insert(
+        "core",
+        "hash",
+        Value::Func(FuncPtr::native(1, |values| match values[0].hash() {
+            Some(hash) => Ok(Value::Int(hash.into())),
+            None => Err(format!("'{}' is not hashable", values[0].kind())),
+        })),
+    );
+    prelude.insert(
+        "core",
+        "assert_eq",
+        Value::Func(FuncPtr::native(2, assert_eq)),
+    );
+    prelude.insert(
+        "core",
+        "panic",
+        Value::Func(FuncPtr::native(1, |values| Err(values[0].to_string()))),
+    );
+
+    // STRINGS
+    prelude.insert("string", "join", Value::Func(FuncPtr::native(2, join)));
+    prelude.insert("string", "chars", Value::Func(FuncPtr::native(1, chars)));
+    prelude.insert(
+        "string",
+        "starts_with",
+        Value::Func(FuncPtr::native(2, starts_with)),
+    );
+    prelude.insert(
+        "string",
+        "ends_with",
+        Value::Func(FuncPtr::native(2, ends_with)),
+    );
+    prelude.insert(
+        "string",
+        "replace",
+        Value::Func(FuncPtr::native(3, replace)),
+    );
+    prelude.insert(
+        "string",
+        "to_fixed",
+        Value::Func(FuncPtr::native(2, to_fixed)),
+    );
+    prelude.insert(
+        "string",
+        "to_radix",
+        Value::Func(FuncPtr::native(2, to_radix)),
+    );
+
+    // MATH
+    prelude.insert("math", "clamp", Value::Func(FuncPtr::native(3, clamp)));
+    prelude.insert("math", "lerp", Value::Func(FuncPtr::native(3, lerp)));
+
+    // fixed width integer ops take an optional bit width as a third param
+    let fixed: [(&str, NativeFn<Source>); 9] = [
+        ("checked_add", |v| {
+            fixed("checked_add", v, Overflow::Checked, add)
+        }),
+        ("checked_sub", |v| {
+            fixed("checked_sub", v, Overflow::Checked, sub)
+        }),
+        ("checked_mul", |v| {
+            fixed("checked_mul", v, Overflow::Checked, mul)
+        }),
+        ("wrapping_add", |v| {
+            fixed("wrapping_add", v, Overflow::Wrapping, add)
+        }),
+        ("wrapping_sub", |v| {
+            fixed("wrapping_sub", v, Overflow::Wrapping, sub)
+        }),
+        ("wrapping_mul", |v| {
+            fixed("wrapping_mul", v, Overflow::Wrapping, mul)
+        }),
+        ("saturating_add", |v| {
+            fixed("saturating_add", v, Overflow::Saturating, add)
+        }),
+        ("saturating_sub", |v| {
+            fixed("saturating_sub", v, Overflow::Saturating, sub)
+        }),
+        ("saturating_mul", |v| {
+            fixed("saturating_mul", v, Overflow::Saturating, mul)
+        }),
+    ];
+    for (name, native) in fixed {
+        let func = FuncPtr::native_variadic(2, native);
+        prelude.insert("math", name, Value::Func(func));
+    }
+
+    // COLLECTIONS
+    prelude.insert(
+        "collections",
+        "contains",
+        Value::Func(FuncPtr::native(2, contains)),
+    );
+    prelude.insert("collections", "keys", Value::Func(FuncPtr::native(1, keys)));
+    prelude.insert(
+        "collections",
+        "values",
+        Value::Func(FuncPtr::native(1, values)),
+    );
+    prelude.insert(
+        "collections",
+        "items",
+        Value::Func(FuncPtr::native(1, items)),
+    );
+    prelude.insert(
+        "collections",
+        "set_key",
+        Value::Func(FuncPtr::native_mut(3, set_key)),
+    );
+    prelude.insert(
+        "collections",
+        "remove",
+        Value::Func(FuncPtr::native_mut(2, remove)),
+    );
+    prelude.insert(
+        "collections",
+        "push",
+        Value::Func(FuncPtr::native_mut(2, push)),
+    );
+    prelude.insert(
+        "collections",
+        "pop",
+        Value::Func(FuncPtr::native_mut(1, pop)),
+    );
+
+    // RESULTS
+    prelude.insert(
+        "result",
+        "ok",
+        Value::Func(FuncPtr::native(1, |mut values| {
+            Ok(Value::Result(Box::new(Ok(values.remove(0)))))
+        })),
+    );
+    prelude.insert(
+        "result",
+        "err",
+        Value::Func(FuncPtr::native(1, |mut values| {
+            Ok(Value::Result(Box::new(Err(values.remove(0)))))
+        })),
+    );
+    prelude.insert(
+        "result",
+        "is_ok",
+        Value::Func(FuncPtr::native(1, |values| {
+            let [result] = results("is_ok", values)?;
+            Ok(Value::Bool(result.is_ok()))
+        })),
+    );
+    prelude.insert(
+        "result",
+        "is_err",
+        Value::Func(FuncPtr::native(1, |values| {
+            let [result] = results("is_err", values)?;
+            Ok(Value::Bool(result.is_err()))
+        })),
+    );
+    prelude.insert("result", "unwrap", Value::Func(FuncPtr::native(1, unwrap)));
+    prelude.insert(
+        "result",
+        "unwrap_or",
+        Value::Func(FuncPtr::native(2, unwrap_or)),
+    );
+
+    // JSON
+    prelude.insert(
+        "json",
+        "parse_json",
+        Value::Func(FuncPtr::native(1, parse_json)),
+    );
+    prelude.insert("json", "to_json", Value::Func(FuncPtr::native(1, to_json)));
+
+    // RANDOM
+    prelude.insert(
+        "random",
+        "random",
+        Value::Func(FuncPtr::native_capability(0, Capability::Random, random)),
+    );
+    prelude.insert(
+        "random",
+        "randint",
+        Value::Func(FuncPtr::native_capability(2, Capability::Random, randint)),
+    );
+    prelude.insert(
+        "random",
+        "seed",
+        Value::Func(FuncPtr::native_capability(1, Capability::Random, seed)),
+    );
+
+    // TIME
+    prelude.insert(
+        "time",
+        "now",
+        Value::Func(FuncPtr::native_capability(0, Capability::Time, now)),
+    );
+    prelude.insert(
+        "time",
+        "clock",
+        Value::Func(FuncPtr::native_capability(0, Capability::Time, clock)),
+    );
+
+    // ENVIRONMENT
+    prelude.insert(
+        "env",
+        "env",
+        Value::Func(FuncPtr::native_capability(1, Capability::Env, env_var)),
+    );
+
+    // FILES
+    prelude.insert(
+        "io",
+        "read_file",
+        Value::Func(FuncPtr::native_capability(1, Capability::Io, read_file)),
+    );
+    prelude.insert(
+        "io",
+        "write_file",
+        Value::Func(FuncPtr::native_capability(2, Capability::Io, write_file)),
+    );
+
+    prelude
+}
+
+/// Unpacks a fixed number of string params, erroring on any other type.
+fn strings<const N: usize, Source>(
+    name: &str,
+    values: Vec<Value<Source>>,
+) -> Result<[String; N], String> {
+    let mut strings = Vec::with_capacity(N);
+    for value in values {
+        match value {
+            Value::String(s) => strings.push(s),
+            value => return Err(format!("{name} expects strings, found {}", value.kind())),
+        }
+    }
+
+    match strings.try_into() {
+        Ok(strings) => Ok(strings),
+        Err(_) => unreachable!("{name} is registered with {N} params"),
+    }
+}
+
+/// Unpacks a fixed number of int or float params, erroring on any other type.
+fn numbers<const N: usize, Source>(
+    name: &str,
+    values: Vec<Value<Source>>,
+) -> Result<[Value<Source>; N], String> {
+    if let Some(value) = values
+        .iter()
+        .find(|value| !matches!(value, Value::Int(_) | Value::Float(_)))
+    {
+        return Err(format!("{name} expects numbers, found {}", value.kind()));
+    }
+
+    match values.try_into() {
+        Ok(numbers) => Ok(numbers),
+        Err(_) => unreachable!("{name} is registered with {N} params"),
+    }
+}
+
+fn to_float<Source>(value: &Value<Source>) -> f64 {
+    match value {
+        Value::Int(int) => int.to_f64().value(),
+        Value::Float(float) => *float,
+        _ => unreachable!("only numbers are converted to floats"),
+    }
+}
+
+/// Compares two numbers, using a total ordering for floats.
+fn compare<Source>(v1: &Value<Source>, v2: &Value<Source>) -> Ordering {
+    match (v1, v2) {
+        (Value::Int(v1), Value::Int(v2)) => v1.cmp(v2),
+        _ => to_float(v1).total_cmp(&to_float(v2)),
+    }
+}
+
+/// Bounds a number to the range from `lo` to `hi`.
+///
+/// The output is a float if any of the params are floats.
+fn clamp<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let [x, lo, hi] = numbers("clamp", values)?;
+    if compare(&lo, &hi).is_gt() {
+        return Err(format!("clamp expects lo <= hi, found {lo} > {hi}"));
+    }
+
+    let float = [&x, &lo, &hi].iter().any(|v| matches!(v, Value::Float(_)));
+    let output = match (compare(&x, &lo), compare(&x, &hi)) {
+        (Ordering::Less, _) => lo,
+        (_, Ordering::Greater) => hi,
+        _ => x,
+    };
+
+    match float {
+        true => Ok(Value::Float(to_float(&output))),
+        false => Ok(output),
+    }
+}
+
+/// Linearly interpolates from `a` to `b` by `t` as floats.
+fn lerp<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let [a, b, t] = numbers("lerp", values)?.map(|v| to_float(&v));
+    Ok(Value::Float(a + (b - a) * t))
+}
+
+/// Formats a number with exactly `digits` decimal places.
+///
+/// Floats are rounded to the nearest decimal, with exact ties rounding to even.
+fn to_fixed<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let digits = match &values[1] {
+        Value::Int(digits) => usize::try_from(digits).ok().filter(|digits| *digits <= 100),
+        value => {
+            return Err(format!(
+                "to_fixed expects int digits, found {}",
+                value.kind()
+            ))
+        }
+    };
+
+    let Some(digits) = digits else {
+        return Err(format!(
+            "to_fixed expects 0 to 100 digits, found {}",
+            values[1]
+        ));
+    };
+
+    match &values[0] {
+        Value::Int(int) if digits == 0 => Ok(Value::String(int.to_string())),
+        Value::Int(int) => Ok(Value::String(format!("{int}.{}", "0".repeat(digits)))),
+        Value::Float(float) => Ok(Value::String(format!("{float:.digits$}"))),
+        value => Err(format!("to_fixed expects a number, found {}", value.kind())),
+    }
+}
+
+/// Formats an int in a base from 2 to 36, using lowercase letters for digits above 9.
+fn to_radix<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let (int, base) = match (&values[0], &values[1]) {
+        (Value::Int(int), Value::Int(base)) => (int, base),
+        (Value::Int(_), value) | (value, _) => {
+            return Err(format!("to_radix expects ints, found {}", value.kind()))
+        }
+    };
+
+    match u32::try_from(base) {
+        Ok(base @ 2..=36) => Ok(Value::String(int.in_radix(base).to_string())),
+        _ => Err(format!(
+            "to_radix expects a base from 2 to 36, found {base}"
+        )),
+    }
+}
+
+/// Replaces each `{}` in the template with the next argument.
+///
+/// `{{` and `}}` are escapes for literal braces.
+fn format<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let mut values = values.into_iter();
+    let template = match values.next() {
+        Some(Value::String(template)) => template,
+        Some(value) => {
+            return Err(format!(
+                "format expects a string template, found {}",
+                value.kind()
+            ))
+        }
+        None => return Err("format expects a template".to_string()),
+    };
+
+    let args = values.len();
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                output.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                match values.next() {
+                    Some(value) => output.push_str(&value.to_string()),
+                    None => return Err(format!("format template has more placeholders than the {args} argument(s) provided")),
+                }
+            }
+            ('{', _) => return Err("unclosed '{' in format template".to_string()),
+            ('}', _) => return Err("unmatched '}' in format template".to_string()),
+            _ => output.push(c),
+        }
+    }
+
+    match values.len() {
+        0 => Ok(Value::String(output)),
+        unused => Err(format!(
+            "format received {unused} more argument(s) than the template uses"
+        )),
+    }
+}
+
+/// Concatenates a tuple of strings with a separator between each one.
+fn join<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let (sep, seq) = match <[_; 2]>::try_from(values) {
+        Ok([Value::String(sep), Value::Tuple(seq)]) => (sep, seq),
+        Ok([Value::String(_), value]) => {
+            return Err(format!("join expects a tuple, found {}", value.kind()))
+        }
+        Ok([value, _]) => return Err(format!("join expects a string, found {}", value.kind())),
+        Err(_) => unreachable!("join is registered with 2 params"),
+    };
+
+    let mut items = Vec::with_capacity(seq.len());
+    for value in seq.iter() {
+        match value {
+            Value::String(item) => items.push(item.as_str()),
+            value => return Err(format!("join expects strings, found {}", value.kind())),
+        }
+    }
+
+    Ok(Value::String(items.join(&sep)))
+}
+
+/// Splits a string into a tuple of its graphemes.
+fn chars<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    match &values[0] {
+        Value::String(s) => Ok(Value::Tuple(
+            s.graphemes(true)
+                .map(|g| Value::String(g.to_string()))
+                .collect(),
+        )),
+        value => Err(format!("chars expects a string, found {}", value.kind())),
+    }
+}
+
+/// Checks for a substring in a string, an item in a tuple or list, or a key in a map.
+fn contains<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let item = &values[1];
+    match &values[0] {
+        Value::Tuple(items) => Ok(Value::Bool(items.iter().any(|v| v.total_eq(item)))),
+        Value::List(items) => Ok(Value::Bool(items.iter().any(|v| v.total_eq(item)))),
+        Value::Map(map) => Ok(Value::Bool(map.get(item).is_some())),
+        _ => {
+            let [s, sub] = strings("contains", values)?;
+            Ok(Value::Bool(s.contains(&sub)))
+        }
+    }
+}
+
+fn starts_with<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let [s, prefix] = strings("starts_with", values)?;
+    Ok(Value::Bool(s.starts_with(&prefix)))
+}
+
+fn ends_with<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let [s, suffix] = strings("ends_with", values)?;
+    Ok(Value::Bool(s.ends_with(&suffix)))
+}
+
+fn replace<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let [s, from, to] = strings("replace", values)?;
+    match from.is_empty() {
+        true => Err("replace cannot replace an empty string".to_string()),
+        false => Ok(Value::String(s.replace(&from, &to))),
+    }
+}
+
+/// Errors if the found value (first) is not equal to the expected value (second).
+fn assert_eq<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let (found, expect) = (&values[0], &values[1]);
+    match found.total_eq(expect) {
+        true => Ok(Value::None),
+        false => Err(format!(
+            "assertion failed: expected {expect} ({}), found {found} ({})",
+            expect.kind(),
+            found.kind()
+        )),
+    }
+}
+
+/// Returns a random float in the range `[0, 1)`.
+fn random<Source>(
+    engine: &mut Engine<Source>,
+    _: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    Ok(Value::Float(engine.rng_mut().next_float()))
+}
+
+/// Returns a random int in the range from `lo` to `hi` inclusive.
+fn randint<Source>(
+    engine: &mut Engine<Source>,
+    values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let (lo, hi) = match (&values[0], &values[1]) {
+        (Value::Int(lo), Value::Int(hi)) => (lo, hi),
+        (Value::Int(_), value) | (value, _) => {
+            return Err(format!("randint expects ints, found {}", value.kind()))
+        }
+    };
+
+    if lo > hi {
+        return Err(format!("randint expects lo <= hi, found {lo} > {hi}"));
+    }
+
+    let span = hi - lo + IBig::ONE;
+    match u64::try_from(&span) {
+        Ok(span) => Ok(Value::Int(lo + engine.rng_mut().below(span))),
+        Err(_) => Err(format!("randint range of {span} values is too large")),
+    }
+}
+
+/// Seeds the engine random number generator so runs are reproducible.
+///
+/// Ints outside the `u64` range wrap around.
+fn seed<Source>(
+    engine: &mut Engine<Source>,
+    values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let Value::Int(int) = &values[0] else {
+        return Err(format!("seed expects an int, found {}", values[0].kind()));
+    };
+
+    let modulus = IBig::ONE << 64;
+    let mut seed = int % &modulus;
+    if seed < IBig::ZERO {
+        seed += modulus;
+    }
+
+    match u64::try_from(&seed) {
+        Ok(seed) => engine.seed_rng(seed),
+        Err(_) => unreachable!("seeds are wrapped into the u64 range"),
+    }
+    Ok(Value::None)
+}
+
+/// Returns the seconds since the unix epoch as a float.
+fn now<Source>(_: &mut Engine<Source>, _: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => Ok(Value::Float(duration.as_secs_f64())),
+        Err(error) => Ok(Value::Float(-error.duration().as_secs_f64())),
+    }
+}
+
+/// Returns the monotonic seconds since the engine was created as a float.
+fn clock<Source>(
+    engine: &mut Engine<Source>,
+    _: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    Ok(Value::Float(engine.start_instant().elapsed().as_secs_f64()))
+}
+
+/// Reads an environment variable, returning `none` if it is unset or not valid unicode.
+fn env_var<Source>(
+    _: &mut Engine<Source>,
+    values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let Value::String(name) = &values[0] else {
+        return Err(format!("env expects a string, found {}", values[0].kind()));
+    };
+
+    // names the platform cannot look up are never set
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Ok(Value::None);
+    }
+
+    match env::var_os(name).map(|value| value.into_string()) {
+        Some(Ok(value)) => Ok(Value::String(value)),
+        Some(Err(_)) | None => Ok(Value::None),
+    }
+}
+
+/// Reads the whole file at a path into a string.
+fn read_file<Source>(
+    _: &mut Engine<Source>,
+    values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let [path] = strings("read_file", values)?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(Value::String(contents)),
+        Err(error) => Err(format!("failed to read '{path}': {error}")),
+    }
+}
+
+/// Writes a string to the file at a path, replacing any existing contents.
+fn write_file<Source>(
+    _: &mut Engine<Source>,
+    values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let [path, contents] = strings("write_file", values)?;
+    match fs::write(&path, contents) {
+        Ok(()) => Ok(Value::None),
+        Err(error) => Err(format!("failed to write '{path}': {error}")),
+    }
+}
+
+/// Parses a json string into maps, lists, strings, numbers, bools and none.
+///
+/// Returns an `err` result with a message if the text is not valid json.
+fn parse_json<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let [text] = strings("parse_json", values)?;
+    let result = match serde_json::from_str(&text) {
+        Ok(json) => Ok(from_json(json)),
+        Err(error) => Err(Value::String(format!("invalid json: {error}"))),
+    };
+    Ok(Value::Result(Box::new(result)))
+}
+
+/// Unpacks the map param of `name`, erroring on any other type.
+fn map<Source>(name: &str, mut values: Vec<Value<Source>>) -> Result<Map<Source>, String> {
+    match values.remove(0) {
+        Value::Map(map) => Ok(map),
+        value => Err(format!("{name} expects a map, found {}", value.kind())),
+    }
+}
+
+/// Lists the keys of a map in insertion order.
+fn keys<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let map = map("keys", values)?;
+    Ok(Value::List(map.into_iter().map(|(k, _)| k).collect()))
+}
+
+/// Lists the values of a map in insertion order.
+fn values<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let map = map("values", values)?;
+    Ok(Value::List(map.into_iter().map(|(_, v)| v).collect()))
+}
+
+/// Lists the `(key, value)` entries of a map in insertion order.
+fn items<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let map = map("items", values)?;
+    let items = map
+        .into_iter()
+        .map(|(k, v)| Value::Tuple([k, v].into_iter().collect()));
+    Ok(Value::List(items.collect()))
+}
+
+// Collections have value semantics, so the mutating builtins below
+// update the variable passed as their first param and no other copies.
+
+/// Sets the value of a key in a map, keeping the position of existing keys.
+fn set_key<Source>(
+    target: &mut Value<Source>,
+    mut values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let Value::Map(map) = target else {
+        return Err(format!("set_key expects a map, found {}", target.kind()));
+    };
+
+    let value = values.pop().expect("set_key has three params");
+    match MapKey::new(values.remove(0)) {
+        Ok(key) => map.insert(key, value),
+        Err(key) => return Err(format!("{} cannot be used as a map key", key.kind())),
+    };
+    Ok(Value::None)
+}
+
+/// Removes a key from a map, returning its value.
+fn remove<Source>(
+    target: &mut Value<Source>,
+    values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let Value::Map(map) = target else {
+        return Err(format!("remove expects a map, found {}", target.kind()));
+    };
+
+    match map.remove(&values[0]) {
+        Some(value) => Ok(value),
+        None => Err(format!("remove could not find key {}", values[0].repr())),
+    }
+}
+
+/// Appends a value to the end of a list.
+fn push<Source>(
+    target: &mut Value<Source>,
+    mut values: Vec<Value<Source>>,
+) -> Result<Value<Source>, String> {
+    let Value::List(list) = target else {
+        return Err(format!("push expects a list, found {}", target.kind()));
+    };
+
+    list.push(values.remove(0));
+    Ok(Value::None)
+}
+
+/// Removes and returns the last value of a list.
+fn pop<Source>(target: &mut Value<Source>, _: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let Value::List(list) = target else {
+        return Err(format!("pop expects a list, found {}", target.kind()));
+    };
+
+    list.pop()
+        .ok_or_else(|| "pop expects a non empty list".to_string())
+}
+
+/// Unpacks the result params of `name`, erroring on any other type.
+fn results<const N: usize, Source>(
+    name: &str,
+    values: Vec<Value<Source>>,
+) -> Result<[ValueResult<Source>; N], String> {
+    let mut results = Vec::with_capacity(N);
+    for value in values {
+        match value {
+            Value::Result(result) => results.push(*result),
+            value => return Err(format!("{name} expects a result, found {}", value.kind())),
+        }
+    }
+
+    match results.try_into() {
+        Ok(results) => Ok(results),
+        Err(_) => unreachable!("natives are only called with their param count"),
+    }
+}
+
+/// Returns the value of an `ok` result.
+///
+/// Unwrapping an `err` fails with its value, which a `try` expression can catch.
+fn unwrap<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    match results("unwrap", values)? {
+        [Ok(value)] => Ok(value),
+        [Err(value)] => Err(format!("called unwrap on err({value})")),
+    }
+}
+
+/// Returns the value of an `ok` result, or the default for an `err`.
+fn unwrap_or<Source>(mut values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let default = values.pop().expect("unwrap_or has two params");
+    match results("unwrap_or", values)? {
+        [Ok(value)] => Ok(value),
+        [Err(_)] => Ok(default),
+    }
+}
+
+/// Encodes a value as a compact json string.
+fn to_json<Source>(values: Vec<Value<Source>>) -> Result<Value<Source>, String> {
+    let json = into_json(&values[0])?;
+    Ok(Value::String(json.to_string()))
+}
+
+fn from_json<Source>(json: serde_json::Value) -> Value<Source> {
+    match json {
+        serde_json::Value::Null => Value::None,
+        serde_json::Value::Bool(v) => Value::Bool(v),
+        serde_json::Value::Number(v) => {
+            // numbers keep their source text, so big ints are not truncated
+            let text = v.to_string();
+            match text.parse::<IBig>() {
+                Ok(int) => Value::Int(int),
+                Err(_) => Value::Float(text.parse().unwrap_or(f64::NAN)),
+            }
+        }
+        serde_json::Value::String(v) => Value::String(v),
+        serde_json::Value::Array(items) => Value::List(items.into_iter().map(from_json).collect()),
+        serde_json::Value::Object(entries) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| match MapKey::new(Value::String(k)) {
+                    Ok(key) => (key, from_json(v)),
+                    Err(_) => unreachable!("strings are hashable"),
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn into_json<Source>(value: &Value<Source>) -> Result<serde_json::Value, String> {
+    Ok(match value {
+        Value::None => serde_json::Value::Null,
+        Value::Bool(v) => serde_json::Value::Bool(*v),
+        Value::Int(v) => match v.to_string().parse() {
+            Ok(number) => serde_json::Value::Number(number),
+            Err(_) => unreachable!("ints are valid json numbers"),
+        },
+        Value::Float(v) => match serde_json::Number::from_f64(*v) {
+            Some(number) => serde_json::Value::Number(number),
+            None => return Err(format!("to_json cannot encode the float '{v}'")),
+        },
+        Value::String(v) => serde_json::Value::String(v.clone()),
+        Value::Tuple(items) => items.iter().map(into_json).collect::<Result<_, _>>()?,
+        Value::List(items) => items.iter().map(into_json).collect::<Result<_, _>>()?,
+        Value::Map(map) => {
+            let mut entries = serde_json::Map::with_capacity(map.len());
+            for (key, value) in map.iter() {
+                let Value::String(key) = key else {
+                    return Err(format!("to_json expects string keys, found {}", key.kind()));
+                };
+                entries.insert(key.clone(), into_json(value)?);
+            }
+            serde_json::Value::Object(entries)
+        }
+        Value::Result(_) | Value::Func(_) => {
+            return Err(format!("to_json cannot encode {}", value.kind()))
+        }
+    })
+}
+
+/// How a fixed width integer op handles results that do not fit.
+#[derive(Clone, Copy)]
+enum Overflow {
+    /// Produces `none`.
+    Checked,
+    /// Wraps around using two's complement.
+    Wrapping,
+    /// Clamps to the nearest bound.
+    Saturating,
+}
+
+const DEFAULT_WIDTH: usize = 64;
+const MAX_WIDTH: usize = 4096;
+
+fn add(a: &IBig, b: &IBig) -> IBig {
+    a + b
+}
+
+fn sub(a: &IBig, b: &IBig) -> IBig {
+    a - b
+}
+
+fn mul(a: &IBig, b: &IBig) -> IBig {
+    a * b
+}
+
+/// Applies an op to two ints as if they were signed ints with a fixed bit width.
+///
+/// The width is an optional third param that defaults to 64 bits.
+/// Both ints must already fit within the width.
+fn fixed<Source>(
+    name: &str,
+    values: Vec<Value<Source>>,
+    overflow: Overflow,
+    op: fn(&IBig, &IBig) -> IBig,
+) -> Result<Value<Source>, String> {
+    let bits = match values.get(2) {
+        _ if values.len() > 3 => {
+            return Err(format!(
+                "{name} expects 2 or 3 params, found {}",
+                values.len()
+            ))
+        }
+        None => DEFAULT_WIDTH,
+        Some(Value::Int(bits)) => match usize::try_from(bits) {
+            Ok(bits) if (1..=MAX_WIDTH).contains(&bits) => bits,
+            _ => {
+                return Err(format!(
+                    "{name} expects a bit width from 1 to {MAX_WIDTH}, found {bits}"
+                ))
+            }
+        },
+        Some(value) => {
+            return Err(format!(
+                "{name} expects an int width, found {}",
+                value.kind()
+            ))
+        }
+    };
+
+    let (a, b) = match (&values[0], &values[1]) {
+        (Value::Int(a), Value::Int(b)) => (a, b),
+        (Value::Int(_), value) | (value, _) => {
+            return Err(format!("{name} expects ints, found {}", value.kind()))
+        }
+    };
+
+    let max = (IBig::ONE << (bits - 1)) - IBig::ONE;
+    let min = -(IBig::ONE << (bits - 1));
+    for int in [a, b] {
+        if *int < min || *int > max {
+            return Err(format!("{name} expects {bits} bit ints, found {int}"));
+        }
+    }
+
+    let output = op(a, b);
+    if min <= output && output <= max {
+        return Ok(Value::Int(output));
+    }
+
+    match overflow {
+        Overflow::Checked => Ok(Value::None),
+        Overflow::Saturating if output < min => Ok(Value::Int(min)),
+        Overflow::Saturating => Ok(Value::Int(max)),
+        Overflow::Wrapping => {
+            let modulus = IBig::ONE << bits;
+            let mut offset = (output - &min) % &modulus;
+            if offset < IBig::ZERO {
+                offset += modulus;
+            }
+            Ok(Value::Int(offset + min))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{Capabilities, EvalError};
+
+    use super::*;
+
+    fn string(s: &str) -> Value<()> {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn format_placeholders() {
+        let values = vec![
+            string("{} + {} = {}"),
+            Value::Int(1.into()),
+            Value::Float(2.5),
+            string("3.5"),
+        ];
+        assert_eq!(format(values), Ok(string("1 + 2.5 = 3.5")));
+        assert_eq!(
+            format(vec![string("{{}} {{{}}}"), Value::Bool(true)]),
+            Ok(string("{} {true}"))
+        );
+    }
+
+    #[test]
+    fn format_argument_mismatch() {
+        assert!(format(vec![string("{} {}"), Value::None]).is_err());
+        assert!(format(vec![string("{}"), Value::None, Value::None]).is_err());
+        assert!(format(vec![string("{")]).is_err());
+        assert!(format(vec![Value::<()>::None]).is_err());
+    }
+
+    #[test]
+    fn join_and_chars() {
+        let seq = Value::Tuple(
+            [string("a"), string("b"), string("c")]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(join(vec![string(", "), seq]), Ok(string("a, b, c")));
+
+        let mixed = Value::Tuple([string("a"), Value::Int(1.into())].into_iter().collect());
+        assert!(join(vec![string(""), mixed]).is_err());
+
+        let expect = [string("e\u{301}"), string("🐱"), string("!")];
+        let expect = Value::Tuple(expect.into_iter().collect());
+        assert_eq!(chars(vec![string("e\u{301}🐱!")]), Ok(expect));
+    }
+
+    #[test]
+    fn string_predicates() {
+        let check = |f: NativeFn<()>, s: &str, sub: &str| f(vec![string(s), string(sub)]);
+        assert_eq!(
+            check(contains, "naïve café", "ve ca"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(check(contains, "naïve", "naive"), Ok(Value::Bool(false)));
+        assert_eq!(check(starts_with, "🐱cat", "🐱"), Ok(Value::Bool(true)));
+        assert_eq!(check(ends_with, "日本語", "本語"), Ok(Value::Bool(true)));
+        assert_eq!(check(ends_with, "日本語", "日本"), Ok(Value::Bool(false)));
+        assert!(contains(vec![string("1"), Value::Int(1.into())]).is_err());
+    }
+
+    #[test]
+    fn replace_all() {
+        let replaced = replace(vec![string("aaa"), string("aa"), string("b")]);
+        assert_eq!(replaced, Ok(string("ba")));
+        assert!(replace(vec![string("abc"), string(""), string("-")]).is_err());
+    }
+
+    #[test]
+    fn assert_equal_values() {
+        assert_eq!(assert_eq(vec![string("a"), string("a")]), Ok(Value::None));
+        let nan = Value::<()>::Float(f64::NAN);
+        assert_eq!(assert_eq(vec![nan.clone(), nan]), Ok(Value::None));
+
+        let failed = assert_eq(vec![Value::<()>::Int(1.into()), Value::Float(1.)]);
+        let expect = "assertion failed: expected 1 (float), found 1 (int)";
+        assert_eq!(failed, Err(expect.to_string()));
+    }
+    #[test]
+    fn fixed_width_ints() {
+        let int = |i: i64| Value::<()>::Int(i.into());
+        let op = |overflow, op, a, b| fixed("op", vec![int(a), int(b), int(8)], overflow, op);
+
+        assert_eq!(op(Overflow::Checked, add, 100, 27), Ok(int(127)));
+        assert_eq!(op(Overflow::Checked, add, 100, 28), Ok(Value::None));
+        assert_eq!(op(Overflow::Wrapping, add, 127, 1), Ok(int(-128)));
+        assert_eq!(op(Overflow::Wrapping, sub, -128, 1), Ok(int(127)));
+        assert_eq!(op(Overflow::Wrapping, mul, 16, 16), Ok(int(0)));
+        assert_eq!(op(Overflow::Saturating, mul, -16, 16), Ok(int(-128)));
+        assert_eq!(op(Overflow::Saturating, sub, 100, -100), Ok(int(127)));
+
+        // the width defaults to 64 bits
+        let max = int(i64::MAX);
+        let wrapped = fixed("op", vec![max, int(1)], Overflow::Wrapping, add);
+        assert_eq!(wrapped, Ok(int(i64::MIN)));
+
+        // ints must fit the width and widths must be positive
+        assert!(op(Overflow::Checked, add, 128, 0).is_err());
+        assert!(fixed("op", vec![int(1), int(1), int(0)], Overflow::Checked, add).is_err());
+        assert!(fixed("op", vec![int(1), Value::Float(1.)], Overflow::Checked, add).is_err());
+    }
+    #[test]
+    fn clamp_and_lerp() {
+        let int = |i: i32| Value::<()>::Int(i.into());
+        let float = |f: f64| Value::<()>::Float(f);
+
+        assert_eq!(clamp(vec![int(5), int(0), int(3)]), Ok(int(3)));
+        assert_eq!(clamp(vec![int(-5), int(0), int(3)]), Ok(int(0)));
+        assert_eq!(clamp(vec![int(2), int(0), int(3)]), Ok(int(2)));
+        assert_eq!(clamp(vec![int(2), float(0.), int(3)]), Ok(float(2.)));
+        assert_eq!(clamp(vec![float(0.5), int(1), int(3)]), Ok(float(1.)));
+        assert!(clamp(vec![int(2), int(3), int(0)]).is_err());
+        assert!(clamp(vec![string("2"), int(0), int(3)]).is_err());
+
+        assert_eq!(lerp(vec![int(0), int(10), float(0.25)]), Ok(float(2.5)));
+        assert_eq!(lerp(vec![float(1.), int(-1), int(1)]), Ok(float(-1.)));
+        assert!(lerp(vec![int(0), int(1), Value::None]).is_err());
+    }
+    #[test]
+    fn seeded_random() {
+        let mut engine = Engine::<()>::new();
+        let sample = |engine: &mut Engine<()>| {
+            seed(engine, vec![Value::Int((-1).into())]).unwrap();
+            let float = random(engine, vec![]).unwrap();
+            let int = randint(engine, vec![Value::Int(1.into()), Value::Int(6.into())]).unwrap();
+            (float, int)
+        };
+
+        let first = sample(&mut engine);
+        assert_eq!(sample(&mut engine), first);
+        assert!(matches!(first.0, Value::Float(f) if (0. ..1.).contains(&f)));
+        let dice = IBig::ONE..=IBig::from(6);
+        assert!(matches!(first.1, Value::Int(i) if dice.contains(&i)));
+
+        let one = randint(
+            &mut engine,
+            vec![Value::Int(3.into()), Value::Int(3.into())],
+        );
+        assert_eq!(one, Ok(Value::Int(3.into())));
+        assert!(randint(
+            &mut engine,
+            vec![Value::Int(3.into()), Value::Int(2.into())]
+        )
+        .is_err());
+    }
+    #[test]
+    fn capability_gated_builtins() {
+        let mut engine = Engine::<()>::new();
+        let call = |engine: &mut Engine<()>, name: &str| {
+            let Some(Value::Func(func)) = engine.vars().get(name).cloned() else {
+                panic!("{name} is not a builtin");
+            };
+            func.call(&(), vec![], engine)
+        };
+
+        let denied = |result, expect| matches!(result, Err(EvalError::CapabilityDenied { capability, .. }) if capability == expect);
+        assert!(denied(call(&mut engine, "now"), Capability::Time));
+        assert!(denied(call(&mut engine, "random"), Capability::Random));
+
+        engine.allow(Capability::Time, true);
+        assert!(matches!(call(&mut engine, "now"), Ok(Value::Float(f)) if f > 0.));
+        let Ok(Value::Float(start)) = call(&mut engine, "clock") else {
+            panic!("expected a float");
+        };
+        assert!(matches!(call(&mut engine, "clock"), Ok(Value::Float(f)) if f >= start));
+        assert!(denied(call(&mut engine, "random"), Capability::Random));
+
+        // pure builtins never need a capability
+        engine.set_capabilities(Capabilities::none());
+        let Some(Value::Func(chars)) = engine.vars().get("chars").cloned() else {
+            panic!("chars is not a builtin");
+        };
+        assert!(chars.call(&(), vec![string("ab")], &mut engine).is_ok());
+    }
+    #[test]
+    fn builtins_respect_alloc_limit() {
+        let mut engine = Engine::<()>::new();
+        engine.set_alloc_limit(Some(4));
+        let mut call = |name: &str, values: Vec<Value<()>>| {
+            let Some(Value::Func(func)) = engine.vars().get(name).cloned() else {
+                panic!("{name} is not a builtin");
+            };
+            let mut target = values[0].clone();
+            match func.mutates() {
+                false => func.call(&(), values, &mut engine),
+                true => func.call_mut(&(), &mut target, values[1..].to_vec(), &mut engine),
+            }
+        };
+        let over = |result| matches!(result, Err(EvalError::AllocationLimit { limit: 4, .. }));
+
+        let pair = Value::Tuple([string("ab"), string("c")].into_iter().collect());
+        assert!(call("join", vec![string(""), pair.clone()]).is_ok());
+        assert!(over(call("join", vec![string(", "), pair])));
+        assert!(over(call(
+            "format",
+            vec![string("{}{}"), string("ab"), string("cd!")]
+        )));
+        assert!(over(call(
+            "replace",
+            vec![string("aa"), string("a"), string("bcd")]
+        )));
+
+        let list = Value::List((0..4).map(|i| Value::Int(i.into())).collect());
+        assert!(over(call("push", vec![list.clone(), Value::None])));
+        assert!(call("pop", vec![list]).is_ok());
+
+        let mut map = Map::new();
+        for key in ["a", "b", "c", "d"] {
+            map.insert(MapKey::new(string(key)).unwrap(), Value::None);
+        }
+        let map = Value::Map(map);
+        assert!(call("set_key", vec![map.clone(), string("d"), Value::None]).is_ok());
+        assert!(over(call("set_key", vec![map, string("e"), Value::None])));
+
+        let big = Value::List((0..5).map(|i| Value::Int(i.into())).collect());
+        assert!(over(call("deepcopy", vec![big])));
+    }
+
+    #[test]
+    fn env_vars() {
+        let mut engine = Engine::<()>::new();
+        let path = env::var("PATH").expect("PATH is set");
+        assert_eq!(
+            env_var(&mut engine, vec![string("PATH")]),
+            Ok(string(&path))
+        );
+
+        let unset = "BOBA_SCRIPT_TEST_UNSET_VARIABLE";
+        assert_eq!(env_var(&mut engine, vec![string(unset)]), Ok(Value::None));
+        assert_eq!(env_var(&mut engine, vec![string("")]), Ok(Value::None));
+        assert_eq!(env_var(&mut engine, vec![string("A=B")]), Ok(Value::None));
+        assert_eq!(env_var(&mut engine, vec![string("A\0")]), Ok(Value::None));
+        assert!(env_var(&mut engine, vec![Value::None]).is_err());
+    }
+
+    #[test]
+    fn file_io() {
+        let mut engine = Engine::<()>::new();
+        let file = env::temp_dir().join(format!("boba-script-io-{}", std::process::id()));
+        let file = file.to_string_lossy().into_owned();
+        let path = string(&file);
+
+        let written = write_file(&mut engine, vec![path.clone(), string("a\nb")]);
+        assert_eq!(written, Ok(Value::None));
+        let read = read_file(&mut engine, vec![path]);
+        assert_eq!(read, Ok(string("a\nb")));
+
+        fs::remove_file(&file).unwrap();
+        let missing = read_file(&mut engine, vec![string(&file)]);
+        assert!(matches!(missing, Err(message) if message.starts_with("failed to read")));
+        assert!(write_file(&mut engine, vec![string(&file), Value::None]).is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let text = r#"{"b":[1,2.5,"x"],"a":null,"big":123456789012345678901234567890,"t":true}"#;
+        let Ok(Value::Result(result)) = parse_json(vec![string(text)]) else {
+            panic!("expected a result");
+        };
+        let Ok(Value::Map(map)) = *result else {
+            panic!("expected a map");
+        };
+        let keys = map.iter().map(|(k, _)| k.to_string()).collect::<Vec<_>>();
+        assert_eq!(keys, ["b", "a", "big", "t"]);
+        assert_eq!(map.get(&string("a")), Some(&Value::None));
+        assert_eq!(
+            map.get(&string("b")),
+            Some(&Value::List(
+                [Value::Int(1.into()), Value::Float(2.5), string("x")]
+                    .into_iter()
+                    .collect()
+            ))
+        );
+        assert_eq!(to_json(vec![Value::Map(map)]), Ok(string(text)),);
+
+        let tuple = Value::Tuple([Value::Bool(false), string("\"")].into_iter().collect());
+        assert_eq!(to_json(vec![tuple]), Ok(string(r#"[false,"\""]"#)));
+    }
+
+    #[test]
+    fn json_errors() {
+        let invalid = parse_json::<()>(vec![string("{\n  \"a\": }")]);
+        let message = string("invalid json: expected value at line 2 column 8");
+        assert_eq!(invalid, Ok(Value::Result(Box::new(Err(message)))));
+        assert!(parse_json::<()>(vec![Value::None]).is_err());
+
+        let func = Value::<()>::Func(FuncPtr::native(0, |_| Ok(Value::None)));
+        assert!(to_json(vec![func]).is_err());
+        assert!(to_json::<()>(vec![Value::Float(f64::NAN)]).is_err());
+        let key = MapKey::new(Value::Int(1.into())).unwrap();
+        let map = [(key, Value::None)].into_iter().collect();
+        assert!(to_json::<()>(vec![Value::Map(map)]).is_err());
+    }
+
+    #[test]
+    fn dbg_passes_through() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use crate::ast::{node::Builder, Expr};
+
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::<usize>::new();
+        let written = output.clone();
+        engine.set_debug_output(move |source, value| {
+            written
+                .borrow_mut()
+                .push(format!("{source}: {}", value.repr()));
+        });
+
+        // dbg returns its argument so it can be used inside other expressions
+        let arg = Expr::String("a".into()).build_node(4);
+        let call = Expr::Call {
+            name: "dbg".into(),
+            params: vec![arg],
+        };
+        let tuple = Expr::Tuple(vec![call.build_node(1), Expr::Int(2.into()).build_node(9)]);
+        let value = engine.eval(tuple.build_node(0)).unwrap();
+        assert_eq!(value.to_string(), "(a, 2)");
+        assert_eq!(*output.borrow(), ["1: \"a\""]);
+    }
+
+    #[test]
+    fn copies_are_independent() {
+        use crate::ast::{node::Builder, Expr, Statement};
+
+        // let a = unwrap(parse_json("[[1]]"))
+        // let b, c = copy(a), deepcopy(a)
+        // push(a, 2)
+        // (a, b, c)
+        let call = |name: &str, params| {
+            Expr::Call {
+                name: name.into(),
+                params,
+            }
+            .build_node(())
+        };
+        let var = |name: &str| Expr::Var(name.into()).build_node(());
+        let init = |lhs, rhs| {
+            Statement::Assign {
+                init: true,
+                lhs,
+                ty: None,
+                rhs,
+            }
+            .build_node(())
+        };
+
+        let mut engine = Engine::<()>::new();
+        let text = Expr::String("[[1]]".into()).build_node(());
+        let list = call("unwrap", vec![call("parse_json", vec![text])]);
+        engine.eval(init(var("a"), list)).unwrap();
+        let copies = Expr::Tuple(vec![
+            call("copy", vec![var("a")]),
+            call("deepcopy", vec![var("a")]),
+        ]);
+        let names = Expr::Tuple(vec![var("b"), var("c")]);
+        engine
+            .eval(init(names.build_node(()), copies.build_node(())))
+            .unwrap();
+        engine
+            .eval(call(
+                "push",
+                vec![var("a"), Expr::Int(2.into()).build_node(())],
+            ))
+            .unwrap();
+
+        let all = Expr::Tuple(vec![var("a"), var("b"), var("c")]);
+        let value = engine.eval(all.build_node(())).unwrap();
+        assert_eq!(value.to_string(), "([[1], 2], [[1]], [[1]])");
+    }
+
+    #[test]
+    fn result_accessors() {
+        let ok = || Value::<()>::Result(Box::new(Ok(Value::Int(1.into()))));
+        let err = || Value::<()>::Result(Box::new(Err(string("bad"))));
+        assert_eq!(unwrap(vec![ok()]), Ok(Value::Int(1.into())));
+        assert_eq!(unwrap(vec![err()]), Err("called unwrap on err(bad)".into()));
+        assert_eq!(unwrap_or(vec![err(), Value::None]), Ok(Value::None));
+        assert_eq!(
+            unwrap::<()>(vec![Value::None]),
+            Err("unwrap expects a result, found none".into())
+        );
+        assert_eq!(ok().repr(), "ok(1)");
+        assert_eq!(err().repr(), "err(\"bad\")");
+        assert_ne!(ok().hash(), None);
+        assert_ne!(ok(), err());
+    }
+
+    #[test]
+    fn panic_reports_call() {
+        use crate::ast::{node::Builder, Expr};
+
+        let mut engine = Engine::<usize>::new();
+        let call = Expr::Call {
+            name: "panic".into(),
+            params: vec![Expr::String("boom".into()).build_node(6)],
+        };
+        assert!(matches!(
+            engine.eval(call.build_node(1)),
+            Err(EvalError::NativeCall { message, source: 1 }) if message == "boom"
+        ));
+    }
+
+    #[test]
+    fn number_formatting() {
+        let int = |i: i64| Value::<()>::Int(i.into());
+        let fixed = |x, digits| to_fixed(vec![x, int(digits)]);
+        assert_eq!(fixed(Value::Float(2.0 / 3.0), 3), Ok(string("0.667")));
+        assert_eq!(fixed(Value::Float(0.125), 2), Ok(string("0.12")));
+        assert_eq!(fixed(Value::Float(-1.5), 0), Ok(string("-2")));
+        assert_eq!(fixed(int(7), 2), Ok(string("7.00")));
+        assert!(fixed(Value::Float(1.), -1).is_err());
+        assert!(fixed(string("1"), 1).is_err());
+
+        let radix = |n, base| to_radix(vec![int(n), int(base)]);
+        assert_eq!(radix(255, 16), Ok(string("ff")));
+        assert_eq!(radix(-5, 2), Ok(string("-101")));
+        assert_eq!(radix(35, 36), Ok(string("z")));
+        assert_eq!(
+            radix(1, 37),
+            Err("to_radix expects a base from 2 to 36, found 37".into())
+        );
+        assert!(radix(1, 1).is_err());
+    }
+
+    #[test]
+    fn map_accessors() {
+        let text = r#"{"b": 1, "a": [2]}"#;
+        let Ok(Value::Result(result)) = parse_json(vec![string(text)]) else {
+            panic!("expected a result");
+        };
+        let map = result.unwrap();
+
+        let found = keys(vec![map.clone()]).unwrap();
+        assert_eq!(found.repr(), r#"["b", "a"]"#);
+        let found = values(vec![map.clone()]).unwrap();
+        assert_eq!(found.repr(), "[1, [2]]");
+        let found = items(vec![map]).unwrap();
+        assert_eq!(found.repr(), r#"[("b", 1), ("a", [2])]"#);
+
+        assert_eq!(
+            keys::<()>(vec![Value::List(Default::default())]),
+            Err("keys expects a map, found list".into())
+        );
+    }
+
+    #[test]
+    fn mutation() {
+        let Ok(Value::Result(result)) = parse_json(vec![string(r#"{"a": 1}"#)]) else {
+            panic!("expected a result");
+        };
+        let mut map = result.unwrap();
+
+        set_key(&mut map, vec![string("b"), Value::Int(2.into())]).unwrap();
+        set_key(&mut map, vec![string("a"), Value::Int(3.into())]).unwrap();
+        assert_eq!(map.repr(), r#"{"a": 3, "b": 2}"#);
+        let found = remove(&mut map, vec![string("a")]).unwrap();
+        assert_eq!(found.repr(), "3");
+        assert_eq!(map.repr(), r#"{"b": 2}"#);
+        assert!(remove(&mut map, vec![string("a")]).is_err());
+
+        let mut list = Value::<()>::List(Default::default());
+        push(&mut list, vec![map.clone()]).unwrap();
+        assert_eq!(
+            contains(vec![list.clone(), map.clone()]),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(contains(vec![map, string("b")]), Ok(Value::Bool(true)));
+        assert_eq!(pop(&mut list, vec![]).unwrap().repr(), r#"{"b": 2}"#);
+        assert_eq!(
+            pop(&mut list, vec![]),
+            Err("pop expects a non empty list".into())
+        );
+    }
 }
@@ -0,0 +1,234 @@
+use std::{
+    hash::{Hash, Hasher},
+    mem::discriminant,
+};
+
+use fxhash::FxHasher64;
+
+use crate::ast::{Expr, ExprNode};
+
+/// Returns the structural hash of a pure expression, ignoring its sources.
+///
+/// Returns `None` if the expression reads variables, calls functions or assigns,
+/// as its value could change between evaluations.
+/// The result is stored on the node, so each node is only hashed once,
+/// and each hash is built from the stored hashes of its children.
+pub fn pure_hash<Source>(node: &ExprNode<Source>) -> Option<u64> {
+    *node.pure.get_or_init(|| hash_expr(&node.item))
+}
+
+/// Returns true if the expression is a literal,
+/// which is cheaper to evaluate than to look up.
+pub fn is_literal<Source>(expr: &Expr<Source>) -> bool {
+    matches!(
+        expr,
+        Expr::None | Expr::Bool(_) | Expr::Int(_) | Expr::Float(_) | Expr::String(_)
+    )
+}
+
+fn hash_expr<Source>(expr: &Expr<Source>) -> Option<u64> {
+    let mut state = FxHasher64::default();
+    discriminant(expr).hash(&mut state);
+    let children = |state: &mut FxHasher64, exprs: &[&ExprNode<Source>]| {
+        for expr in exprs {
+            pure_hash(expr)?.hash(state);
+        }
+        Some(())
+    };
+
+    match expr {
+        // SIMPLE VALUES
+        Expr::None => (),
+        Expr::Bool(value) => value.hash(&mut state),
+        Expr::Int(value) => value.hash(&mut state),
+        Expr::Float(value) => value.to_bits().hash(&mut state),
+        Expr::String(value) => value.hash(&mut state),
+        Expr::Tuple(exprs) => {
+            exprs.len().hash(&mut state);
+            for expr in exprs {
+                pure_hash(expr)?.hash(&mut state);
+            }
+        }
+
+        // SPLAT
+        Expr::Splat(inner) => children(&mut state, &[inner])?,
+
+        // IMPURE VALUES
+        Expr::Var(_)
+        | Expr::Func(_)
+        | Expr::Call { .. }
+        | Expr::Walrus(_, _)
+        | Expr::At { .. }
+        | Expr::Try { .. } => return None,
+
+        // FIELD ACCESS
+        Expr::Field { target, field } | Expr::OptField { target, field } => {
+            field.item.hash(&mut state);
+            children(&mut state, &[target])?;
+        }
+
+        // TERNARY
+        Expr::Ternary { cond, pass, fail } => children(&mut state, &[cond, pass, fail])?,
+
+        // UNARY OPS
+        Expr::Pos(inner) | Expr::Neg(inner) | Expr::Not(inner) => children(&mut state, &[inner])?,
+
+        // BINARY OPS
+        Expr::Add(lhs, rhs)
+        | Expr::Sub(lhs, rhs)
+        | Expr::Mul(lhs, rhs)
+        | Expr::Div(lhs, rhs)
+        | Expr::Modulo(lhs, rhs)
+        | Expr::Pow(lhs, rhs)
+        | Expr::Eq(lhs, rhs)
+        | Expr::Lt(lhs, rhs)
+        | Expr::Gt(lhs, rhs)
+        | Expr::NEq(lhs, rhs)
+        | Expr::LtEq(lhs, rhs)
+        | Expr::GtEq(lhs, rhs)
+        | Expr::And(lhs, rhs)
+        | Expr::Or(lhs, rhs) => children(&mut state, &[lhs, rhs])?,
+    }
+
+    Some(state.finish())
+}
+
+/// Returns true if two pure expressions have the same structure, ignoring their sources.
+///
+/// Used to tell apart different expressions whose hashes collide.
+pub fn same_pure<Source>(lhs: &Expr<Source>, rhs: &Expr<Source>) -> bool {
+    let all = |lhs: &[&ExprNode<Source>], rhs: &[&ExprNode<Source>]| {
+        lhs.len() == rhs.len()
+            && lhs
+                .iter()
+                .zip(rhs)
+                .all(|(l, r)| same_pure(&l.item, &r.item))
+    };
+
+    match (lhs, rhs) {
+        (Expr::None, Expr::None) => true,
+        (Expr::Bool(l), Expr::Bool(r)) => l == r,
+        (Expr::Int(l), Expr::Int(r)) => l == r,
+        (Expr::Float(l), Expr::Float(r)) => l.to_bits() == r.to_bits(),
+        (Expr::String(l), Expr::String(r)) => l == r,
+        (Expr::Tuple(l), Expr::Tuple(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| same_pure(&l.item, &r.item))
+        }
+        (
+            Expr::Field {
+                target: lt,
+                field: lf,
+            },
+            Expr::Field {
+                target: rt,
+                field: rf,
+            },
+        )
+        | (
+            Expr::OptField {
+                target: lt,
+                field: lf,
+            },
+            Expr::OptField {
+                target: rt,
+                field: rf,
+            },
+        ) => lf.item == rf.item && all(&[lt], &[rt]),
+        (
+            Expr::Ternary { cond, pass, fail },
+            Expr::Ternary {
+                cond: rc,
+                pass: rp,
+                fail: rf,
+            },
+        ) => all(&[cond, pass, fail], &[rc, rp, rf]),
+        (Expr::Splat(l), Expr::Splat(r))
+        | (Expr::Pos(l), Expr::Pos(r))
+        | (Expr::Neg(l), Expr::Neg(r))
+        | (Expr::Not(l), Expr::Not(r)) => all(&[l], &[r]),
+        (Expr::Add(ll, lr), Expr::Add(rl, rr))
+        | (Expr::Sub(ll, lr), Expr::Sub(rl, rr))
+        | (Expr::Mul(ll, lr), Expr::Mul(rl, rr))
+        | (Expr::Div(ll, lr), Expr::Div(rl, rr))
+        | (Expr::Modulo(ll, lr), Expr::Modulo(rl, rr))
+        | (Expr::Pow(ll, lr), Expr::Pow(rl, rr))
+        | (Expr::Eq(ll, lr), Expr::Eq(rl, rr))
+        | (Expr::Lt(ll, lr), Expr::Lt(rl, rr))
+        | (Expr::Gt(ll, lr), Expr::Gt(rl, rr))
+        | (Expr::NEq(ll, lr), Expr::NEq(rl, rr))
+        | (Expr::LtEq(ll, lr), Expr::LtEq(rl, rr))
+        | (Expr::GtEq(ll, lr), Expr::GtEq(rl, rr))
+        | (Expr::And(ll, lr), Expr::And(rl, rr))
+        | (Expr::Or(ll, lr), Expr::Or(rl, rr)) => all(&[ll, lr], &[rl, rr]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::node::Builder;
+
+    use super::*;
+
+    fn node(expr: Expr<u32>, source: u32) -> Box<ExprNode<u32>> {
+        Box::new(expr.build_node(source))
+    }
+
+    #[test]
+    fn pure_hashes() {
+        let a = Expr::Mul(
+            node(Expr::String("ab".into()), 0),
+            node(Expr::Int(3.into()), 1),
+        )
+        .build_node(2);
+        let b = Expr::Mul(
+            node(Expr::String("ab".into()), 5),
+            node(Expr::Int(3.into()), 6),
+        )
+        .build_node(7);
+        let hash = pure_hash(&a);
+        assert!(hash.is_some());
+        assert_eq!(hash, pure_hash(&b), "sources are not part of the hash");
+        assert!(same_pure(&a.item, &b.item));
+
+        let c = Expr::Mul(
+            node(Expr::String("ab".into()), 0),
+            node(Expr::Int(4.into()), 1),
+        )
+        .build_node(2);
+        assert_ne!(hash, pure_hash(&c));
+        assert!(!same_pure(&a.item, &c.item));
+
+        // operators with the same operands are still different expressions
+        let d = Expr::Add(
+            node(Expr::String("ab".into()), 0),
+            node(Expr::Int(3.into()), 1),
+        )
+        .build_node(2);
+        assert_ne!(hash, pure_hash(&d));
+        assert!(!same_pure(&a.item, &d.item));
+
+        let var = Expr::Add(node(Expr::Var("x".into()), 0), node(Expr::Int(1.into()), 1));
+        assert_eq!(pure_hash(&var.build_node(2)), None);
+        assert!(is_literal(&Expr::<u32>::Int(1.into())));
+    }
+
+    #[test]
+    fn hashed_once() {
+        let mut expr = Expr::Neg(node(Expr::Int(1.into()), 0)).build_node(1);
+        let hash = pure_hash(&expr);
+        assert_eq!(expr.pure.get(), Some(&hash));
+        let Expr::Neg(inner) = &expr.item else {
+            unreachable!()
+        };
+        assert!(
+            inner.pure.get().is_some(),
+            "children are hashed along the way"
+        );
+
+        // changing the item forgets the stored hash
+        *expr = Expr::Var("x".into());
+        assert_eq!(expr.pure.get(), None);
+        assert_eq!(pure_hash(&expr), None);
+    }
+}
@@ -0,0 +1,60 @@
+use derive_more::Display;
+
+/// A side effect that native builtins need permission to perform.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    #[display(fmt = "io")]
+    Io,
+    #[display(fmt = "time")]
+    Time,
+    #[display(fmt = "random")]
+    Random,
+    #[display(fmt = "env")]
+    Env,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 4] = [Self::Io, Self::Time, Self::Random, Self::Env];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// A set of enabled capabilities.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    bits: u8,
+}
+
+impl FromIterator<Capability> for Capabilities {
+    fn from_iter<T: IntoIterator<Item = Capability>>(iter: T) -> Self {
+        let mut capabilities = Self::none();
+        for capability in iter {
+            capabilities.insert(capability);
+        }
+        capabilities
+    }
+}
+
+impl Capabilities {
+    pub fn none() -> Self {
+        Self { bits: 0 }
+    }
+
+    pub fn all() -> Self {
+        Capability::ALL.into_iter().collect()
+    }
+
+    pub fn contains(&self, capability: Capability) -> bool {
+        self.bits & capability.bit() != 0
+    }
+
+    pub fn insert(&mut self, capability: Capability) {
+        self.bits |= capability.bit();
+    }
+
+    pub fn remove(&mut self, capability: Capability) {
+        self.bits &= !capability.bit();
+    }
+}
@@ -0,0 +1,408 @@
+use crate::ast::{Expr, ExprNode, Statement, StatementNode};
+
+use super::{ops::OpManager, value::ValueKind, EvalError, Value};
+
+type UnaryOp<Source> = fn(&OpManager<Source>, &Value<Source>) -> Option<Value<Source>>;
+type BinaryOp<Source> =
+    fn(&OpManager<Source>, &Value<Source>, &Value<Source>) -> Option<Value<Source>>;
+
+/// A conservative static checker that finds type errors before evaluation.
+///
+/// Only expressions whose types are known from literals are checked.
+/// Anything that depends on a variable or function call is treated as dynamic
+/// and will only be checked at runtime.
+pub struct Checker<'a, Source> {
+    ops: &'a OpManager<Source>,
+    errors: Vec<EvalError<Source>>,
+}
+
+impl<'a, Source: Clone> Checker<'a, Source> {
+    pub fn new(ops: &'a OpManager<Source>) -> Self {
+        Self {
+            ops,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn errors(&self) -> &[EvalError<Source>] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<EvalError<Source>> {
+        self.errors
+    }
+
+    pub fn check_statement(&mut self, statement: &StatementNode<Source>) {
+        match &statement.item {
+            Statement::Expr { expr, .. } => {
+                self.check_expr(expr);
+            }
+            Statement::Assign { ty, rhs, .. } | Statement::Const { ty, rhs, .. } => {
+                let value = self.check_expr(rhs);
+                if let (Some(ty), Some(value)) = (ty, value) {
+                    if !ty.item.matches(&value.kind()) {
+                        self.errors.push(EvalError::TypeMismatch {
+                            expect: ty.item,
+                            found: value.kind(),
+                            source: rhs.source.clone(),
+                        });
+                    }
+                }
+            }
+            Statement::While { cond, body } => {
+                self.check_cond(cond);
+                self.check_body(body);
+            }
+            Statement::Repeat { body, cond } => {
+                self.check_body(body);
+                self.check_cond(cond);
+            }
+            Statement::If { cond, pass, fail } => {
+                self.check_cond(cond);
+                self.check_body(pass);
+                self.check_body(fail);
+            }
+            // modules are not loaded until the statement runs
+            Statement::Use { .. } => {}
+            Statement::Commented { statement, .. } => self.check_statement(statement),
+        }
+    }
+
+    /// Checks an expression and returns a sample value of its type if it is known.
+    ///
+    /// The sample is only representative of the type, not the actual value.
+    pub fn check_expr(&mut self, expr: &ExprNode<Source>) -> Option<Value<Source>> {
+        match &expr.item {
+            // SIMPLE VALUES
+            Expr::None => Some(Value::None),
+            Expr::Bool(_) => Some(Value::Bool(true)),
+            Expr::Int(_) => Some(Value::Int(1.into())),
+            Expr::Float(_) => Some(Value::Float(1.)),
+            Expr::String(_) => Some(Value::String(String::new())),
+            Expr::Tuple(exprs) => {
+                let samples = exprs
+                    .iter()
+                    .map(|expr| self.check_expr(expr))
+                    .collect::<Vec<_>>();
+                let items = samples.into_iter().collect::<Option<Vec<_>>>()?;
+                Some(Value::Tuple(items.into_iter().collect()))
+            }
+            Expr::Func(func) => {
+                self.check_body(&func.body);
+                None
+            }
+
+            // SPLAT
+            Expr::Splat(inner) => {
+                let sample = self.check_expr(inner)?;
+                if !matches!(sample, Value::Tuple(_) | Value::List(_)) {
+                    self.errors.push(EvalError::InvalidSplat {
+                        found: sample.kind(),
+                        source: expr.source.clone(),
+                    });
+                }
+
+                // the number of expanded items is not known
+                None
+            }
+
+            // PATTERNS
+            Expr::At { .. } => {
+                self.errors.push(EvalError::InvalidPattern {
+                    source: expr.source.clone(),
+                });
+                None
+            }
+
+            // DYNAMIC VALUES
+            Expr::Var(_) => None,
+            Expr::Call { params, .. } => {
+                for param in params {
+                    self.check_expr(param);
+                }
+                None
+            }
+            Expr::Field { target, .. } | Expr::OptField { target, .. } => {
+                self.check_expr(target);
+                None
+            }
+            Expr::Walrus(_, rhs) => self.check_expr(rhs),
+
+            // TERNARY
+            Expr::Ternary { cond, pass, fail } => {
+                self.check_cond(cond);
+                let pass = self.check_expr(pass);
+                let fail = self.check_expr(fail);
+                let (pass, fail) = (pass?, fail?);
+                match pass.kind() == fail.kind() {
+                    true => Some(pass),
+                    false => None,
+                }
+            }
+
+            // ERROR HANDLING
+            Expr::Try { body, handler, .. } => {
+                // recoverable errors in the body are caught at runtime
+                let checked = self.errors.len();
+                self.check_expr(body);
+                let errors = self.errors.split_off(checked);
+                let uncaught = errors.into_iter().filter(|error| !error.is_recoverable());
+                self.errors.extend(uncaught);
+
+                self.check_expr(handler);
+                None
+            }
+
+            // UNARY OPS
+            Expr::Pos(inner) => self.check_unary(expr, inner, "+", OpManager::pos),
+            Expr::Neg(inner) => self.check_unary(expr, inner, "-", OpManager::neg),
+            Expr::Not(inner) => self.check_unary(expr, inner, "not", OpManager::not),
+
+            // BINARY OPS
+            Expr::Add(lhs, rhs) => self.check_binary(expr, lhs, rhs, "+", OpManager::add),
+            Expr::Sub(lhs, rhs) => self.check_binary(expr, lhs, rhs, "-", OpManager::sub),
+            Expr::Mul(lhs, rhs) => self.check_binary(expr, lhs, rhs, "*", OpManager::mul),
+            Expr::Div(lhs, rhs) => self.check_binary(expr, lhs, rhs, "/", OpManager::div),
+            Expr::Modulo(lhs, rhs) => self.check_binary(expr, lhs, rhs, "%", OpManager::modulo),
+            Expr::Pow(lhs, rhs) => self.check_binary(expr, lhs, rhs, "**", OpManager::pow),
+            Expr::Eq(lhs, rhs) => self.check_binary(expr, lhs, rhs, "==", OpManager::eq),
+            Expr::Lt(lhs, rhs) => self.check_binary(expr, lhs, rhs, "<", OpManager::lt),
+            Expr::Gt(lhs, rhs) => self.check_binary(expr, lhs, rhs, ">", OpManager::gt),
+            Expr::NEq(lhs, rhs) => self.check_binary(expr, lhs, rhs, "!=", OpManager::neq),
+            Expr::LtEq(lhs, rhs) => self.check_binary(expr, lhs, rhs, "<=", OpManager::lteq),
+            Expr::GtEq(lhs, rhs) => self.check_binary(expr, lhs, rhs, ">=", OpManager::gteq),
+            Expr::And(lhs, rhs) => self.check_binary(expr, lhs, rhs, "and", OpManager::and),
+            Expr::Or(lhs, rhs) => self.check_binary(expr, lhs, rhs, "or", OpManager::or),
+        }
+    }
+
+    /// Finds the type of the innermost expression whose source matches `at`.
+    ///
+    /// Returns `"any"` when the type is only known at runtime,
+    /// and `None` when no expression in the statement matches.
+    pub fn type_at(
+        &mut self,
+        statement: &StatementNode<Source>,
+        at: &impl Fn(&Source) -> bool,
+    ) -> Option<String> {
+        match &statement.item {
+            Statement::Expr { expr, .. } => self.expr_type_at(expr, at),
+            Statement::Assign { lhs, ty, rhs, .. } | Statement::Const { lhs, ty, rhs } => {
+                // annotated bindings report their annotation
+                if let Some(ty) = ty {
+                    if at(&ty.source) || at(&lhs.source) {
+                        return Some(ty.item.to_string());
+                    }
+                }
+
+                // single variables report the type they are bound to
+                if let (Expr::Var(_), true) = (&lhs.item, at(&lhs.source)) {
+                    return Some(self.type_name(rhs));
+                }
+
+                self.expr_type_at(lhs, at)
+                    .or_else(|| self.expr_type_at(rhs, at))
+            }
+            Statement::While { cond, body } => self
+                .expr_type_at(cond, at)
+                .or_else(|| self.body_type_at(body, at)),
+            Statement::Repeat { body, cond } => self
+                .body_type_at(body, at)
+                .or_else(|| self.expr_type_at(cond, at)),
+            Statement::If { cond, pass, fail } => self
+                .expr_type_at(cond, at)
+                .or_else(|| self.body_type_at(pass, at))
+                .or_else(|| self.body_type_at(fail, at)),
+            Statement::Use { .. } => None,
+            Statement::Commented { statement, .. } => self.type_at(statement, at),
+        }
+    }
+
+    fn body_type_at(
+        &mut self,
+        body: &[StatementNode<Source>],
+        at: &impl Fn(&Source) -> bool,
+    ) -> Option<String> {
+        body.iter()
+            .find_map(|statement| self.type_at(statement, at))
+    }
+
+    fn expr_type_at(
+        &mut self,
+        expr: &ExprNode<Source>,
+        at: &impl Fn(&Source) -> bool,
+    ) -> Option<String> {
+        if !at(&expr.source) {
+            return None;
+        }
+
+        // prefer the innermost matching expression
+        let inner = match &expr.item {
+            Expr::None
+            | Expr::Bool(_)
+            | Expr::Int(_)
+            | Expr::Float(_)
+            | Expr::String(_)
+            | Expr::Var(_) => None,
+            Expr::Tuple(exprs) | Expr::Call { params: exprs, .. } => {
+                exprs.iter().find_map(|expr| self.expr_type_at(expr, at))
+            }
+            Expr::Func(func) => self.body_type_at(&func.body, at),
+            Expr::Splat(inner) | Expr::Pos(inner) | Expr::Neg(inner) | Expr::Not(inner) => {
+                self.expr_type_at(inner, at)
+            }
+            Expr::At { pattern, .. } => self.expr_type_at(pattern, at),
+            Expr::Field { target, .. } | Expr::OptField { target, .. } => {
+                self.expr_type_at(target, at)
+            }
+            Expr::Ternary { cond, pass, fail } => self
+                .expr_type_at(cond, at)
+                .or_else(|| self.expr_type_at(pass, at))
+                .or_else(|| self.expr_type_at(fail, at)),
+            Expr::Try { body, handler, .. } => self
+                .expr_type_at(body, at)
+                .or_else(|| self.expr_type_at(handler, at)),
+            Expr::Add(lhs, rhs)
+            | Expr::Sub(lhs, rhs)
+            | Expr::Mul(lhs, rhs)
+            | Expr::Div(lhs, rhs)
+            | Expr::Modulo(lhs, rhs)
+            | Expr::Pow(lhs, rhs)
+            | Expr::Eq(lhs, rhs)
+            | Expr::Lt(lhs, rhs)
+            | Expr::Gt(lhs, rhs)
+            | Expr::NEq(lhs, rhs)
+            | Expr::LtEq(lhs, rhs)
+            | Expr::GtEq(lhs, rhs)
+            | Expr::And(lhs, rhs)
+            | Expr::Or(lhs, rhs)
+            | Expr::Walrus(lhs, rhs) => self
+                .expr_type_at(lhs, at)
+                .or_else(|| self.expr_type_at(rhs, at)),
+        };
+
+        inner.or_else(|| Some(self.type_name(expr)))
+    }
+
+    fn type_name(&mut self, expr: &ExprNode<Source>) -> String {
+        match &expr.item {
+            // functions report their full signature
+            Expr::Func(func) => func.item.to_string(),
+            _ => match self.check_expr(expr) {
+                Some(value) => value.kind().to_string(),
+                None => "any".into(),
+            },
+        }
+    }
+
+    fn check_body(&mut self, body: &[StatementNode<Source>]) {
+        for statement in body {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_cond(&mut self, cond: &ExprNode<Source>) {
+        match self.check_expr(cond) {
+            None | Some(Value::Bool(_)) => (),
+            Some(value) => self.errors.push(EvalError::UnexpectedType {
+                expect: ValueKind::Bool,
+                found: value.kind(),
+                source: cond.source.clone(),
+            }),
+        }
+    }
+
+    fn check_unary(
+        &mut self,
+        expr: &ExprNode<Source>,
+        inner: &ExprNode<Source>,
+        op: &'static str,
+        apply: UnaryOp<Source>,
+    ) -> Option<Value<Source>> {
+        let inner = self.check_expr(inner)?;
+        let output = apply(self.ops, &inner);
+        if output.is_none() {
+            self.errors.push(EvalError::InvalidUnaryOp {
+                ty: inner.kind(),
+                op,
+                source: expr.source.clone(),
+            });
+        }
+
+        output
+    }
+
+    fn check_binary(
+        &mut self,
+        expr: &ExprNode<Source>,
+        lhs: &ExprNode<Source>,
+        rhs: &ExprNode<Source>,
+        op: &'static str,
+        apply: BinaryOp<Source>,
+    ) -> Option<Value<Source>> {
+        // check both sides before bailing so errors in either are found
+        let v1 = self.check_expr(lhs);
+        let v2 = self.check_expr(rhs);
+        let (v1, v2) = (v1?, v2?);
+
+        let output = apply(self.ops, &v1, &v2);
+        if output.is_none() {
+            self.errors.push(EvalError::InvalidBinaryOp {
+                ty1: v1.kind(),
+                ty2: v2.kind(),
+                op,
+                source: expr.source.clone(),
+            });
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::node::Builder;
+
+    use super::*;
+
+    type BinaryExpr = fn(Box<ExprNode<()>>, Box<ExprNode<()>>) -> Expr<()>;
+
+    fn binary(op: BinaryExpr, lhs: Expr<()>, rhs: Expr<()>) -> Expr<()> {
+        op(Box::new(lhs.build_node(())), Box::new(rhs.build_node(())))
+    }
+
+    #[test]
+    fn literal_type_errors() {
+        let ops = OpManager::new();
+        let mut checker = Checker::new(&ops);
+
+        // ("a" + 1) - 2 is a string minus an int
+        let add = binary(Expr::Add, Expr::String("a".into()), Expr::Int(1.into()));
+        let sub = binary(Expr::Sub, add, Expr::Int(2.into()));
+        assert_eq!(checker.check_expr(&sub.build_node(())), None);
+        assert!(matches!(
+            checker.errors(),
+            [EvalError::InvalidBinaryOp {
+                ty1: ValueKind::String,
+                ty2: ValueKind::Int,
+                op: "-",
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn dynamic_values_are_unchecked() {
+        let ops = OpManager::new();
+        let mut checker = Checker::new(&ops);
+
+        let sub = binary(Expr::Sub, Expr::Var("x".into()), Expr::String("a".into()));
+        assert_eq!(checker.check_expr(&sub.build_node(())), None);
+        assert!(checker.errors().is_empty());
+
+        let add = binary(Expr::Add, Expr::Int(1.into()), Expr::Float(2.));
+        let sample = checker.check_expr(&add.build_node(()));
+        assert_eq!(sample.map(|v| v.kind()), Some(ValueKind::Float));
+        assert!(checker.errors().is_empty());
+    }
+}
@@ -1,33 +1,82 @@
 use crate::{
-    ast::{expr::ExprNode, node::EvalNode, Expr, Node},
+    ast::{
+        expr::{destructure_value, has_splat, pattern_ids, ExprNode},
+        node::EvalNode,
+        Expr, Import, Node, StatementNode, Symbol, Type, Visibility,
+    },
     engine::Value,
 };
 
-use super::{builtins, ops::OpManager, value::ValueStore, EvalError};
+use std::{path::Path, rc::Rc, time::Instant};
+
+use dashu::integer::IBig;
+use fxhash::FxHashMap;
+
+use super::{
+    cache,
+    check::Checker,
+    module::{Exports, ModuleScope, Modules},
+    ops::{BinaryOp, OpManager, UnaryOp},
+    prelude::Prelude,
+    rng::Rng,
+    value::ValueStore,
+    Capabilities, Capability, EvalError,
+};
+
+type Destructured<'a, Source> = Vec<(Symbol, Value<Source>, &'a Source)>;
+
+/// Cached values of pure expressions, keyed by their structural hash.
+type PureCache<Source> = FxHashMap<u64, (ExprNode<Source>, Value<Source>)>;
 
 pub struct Engine<Source> {
     values: ValueStore<Source>,
     ops: OpManager<Source>,
+    alloc_limit: Option<usize>,
+    cache: Option<PureCache<Source>>,
+    rng: Rng,
+    capabilities: Capabilities,
+    start: Instant,
+    debug_output: Option<Box<DebugOutput<Source>>>,
+    modules: Modules<Source>,
 }
 
-impl<Source> Default for Engine<Source> {
+/// Receives the values passed to `dbg()` along with the source of the call.
+pub type DebugOutput<Source> = dyn FnMut(&Source, &Value<Source>);
+
+impl<Source: 'static> Default for Engine<Source> {
     fn default() -> Self {
-        let mut engine = Self::empty();
-        builtins::load_into(&mut engine);
-        engine
+        Self::with_prelude(Prelude::shared())
+    }
+}
+
+impl<Source: 'static> Engine<Source> {
+    /// Creates an engine with the standard prelude, sharing its functions with other engines.
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
 impl<Source> Engine<Source> {
+    /// Creates an engine without any builtins, not even `print`.
     pub fn empty() -> Self {
         Self {
             values: Default::default(),
             ops: Default::default(),
+            alloc_limit: None,
+            cache: None,
+            rng: Rng::new(),
+            capabilities: Capabilities::none(),
+            start: Instant::now(),
+            debug_output: None,
+            modules: Default::default(),
         }
     }
 
-    pub fn new() -> Self {
-        Self::default()
+    /// Creates an engine with every value in `prelude` defined as a global.
+    pub fn with_prelude(prelude: Prelude<Source>) -> Self {
+        let mut engine = Self::empty();
+        prelude.load_into(&mut engine);
+        engine
     }
 
     pub fn ops(&self) -> &OpManager<Source> {
@@ -41,6 +90,119 @@ impl<Source> Engine<Source> {
     pub fn vars_mut(&mut self) -> &mut ValueStore<Source> {
         &mut self.values
     }
+
+    /// Iterates over the names and values of every variable visible in the current scope.
+    ///
+    /// See [`ValueStore::visible`] for the order they are listed in.
+    pub fn variables(&self) -> impl Iterator<Item = (&'static str, &Value<Source>)> {
+        self.values
+            .visible()
+            .map(|(id, value)| (id.resolve(), value))
+    }
+
+    /// Gets the value of a variable visible in the current scope.
+    pub fn get_variable(&self, name: &str) -> Option<&Value<Source>> {
+        self.values.get(name)
+    }
+
+    pub fn rng_mut(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Returns the capabilities that side-effecting builtins may use.
+    ///
+    /// None are enabled by default so embedders opt in to side effects.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    pub fn allow(&mut self, capability: Capability, allow: bool) {
+        match allow {
+            true => self.capabilities.insert(capability),
+            false => self.capabilities.remove(capability),
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(capability)
+    }
+
+    /// Returns the instant the engine was created, used as the origin of `clock()`.
+    pub fn start_instant(&self) -> Instant {
+        self.start
+    }
+
+    /// Replaces where the values passed to `dbg()` are written.
+    ///
+    /// By default they are printed to stderr without the location of the call,
+    /// since the engine does not know how to describe a `Source`.
+    pub fn set_debug_output(&mut self, output: impl FnMut(&Source, &Value<Source>) + 'static) {
+        self.debug_output = Some(Box::new(output));
+    }
+
+    /// Writes a value passed to `dbg()` to the debug output.
+    pub fn debug(&mut self, source: &Source, value: &Value<Source>) {
+        match &mut self.debug_output {
+            Some(output) => output(source, value),
+            None => eprintln!("value = {}", value.repr()),
+        }
+    }
+
+    /// Sets how modules named by `use` statements are read and parsed.
+    ///
+    /// The loader is given the module path resolved relative to the importing module.
+    /// Engines cannot import modules until a loader is set.
+    pub fn set_module_loader(
+        &mut self,
+        loader: impl FnMut(&Path) -> Result<Vec<StatementNode<Source>>, String> + 'static,
+    ) {
+        self.modules.loader = Some(Box::new(loader));
+    }
+
+    /// Reseeds the engine random number generator so runs are reproducible.
+    ///
+    /// Engines are seeded differently on every run until this is called.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::seeded(seed);
+    }
+
+    /// Returns the largest string (in bytes) or collection (in items) the engine may build.
+    pub fn alloc_limit(&self) -> Option<usize> {
+        self.alloc_limit
+    }
+
+    /// Limits the size of strings and collections built during evaluation,
+    /// including those returned or mutated by builtins.
+    ///
+    /// This is useful when running untrusted scripts that could exhaust memory.
+    pub fn set_alloc_limit(&mut self, limit: Option<usize>) {
+        self.alloc_limit = limit;
+
+        // cached values may not fit in the new limit
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Returns true if the values of pure expressions are cached.
+    pub fn caches_pure_exprs(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// Caches the values of expressions that only depend on literals,
+    /// so evaluating an identical expression again is a lookup.
+    ///
+    /// The cache is never evicted, and disabling it drops all cached values.
+    pub fn cache_pure_exprs(&mut self, enabled: bool) {
+        match enabled {
+            false => self.cache = None,
+            true => self.cache = Some(self.cache.take().unwrap_or_default()),
+        }
+    }
 }
 
 impl<Source: Clone> Engine<Source> {
@@ -51,19 +213,150 @@ impl<Source: Clone> Engine<Source> {
         T::eval_node(node.as_ref(), self)
     }
 
+    /// Evaluates using the cached value of the pure expression `node` if there is one.
+    ///
+    /// `hash` is the structural hash of the node, see [`cache::pure_hash`](super::cache::pure_hash).
+    pub(crate) fn eval_cached(
+        &mut self,
+        hash: u64,
+        node: &ExprNode<Source>,
+        eval: impl FnOnce(&mut Self) -> Result<Value<Source>, EvalError<Source>>,
+    ) -> Result<Value<Source>, EvalError<Source>> {
+        let Some(mut cache) = self.cache.take() else {
+            return eval(self);
+        };
+
+        // only expressions with the same structure share a value, even if their hashes collide
+        if let Some((cached, value)) = cache.get(&hash) {
+            if !cache::same_pure(&cached.item, &node.item) {
+                let result = eval(self);
+                self.cache = Some(cache);
+                return result;
+            }
+
+            let value = value.clone();
+            self.cache = Some(cache);
+            return Ok(value);
+        }
+
+        // the cache is taken while evaluating so inner expressions are not cached too
+        let result = eval(self);
+        if let Ok(value) = &result {
+            cache.insert(hash, (node.clone(), value.clone()));
+        }
+        self.cache = Some(cache);
+        result
+    }
+
+    pub fn check_alloc(&self, size: usize, source: &Source) -> Result<(), EvalError<Source>> {
+        match self.alloc_limit {
+            Some(limit) if size > limit => Err(EvalError::AllocationLimit {
+                size,
+                limit,
+                source: source.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn apply_unary(
+        &self,
+        op: UnaryOp,
+        value: &Value<Source>,
+        source: &Source,
+    ) -> Result<Value<Source>, EvalError<Source>> {
+        match self.ops.unary(op, value) {
+            Some(value) => Ok(value),
+            None => Err(EvalError::InvalidUnaryOp {
+                ty: value.kind(),
+                op: op.symbol(),
+                source: source.clone(),
+            }),
+        }
+    }
+
+    pub fn apply_binary(
+        &self,
+        op: BinaryOp,
+        v1: &Value<Source>,
+        v2: &Value<Source>,
+        source: &Source,
+    ) -> Result<Value<Source>, EvalError<Source>> {
+        // check the size of repeated strings before allocating them
+        let repeat = match (op, v1, v2) {
+            (BinaryOp::Mul, Value::String(string), Value::Int(count))
+            | (BinaryOp::Mul, Value::Int(count), Value::String(string)) => {
+                let count = match *count < IBig::ZERO {
+                    true => 0,
+                    false => usize::try_from(count).unwrap_or(usize::MAX),
+                };
+                self.check_alloc(string.len().saturating_mul(count), source)?;
+                true
+            }
+            _ => false,
+        };
+
+        // integer remainders panic on a zero divisor
+        if let (BinaryOp::Modulo, Value::Int(_), Value::Int(divisor)) = (op, v1, v2) {
+            if *divisor == IBig::ZERO {
+                return Err(EvalError::DivideByZero {
+                    source: source.clone(),
+                });
+            }
+        }
+
+        match self.ops.binary(op, v1, v2) {
+            Some(value) => {
+                if op == BinaryOp::Add {
+                    self.check_alloc(value.alloc_size(), source)?;
+                }
+                Ok(value)
+            }
+            // string repetition is valid but can fail to allocate
+            None if repeat => Err(EvalError::StringAllocError {
+                source: source.clone(),
+            }),
+            None => Err(EvalError::InvalidBinaryOp {
+                ty1: v1.kind(),
+                ty2: v2.kind(),
+                op: op.symbol(),
+                source: source.clone(),
+            }),
+        }
+    }
+
+    pub fn check(&self, statement: &StatementNode<Source>) -> Vec<EvalError<Source>> {
+        let mut checker = Checker::new(&self.ops);
+        checker.check_statement(statement);
+        checker.into_errors()
+    }
+
+    /// Finds the statically known type of the expression whose source matches `at`.
+    ///
+    /// See [`Checker::type_at`] for how types are reported.
+    pub fn type_at(
+        &self,
+        statement: &StatementNode<Source>,
+        at: impl Fn(&Source) -> bool,
+    ) -> Option<String> {
+        Checker::new(&self.ops).type_at(statement, &at)
+    }
+
     pub fn assign(
         &mut self,
         lhs: &ExprNode<Source>,
+        ty: Option<&Node<Type, Source>>,
         rhs: &ExprNode<Source>,
     ) -> Result<(), EvalError<Source>> {
+        // single variables are assigned directly without destructuring
+        if let Expr::Var(id) = &lhs.item {
+            let value = self.eval_annotated(ty, rhs)?;
+            return self.set_var(*id, value, &rhs.source);
+        }
+
         let store = self.destructure(lhs, rhs)?;
         for (id, value, source) in store {
-            if let Err(_) = self.values.set(id, value) {
-                return Err(EvalError::UnknownVariable {
-                    name: id.to_string(),
-                    source: source.clone(),
-                });
-            }
+            self.set_var(id, value, source)?;
         }
 
         Ok(())
@@ -72,8 +365,15 @@ impl<Source: Clone> Engine<Source> {
     pub fn init_assign(
         &mut self,
         lhs: &ExprNode<Source>,
+        ty: Option<&Node<Type, Source>>,
         rhs: &ExprNode<Source>,
     ) -> Result<(), EvalError<Source>> {
+        if let Expr::Var(id) = &lhs.item {
+            let value = self.eval_annotated(ty, rhs)?;
+            self.values.init_local(*id, value);
+            return Ok(());
+        }
+
         let store = self.destructure(lhs, rhs)?;
         for (id, value, _) in store {
             self.values.init_local(id, value);
@@ -81,61 +381,926 @@ impl<Source: Clone> Engine<Source> {
         Ok(())
     }
 
-    fn destructure<'a, 'b>(
+    pub fn init_const(
         &mut self,
-        lhs: &'a ExprNode<Source>,
-        rhs: &'b ExprNode<Source>,
-    ) -> Result<Vec<(&'a str, Value<Source>, &'b Source)>, EvalError<Source>> {
-        fn recurse<'a, 'b, Source: Clone>(
-            lhs: &'a ExprNode<Source>,
-            rhs: &'b ExprNode<Source>,
+        lhs: &ExprNode<Source>,
+        ty: Option<&Node<Type, Source>>,
+        rhs: &ExprNode<Source>,
+    ) -> Result<(), EvalError<Source>> {
+        if let Expr::Var(id) = &lhs.item {
+            let value = self.eval_annotated(ty, rhs)?;
+            self.values.init_local_const(*id, value);
+            return Ok(());
+        }
+
+        let store = self.destructure(lhs, rhs)?;
+        for (id, value, _) in store {
+            self.values.init_local_const(id, value);
+        }
+        Ok(())
+    }
+
+    /// Evaluates the module at `path` and binds its top level values in the current scope.
+    ///
+    /// Private functions are only bound when imported by name, which is an error.
+    /// If an `import` is given, only that value is bound, under its alias if it has one.
+    /// Names that are already visible are never replaced,
+    /// unless they are already bound to the same value, which makes importing again a no-op.
+    /// Each module is only evaluated once, later imports reuse the values it defined.
+    pub(crate) fn use_module(
+        &mut self,
+        path: &Node<String, Source>,
+        import: Option<&Import<Source>>,
+    ) -> Result<(), EvalError<Source>> {
+        let exports = self.load_module(path)?;
+        let bindings = match import {
+            // whole modules only bring in the functions they made public
+            None => exports
+                .into_iter()
+                .filter(|(_, value, _)| !is_private(value))
+                .map(|(id, value, constant)| (id, &path.source, value, constant))
+                .collect(),
+            Some(import) => {
+                let name = &import.name;
+                let Some((_, value, constant)) =
+                    exports.into_iter().find(|(id, ..)| *id == name.item)
+                else {
+                    return Err(EvalError::UnknownVariable {
+                        name: name.item.to_string(),
+                        source: name.source.clone(),
+                    });
+                };
+
+                if is_private(&value) {
+                    return Err(EvalError::PrivateAccess {
+                        name: name.item.to_string(),
+                        source: name.source.clone(),
+                    });
+                }
+
+                let binding = import.binding();
+                vec![(binding.item, &binding.source, value, constant)]
+            }
+        };
+
+        // imports never replace a name that is already visible,
+        // and nothing is bound unless every name is free or already bound to the import
+        let mut unbound = Vec::with_capacity(bindings.len());
+        for (id, source, value, constant) in bindings {
+            match self.values.get(id) {
+                None => unbound.push((id, value, constant)),
+                Some(bound) if bound.total_eq(&value) => {}
+                Some(_) => {
+                    return Err(EvalError::ImportConflict {
+                        name: id.to_string(),
+                        source: source.clone(),
+                    })
+                }
+            }
+        }
+
+        for (id, value, constant) in unbound {
+            match constant {
+                false => self.values.init_local(id, value),
+                true => self.values.init_local_const(id, value),
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates the module at `path` if it has not been already, returning its top level values.
+    fn load_module(
+        &mut self,
+        path: &Node<String, Source>,
+    ) -> Result<Exports<Source>, EvalError<Source>> {
+        if !self.allows(Capability::Io) {
+            return Err(EvalError::CapabilityDenied {
+                capability: Capability::Io,
+                source: path.source.clone(),
+            });
+        }
+
+        let import_failed = |message: String| EvalError::ImportFailed {
+            path: path.item.clone(),
+            message,
+            source: path.source.clone(),
+        };
+
+        let resolved = self.modules.resolve(&path.item);
+        if self.modules.loading.contains(&resolved) {
+            return Err(EvalError::CircularImport {
+                path: path.item.clone(),
+                source: path.source.clone(),
+            });
+        }
+
+        if let Some(exports) = self.modules.loaded.get(&resolved).and_then(|m| m.exports()) {
+            return Ok(exports.clone());
+        }
+
+        let Some(loader) = &mut self.modules.loader else {
+            return Err(import_failed("this engine cannot load modules".into()));
+        };
+        let statements = loader(&resolved).map_err(import_failed)?;
+
+        // modules only see the globals, not the program importing them
+        let module = Rc::new(ModuleScope::default());
+        self.values.isolate(module.clone());
+        self.modules.loading.push(resolved.clone());
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.eval(statement).map(|_| ()));
+        self.modules.loading.pop();
+        let exports = self.values.take_locals();
+        self.values.restore();
+        result?;
+
+        // the functions the module defined keep resolving names against its top level
+        module.set_exports(exports.clone());
+        self.modules.loaded.insert(resolved, module);
+        Ok(exports)
+    }
+
+    /// Moves the value out of a variable, leaving none in its place.
+    pub(crate) fn take_var(
+        &mut self,
+        id: Symbol,
+        source: &Source,
+    ) -> Result<Value<Source>, EvalError<Source>> {
+        if self.values.is_const(id) {
+            return Err(EvalError::ReassignConst {
+                name: id.to_string(),
+                source: source.clone(),
+            });
+        }
+
+        match self.values.set(id, Value::None) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(EvalError::UnknownVariable {
+                name: id.to_string(),
+                source: source.clone(),
+            }),
+        }
+    }
+
+    pub(crate) fn set_var(
+        &mut self,
+        id: Symbol,
+        value: Value<Source>,
+        source: &Source,
+    ) -> Result<(), EvalError<Source>> {
+        if self.values.is_const(id) {
+            return Err(EvalError::ReassignConst {
+                name: id.to_string(),
+                source: source.clone(),
+            });
+        }
+
+        match self.values.set(id, value) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(EvalError::UnknownVariable {
+                name: id.to_string(),
+                source: source.clone(),
+            }),
+        }
+    }
+
+    /// Evaluates the rhs of a binding and checks it against the type annotation
+    fn eval_annotated(
+        &mut self,
+        ty: Option<&Node<Type, Source>>,
+        rhs: &ExprNode<Source>,
+    ) -> Result<Value<Source>, EvalError<Source>> {
+        let value = self.eval(rhs)?;
+        match ty {
+            Some(ty) if !ty.item.matches(&value.kind()) => Err(EvalError::TypeMismatch {
+                expect: ty.item,
+                found: value.kind(),
+                source: rhs.source.clone(),
+            }),
+            _ => Ok(value),
+        }
+    }
+
+    fn destructure<'a>(
+        &mut self,
+        lhs: &ExprNode<Source>,
+        rhs: &'a ExprNode<Source>,
+    ) -> Result<Destructured<'a, Source>, EvalError<Source>> {
+        fn recurse<'a, Source: Clone>(
+            lhs: &ExprNode<Source>,
+            rhs: &'a ExprNode<Source>,
             engine: &mut Engine<Source>,
-            store: &mut Vec<(&'a str, Value<Source>, &'b Source)>,
+            store: &mut Destructured<'a, Source>,
         ) -> Result<(), EvalError<Source>> {
             match &lhs.item {
                 // if the lhs is a variable, then directly assign to it
                 Expr::Var(id) => {
                     let value = engine.eval(rhs)?;
-                    store.push((id, value, &rhs.source));
+                    store.push((*id, value, &rhs.source));
                     Ok(())
                 }
                 // if the lhs is a tuple, then loop over each inner expr and assign
                 Expr::Tuple(lhs_exprs) => match &rhs.item {
-                    Expr::Tuple(rhs_exprs) => match lhs_exprs.len() == rhs_exprs.len() {
-                        false => Err(EvalError::InvalidTupleSize {
-                            lhs_count: lhs_exprs.len(),
-                            rhs_count: rhs_exprs.len(),
-                            lhs_source: lhs.source.clone(),
-                            rhs_source: rhs.source.clone(),
-                        }),
-                        true => {
-                            for (lhs, rhs) in lhs_exprs.iter().zip(rhs_exprs) {
-                                recurse(lhs, rhs, engine, store)?;
+                    Expr::Tuple(rhs_exprs) if !has_splat(lhs_exprs) && !has_splat(rhs_exprs) => {
+                        match lhs_exprs.len() == rhs_exprs.len() {
+                            false => Err(EvalError::InvalidTupleSize {
+                                lhs_count: lhs_exprs.len(),
+                                rhs_count: rhs_exprs.len(),
+                                rest: false,
+                                lhs_source: lhs.source.clone(),
+                                rhs_source: rhs.source.clone(),
+                            }),
+                            true => {
+                                for (lhs, rhs) in lhs_exprs.iter().zip(rhs_exprs) {
+                                    recurse(lhs, rhs, engine, store)?;
+                                }
+                                Ok(())
                             }
-                            Ok(())
                         }
-                    },
-                    _ => match lhs_exprs.len() {
-                        1 => recurse(&lhs_exprs[0], rhs, engine, store),
-                        _ => Err(EvalError::InvalidTupleDestructure {
-                            lhs_count: lhs_exprs.len(),
-                            lhs_source: lhs.source.clone(),
-                            rhs_source: rhs.source.clone(),
-                        }),
-                    },
+                    }
+                    _ => unpack(lhs, rhs, engine, store),
                 },
+                // patterns are destructured from the value of the rhs
+                Expr::At { .. } => unpack(lhs, rhs, engine, store),
                 // if the lhs is anything else, then the lhs cannot be assigned to
-                _ => {
-                    return Err(EvalError::InvalidAssign {
-                        source: lhs.source.clone(),
-                    })
-                }
+                _ => Err(EvalError::InvalidAssign {
+                    source: lhs.source.clone(),
+                }),
             }
         }
 
+        fn unpack<'a, Source: Clone>(
+            lhs: &ExprNode<Source>,
+            rhs: &'a ExprNode<Source>,
+            engine: &mut Engine<Source>,
+            store: &mut Destructured<'a, Source>,
+        ) -> Result<(), EvalError<Source>> {
+            let value = engine.eval(rhs)?;
+            let mut values = Vec::new();
+            destructure_value(lhs, value, &rhs.source, &mut values)?;
+
+            let mut ids = Vec::new();
+            pattern_ids(lhs, &mut ids);
+            for (id, value) in ids.into_iter().zip(values) {
+                store.push((id, value, &rhs.source));
+            }
+            Ok(())
+        }
+
         // capture all the destructured variables
         let mut store = Vec::new();
         recurse(lhs, rhs, self, &mut store)?;
         Ok(store)
     }
 }
+
+fn is_private<Source>(value: &Value<Source>) -> bool {
+    matches!(value, Value::Func(func) if func.vis() == Visibility::Private)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use crate::{
+        ast::{func::Func, node::Builder, Statement, Visibility},
+        engine::value::{FuncPtr, ValueKind},
+    };
+
+    use super::*;
+
+    #[test]
+    fn alloc_limit() {
+        let mut engine = Engine::<()>::new();
+        engine.set_alloc_limit(Some(8));
+
+        let repeat = |count: i32| {
+            let lhs = Expr::String("ab".into()).build_node(());
+            let rhs = Expr::Int(count.into()).build_node(());
+            Expr::Mul(Box::new(lhs), Box::new(rhs)).build_node(())
+        };
+
+        let value = engine.eval(repeat(4));
+        assert!(matches!(value, Ok(Value::String(s)) if s == "abababab"));
+
+        let value = engine.eval(repeat(5));
+        assert!(matches!(
+            value,
+            Err(EvalError::AllocationLimit {
+                size: 10,
+                limit: 8,
+                ..
+            })
+        ));
+
+        let items = (0..9).map(|i| Expr::Int(i.into()).build_node(()));
+        let tuple = Expr::Tuple(items.collect()).build_node(());
+        assert!(engine.eval(tuple).is_err());
+    }
+
+    #[test]
+    fn cached_pure_exprs() {
+        let mut engine = Engine::<()>::new();
+        engine.cache_pure_exprs(true);
+        assert!(engine.caches_pure_exprs());
+
+        let repeat = || {
+            let lhs = Expr::String("ab".into()).build_node(());
+            let rhs = Expr::Int(3.into()).build_node(());
+            Expr::Mul(Box::new(lhs), Box::new(rhs)).build_node(())
+        };
+
+        for _ in 0..2 {
+            let value = engine.eval(repeat());
+            assert!(matches!(value, Ok(Value::String(s)) if s == "ababab"));
+        }
+
+        // changing the limit drops values that may no longer fit
+        engine.set_alloc_limit(Some(4));
+        assert!(engine.eval(repeat()).is_err());
+    }
+
+    #[test]
+    fn variable_introspection() {
+        let mut engine = Engine::<()>::empty();
+        engine.vars_mut().init_global("g", Value::Int(1.into()));
+        engine.vars_mut().init_local("a", Value::Int(2.into()));
+        engine.vars_mut().push_scope();
+        engine.vars_mut().init_local("a", Value::Int(3.into()));
+        engine.vars_mut().init_local("b", Value::Int(4.into()));
+
+        let vars = engine
+            .variables()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>();
+        assert_eq!(vars, ["b=4", "a=3", "g=1"]);
+        assert!(matches!(engine.get_variable("a"), Some(Value::Int(i)) if *i == 3.into()));
+
+        engine.vars_mut().pop_scope();
+        assert!(engine.get_variable("b").is_none());
+        assert_eq!(engine.variables().count(), 2);
+
+        // asking about an unknown name does not intern it
+        assert!(engine.get_variable("introspection_unknown").is_none());
+        assert_eq!(Symbol::lookup("introspection_unknown"), None);
+    }
+
+    #[test]
+    fn rest_patterns() {
+        let mut engine = Engine::<()>::new();
+        let var = |name: &str| Expr::Var(name.into()).build_node(());
+        let rest = |name: &str| Expr::Splat(Box::new(var(name))).build_node(());
+        let tuple = |items| Expr::Tuple(items).build_node(());
+        let ints = |count: i32| {
+            tuple(
+                (1..=count)
+                    .map(|i| Expr::Int(i.into()).build_node(()))
+                    .collect(),
+            )
+        };
+
+        let pattern = tuple(vec![var("a"), rest("mid"), var("b")]);
+        engine.init_assign(&pattern, None, &ints(4)).unwrap();
+        let mid = engine.vars().get("mid").unwrap().to_string();
+        assert_eq!(mid, "(2, 3)");
+
+        // the rest item may capture nothing
+        engine.init_assign(&pattern, None, &ints(2)).unwrap();
+        assert_eq!(engine.vars().get("mid").unwrap().to_string(), "()");
+
+        let error = engine.init_assign(&pattern, None, &ints(1)).unwrap_err();
+        assert!(matches!(
+            error,
+            EvalError::InvalidTupleSize {
+                lhs_count: 2,
+                rhs_count: 1,
+                rest: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            error.message(),
+            "expected tuple with at least 2 items, found 1"
+        );
+
+        let pattern = tuple(vec![rest("a"), rest("b")]);
+        assert!(matches!(
+            engine.init_assign(&pattern, None, &ints(2)),
+            Err(EvalError::DuplicateRest { .. })
+        ));
+    }
+
+    #[test]
+    fn tuple_values() {
+        let mut engine = Engine::<()>::new();
+        let int = |i: i32| Value::Int(i.into());
+        let tuple = |items: Vec<ExprNode<()>>| Expr::Tuple(items).build_node(());
+        let splat = |name: &str| Expr::Splat(Box::new(Expr::Var(name.into()).build_node(())));
+
+        // the unit is an empty tuple, not none
+        let unit = engine.eval(tuple(vec![])).unwrap();
+        assert!(unit.total_eq(&Value::Tuple([].into_iter().collect())));
+        assert!(!unit.total_eq(&Value::None));
+        assert_eq!(unit.to_string(), "()");
+
+        let single = engine.eval(tuple(vec![Expr::Int(1.into()).build_node(())]));
+        let expect = Value::Tuple([int(1)].into_iter().collect());
+        assert!(single.unwrap().total_eq(&expect));
+
+        // splatted items are spread in place
+        let inner = Value::Tuple([int(1), int(2)].into_iter().collect());
+        engine.vars_mut().init_global("a", inner);
+        let items = vec![
+            Expr::Int(0.into()).build_node(()),
+            splat("a").build_node(()),
+            Expr::Int(3.into()).build_node(()),
+        ];
+        let expect = Value::Tuple([int(0), int(1), int(2), int(3)].into_iter().collect());
+        assert!(engine.eval(tuple(items)).unwrap().total_eq(&expect));
+
+        let items = vec![splat("a").build_node(()), splat("a").build_node(())];
+        let expect = Value::Tuple([int(1), int(2), int(1), int(2)].into_iter().collect());
+        assert!(engine.eval(tuple(items)).unwrap().total_eq(&expect));
+
+        engine.vars_mut().init_global("b", int(1));
+        let result = engine.eval(tuple(vec![splat("b").build_node(())]));
+        assert!(matches!(result, Err(EvalError::InvalidSplat { .. })));
+    }
+
+    #[test]
+    fn pow_nests_to_the_right() {
+        let mut engine = Engine::<()>::new();
+        let int = |i: i32| Box::new(Expr::Int(i.into()).build_node(()));
+
+        // 2 ** (3 ** 2)
+        let inner = Expr::Pow(int(3), int(2)).build_node(());
+        let value = engine.eval(Expr::Pow(int(2), Box::new(inner)).build_node(()));
+        assert!(matches!(value, Ok(Value::Float(f)) if f == 512.));
+    }
+
+    #[test]
+    fn unknown_field_source() {
+        let mut engine = Engine::<Range<usize>>::new();
+        let int = |i: i32| Value::Int(i.into());
+        let inner = Value::Tuple([int(1)].into_iter().collect());
+        let outer = Value::Tuple([inner, int(2)].into_iter().collect());
+        engine.vars_mut().init_global("t", outer);
+
+        // t.0.<field>
+        let access = |field: &str| {
+            let target = Expr::Var("t".into()).build_node(0..1);
+            let target = Expr::Field {
+                target: Box::new(target),
+                field: Node::new("0".into(), 2..3),
+            };
+            Expr::Field {
+                target: Box::new(target.build_node(0..3)),
+                field: Node::new(field.into(), 4..5),
+            }
+            .build_node(0..5)
+        };
+
+        let value = engine.eval(access("0")).unwrap();
+        assert!(value.total_eq(&int(1)));
+
+        // the error points at the missing field, not the whole access
+        let result = engine.eval(access("1"));
+        assert!(matches!(
+            result,
+            Err(EvalError::UnknownField { field, source, .. })
+                if field == "1" && source == (4..5)
+        ));
+    }
+
+    #[test]
+    fn const_reassign() {
+        let mut engine = Engine::<()>::new();
+        let var = || Expr::Var("x".into()).build_node(());
+        let int = |i: i32| Expr::Int(i.into()).build_node(());
+
+        engine.init_const(&var(), None, &int(1)).unwrap();
+        assert!(matches!(
+            engine.assign(&var(), None, &int(2)),
+            Err(EvalError::ReassignConst { name, .. }) if name == "x"
+        ));
+
+        let walrus = Expr::Walrus(Box::new(var()), Box::new(int(3))).build_node(());
+        assert!(engine.eval(walrus).is_err());
+        assert!(matches!(engine.vars().get("x"), Some(Value::Int(i)) if *i == 1.into()));
+
+        // redefining with let replaces the constant
+        engine.init_assign(&var(), None, &int(4)).unwrap();
+        assert!(engine.assign(&var(), None, &int(5)).is_ok());
+    }
+
+    #[test]
+    fn type_annotations() {
+        let mut engine = Engine::<()>::new();
+        let var = Expr::Var("x".into()).build_node(());
+        let int = Type::Int.build_node(());
+        let value = |expr: Expr<()>| expr.build_node(());
+
+        engine
+            .init_assign(&var, Some(&int), &value(Expr::Int(1.into())))
+            .unwrap();
+
+        let result = engine.init_assign(&var, Some(&int), &value(Expr::Float(1.)));
+        assert!(matches!(
+            result,
+            Err(EvalError::TypeMismatch {
+                expect: Type::Int,
+                found: ValueKind::Float,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        // fn name(n): if n == 0 then base else other(n - 1)
+        let parity = |base: bool, other: &str| {
+            let n = || Box::new(Expr::Var("n".into()).build_node(()));
+            let zero = Box::new(Expr::Int(0.into()).build_node(()));
+            let one = Box::new(Expr::Int(1.into()).build_node(()));
+            let call = Expr::Call {
+                name: other.into(),
+                params: vec![Expr::Sub(n(), one).build_node(())],
+            };
+            let body = Expr::Ternary {
+                cond: Box::new(Expr::Eq(n(), zero).build_node(())),
+                pass: Box::new(Expr::Bool(base).build_node(())),
+                fail: Box::new(call.build_node(())),
+            };
+            let func = Func {
+                vis: Visibility::Private.build_node(()),
+                params: vec!["n".into()],
+                output: None,
+                body: vec![Statement::Expr {
+                    expr: body.build_node(()),
+                    closed: false,
+                }
+                .build_node(())],
+            };
+            Expr::Func(func.build_node(())).build_node(())
+        };
+
+        // even is defined before odd, which it calls
+        let mut engine = Engine::<()>::new();
+        let var = |name: &str| Expr::Var(name.into()).build_node(());
+        engine
+            .init_assign(&var("even"), None, &parity(true, "odd"))
+            .unwrap();
+        engine
+            .init_assign(&var("odd"), None, &parity(false, "even"))
+            .unwrap();
+
+        let call = |name: &str, n: i32| Expr::Call {
+            name: name.into(),
+            params: vec![Expr::Int(n.into()).build_node(())],
+        };
+        let value = engine.eval(call("even", 10).build_node(()));
+        assert!(matches!(value, Ok(Value::Bool(true))));
+        let value = engine.eval(call("odd", 7).build_node(()));
+        assert!(matches!(value, Ok(Value::Bool(true))));
+        let value = engine.eval(call("even", 7).build_node(()));
+        assert!(matches!(value, Ok(Value::Bool(false))));
+    }
+
+    #[test]
+    fn return_type() {
+        let mut engine = Engine::<()>::new();
+        // fn(a) -> int: a
+        let func = FuncPtr::custom(Func {
+            vis: Visibility::Private.build_node(()),
+            params: vec!["a".into()],
+            output: Some(Type::Int.build_node(())),
+            body: vec![Statement::Expr {
+                expr: Expr::Var("a".into()).build_node(()),
+                closed: false,
+            }
+            .build_node(())],
+        });
+
+        let found = func.call(&(), vec![Value::Int(1.into())], &mut engine);
+        assert!(matches!(found, Ok(Value::Int(_))));
+
+        let found = func.call(&(), vec![Value::Float(1.)], &mut engine);
+        assert!(matches!(
+            found,
+            Err(EvalError::ReturnTypeMismatch {
+                expect: Type::Int,
+                found: ValueKind::Float,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn type_at_offset() {
+        let engine = Engine::<Range<usize>>::new();
+        let node = |expr: Expr<Range<usize>>, range: Range<usize>| Box::new(expr.build_node(range));
+
+        // let x = 1 + 2.5
+        let rhs = Expr::Add(
+            node(Expr::Int(1.into()), 8..9),
+            node(Expr::Float(2.5), 12..15),
+        );
+        let statement = Statement::Assign {
+            init: true,
+            lhs: *node(Expr::Var("x".into()), 4..5),
+            ty: None,
+            rhs: *node(rhs, 8..15),
+        }
+        .build_node(0..15);
+
+        let type_at = |offset| engine.type_at(&statement, |source| source.contains(&offset));
+        assert_eq!(type_at(4).as_deref(), Some("float"));
+        assert_eq!(type_at(8).as_deref(), Some("int"));
+        assert_eq!(type_at(10).as_deref(), Some("float"));
+        assert_eq!(type_at(13).as_deref(), Some("float"));
+        assert_eq!(type_at(2), None);
+
+        // variables are only known at runtime
+        let statement = Statement::Expr {
+            expr: *node(Expr::Var("x".into()), 0..1),
+            closed: false,
+        }
+        .build_node(0..1);
+        assert_eq!(engine.type_at(&statement, |_| true).as_deref(), Some("any"));
+    }
+
+    #[test]
+    fn use_modules() {
+        let var = |name: &str| Expr::Var(name.into()).build_node(());
+        let int = |value: i32| Expr::Int(value.into()).build_node(());
+        let use_path = |path: &str| Statement::Use {
+            path: path.to_string().build_node(()),
+            import: None,
+        };
+        let run = |engine: &mut Engine<()>, path: &str| engine.eval(use_path(path).build_node(()));
+
+        let mut engine = Engine::<()>::new();
+        assert!(matches!(
+            run(&mut engine, "lib/a.boba"),
+            Err(EvalError::CapabilityDenied {
+                capability: Capability::Io,
+                ..
+            })
+        ));
+
+        engine.allow(Capability::Io, true);
+        assert!(matches!(
+            run(&mut engine, "lib/a.boba"),
+            Err(EvalError::ImportFailed { .. })
+        ));
+
+        let loads = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = loads.clone();
+        engine.set_module_loader(move |path| {
+            counter.set(counter.get() + 1);
+            let statements = match path.to_str() {
+                // const x = 1; use "b.boba"
+                Some("lib/a.boba") => vec![
+                    Statement::Const {
+                        lhs: var("x"),
+                        ty: None,
+                        rhs: int(1),
+                    },
+                    use_path("b.boba"),
+                ],
+                // let y = 2; fn hidden(): none
+                Some("lib/b.boba") => vec![
+                    Statement::Assign {
+                        init: true,
+                        lhs: var("y"),
+                        ty: None,
+                        rhs: int(2),
+                    },
+                    Statement::Assign {
+                        init: true,
+                        lhs: var("hidden"),
+                        ty: None,
+                        rhs: Expr::Func(
+                            Func {
+                                vis: Visibility::Private.build_node(()),
+                                params: vec![],
+                                output: None,
+                                body: vec![],
+                            }
+                            .build_node(()),
+                        )
+                        .build_node(()),
+                    },
+                ],
+                // z
+                Some("peek.boba") => vec![Statement::Expr {
+                    expr: var("z"),
+                    closed: false,
+                }],
+                Some("cycle.boba") => vec![use_path("./cycle.boba")],
+                _ => return Err("file not found".into()),
+            };
+            Ok(statements.into_iter().map(|s| s.build_node(())).collect())
+        });
+
+        // modules are linked into the importing scope, including their own imports
+        run(&mut engine, "lib/a.boba").unwrap();
+        assert!(matches!(engine.get_variable("x"), Some(Value::Int(i)) if *i == 1.into()));
+        assert!(matches!(engine.get_variable("y"), Some(Value::Int(i)) if *i == 2.into()));
+        assert!(engine.vars().is_const("x"));
+        assert!(engine.get_variable("hidden").is_none());
+        assert_eq!(loads.get(), 2);
+
+        // modules are only loaded once, so importing them again binds nothing new
+        run(&mut engine, "./lib/../lib/a.boba").unwrap();
+        assert_eq!(loads.get(), 2);
+
+        // but their values cannot replace other values
+        engine.init_assign(&var("x"), None, &int(5)).unwrap();
+        assert!(matches!(
+            run(&mut engine, "lib/a.boba"),
+            Err(EvalError::ImportConflict { name, .. }) if name == "x"
+        ));
+        assert!(matches!(engine.get_variable("x"), Some(Value::Int(i)) if *i == 5.into()));
+
+        // modules cannot see the program importing them
+        engine.init_assign(&var("z"), None, &int(3)).unwrap();
+        assert!(matches!(
+            run(&mut engine, "peek.boba"),
+            Err(EvalError::UnknownVariable { name, .. }) if name == "z"
+        ));
+        assert!(matches!(engine.get_variable("z"), Some(Value::Int(_))));
+
+        assert!(matches!(
+            run(&mut engine, "cycle.boba"),
+            Err(EvalError::CircularImport { path, .. }) if path == "./cycle.boba"
+        ));
+        assert!(matches!(
+            run(&mut engine, "missing.boba"),
+            Err(EvalError::ImportFailed { message, .. }) if message == "file not found"
+        ));
+    }
+
+    #[test]
+    fn selective_imports() {
+        let func = |vis: Visibility| {
+            let func = Func {
+                vis: vis.build_node(()),
+                params: vec!["n".into()],
+                output: None,
+                body: vec![Statement::Expr {
+                    expr: Expr::Var("n".into()).build_node(()),
+                    closed: false,
+                }
+                .build_node(())],
+            };
+            Expr::Func(func.build_node(())).build_node(())
+        };
+        let define = |name: &str, rhs: ExprNode<()>| Statement::Assign {
+            init: true,
+            lhs: Expr::Var(name.into()).build_node(()),
+            ty: None,
+            rhs,
+        };
+
+        let mut engine = Engine::<()>::new();
+        engine.allow(Capability::Io, true);
+        engine.set_module_loader(move |_| {
+            // pub fn shown(n): n
+            // fn hidden(n): n
+            // const limit = 3
+            let statements = vec![
+                define("shown", func(Visibility::Public)),
+                define("hidden", func(Visibility::Private)),
+                Statement::Const {
+                    lhs: Expr::Var("limit".into()).build_node(()),
+                    ty: None,
+                    rhs: Expr::Int(3.into()).build_node(()),
+                },
+            ];
+            Ok(statements.into_iter().map(|s| s.build_node(())).collect())
+        });
+
+        let mut run = |name: &str, alias: Option<&str>| {
+            let import = Import {
+                name: Symbol::from(name).build_node(()),
+                alias: alias.map(|alias| Symbol::from(alias).build_node(())),
+            };
+            let path = "math.boba".to_string().build_node(());
+            let statement = Statement::Use {
+                path,
+                import: Some(import),
+            };
+            engine.eval(statement.build_node(()))
+        };
+
+        assert!(run("shown", None).is_ok());
+        assert!(run("limit", Some("cap")).is_ok());
+        assert!(matches!(
+            run("hidden", None),
+            Err(EvalError::PrivateAccess { name, .. }) if name == "hidden"
+        ));
+        assert!(matches!(
+            run("missing", None),
+            Err(EvalError::UnknownVariable { name, .. }) if name == "missing"
+        ));
+
+        // imports cannot replace existing names, including builtins
+        assert!(run("shown", None).is_ok());
+        assert!(matches!(
+            run("limit", Some("shown")),
+            Err(EvalError::ImportConflict { name, .. }) if name == "shown"
+        ));
+        assert!(matches!(
+            run("shown", Some("join")),
+            Err(EvalError::ImportConflict { name, .. }) if name == "join"
+        ));
+        assert!(run("shown", Some("other")).is_ok());
+
+        assert!(matches!(engine.get_variable("shown"), Some(Value::Func(_))));
+        assert!(matches!(engine.get_variable("other"), Some(Value::Func(_))));
+        assert!(engine.get_variable("hidden").is_none());
+        assert!(engine.get_variable("limit").is_none());
+        assert!(engine.vars().is_const("cap"));
+    }
+
+    #[test]
+    fn module_scope() {
+        fn var(name: &str) -> ExprNode<()> {
+            Expr::Var(name.into()).build_node(())
+        }
+        let call = |name: &str, params: Vec<ExprNode<()>>| {
+            Expr::Call {
+                name: name.into(),
+                params,
+            }
+            .build_node(())
+        };
+        let define = |name: &str, vis: Visibility, params: &[&str], body: ExprNode<()>| {
+            let func = Func {
+                vis: vis.build_node(()),
+                params: params.iter().map(|&param| param.into()).collect(),
+                output: None,
+                body: vec![Statement::Expr {
+                    expr: body,
+                    closed: false,
+                }
+                .build_node(())],
+            };
+            Statement::Assign {
+                init: true,
+                lhs: var(name),
+                ty: None,
+                rhs: Expr::Func(func.build_node(())).build_node(()),
+            }
+        };
+
+        let mut engine = Engine::<()>::new();
+        engine.allow(Capability::Io, true);
+        engine.set_module_loader(move |_| {
+            // pub fn api(n): helper(n)
+            // fn helper(n): n + limit
+            // const limit = 3
+            // pub fn lim(): limit
+            let statements = vec![
+                define(
+                    "api",
+                    Visibility::Public,
+                    &["n"],
+                    call("helper", vec![var("n")]),
+                ),
+                define(
+                    "helper",
+                    Visibility::Private,
+                    &["n"],
+                    Expr::Add(Box::new(var("n")), Box::new(var("limit"))).build_node(()),
+                ),
+                Statement::Const {
+                    lhs: var("limit"),
+                    ty: None,
+                    rhs: Expr::Int(3.into()).build_node(()),
+                },
+                define("lim", Visibility::Public, &[], var("limit")),
+            ];
+            Ok(statements.into_iter().map(|s| s.build_node(())).collect())
+        });
+
+        let use_module = Statement::Use {
+            path: "m.boba".to_string().build_node(()),
+            import: None,
+        };
+        engine.eval(use_module.build_node(())).unwrap();
+        assert!(engine.get_variable("helper").is_none());
+
+        // imported functions still see the top level of their own module
+        let value = engine.eval(call("api", vec![Expr::Int(1.into()).build_node(())]));
+        assert!(matches!(value, Ok(Value::Int(i)) if i == 4.into()));
+        let value = engine.eval(call("lim", vec![]));
+        assert!(matches!(value, Ok(Value::Int(i)) if i == 3.into()));
+
+        // but the program importing them still cannot
+        let value = engine.eval(call("helper", vec![Expr::Int(1.into()).build_node(())]));
+        assert!(matches!(value, Err(EvalError::UnknownFunction { .. })));
+    }
+}
@@ -1,4 +1,6 @@
-use super::value::ValueKind;
+use crate::ast::Type;
+
+use super::{value::ValueKind, Capability};
 
 #[derive(Debug, Clone)]
 pub enum EvalError<Source> {
@@ -23,6 +25,8 @@ pub enum EvalError<Source> {
     InvalidTupleSize {
         lhs_count: usize,
         rhs_count: usize,
+        /// The pattern captures a rest item, so it needs at least `lhs_count` items.
+        rest: bool,
         lhs_source: Source,
         rhs_source: Source,
     },
@@ -45,6 +49,10 @@ pub enum EvalError<Source> {
         message: String,
         source: Source,
     },
+    CapabilityDenied {
+        capability: Capability,
+        source: Source,
+    },
     UnknownFunction {
         name: String,
         source: Source,
@@ -54,4 +62,158 @@ pub enum EvalError<Source> {
         found: ValueKind,
         source: Source,
     },
+    UnknownField {
+        field: String,
+        ty: ValueKind,
+        source: Source,
+    },
+    StringAllocError {
+        source: Source,
+    },
+    AllocationLimit {
+        size: usize,
+        limit: usize,
+        source: Source,
+    },
+    ReassignConst {
+        name: String,
+        source: Source,
+    },
+    InvalidSplat {
+        found: ValueKind,
+        source: Source,
+    },
+    InvalidPattern {
+        source: Source,
+    },
+    DuplicateRest {
+        source: Source,
+    },
+    DivideByZero {
+        source: Source,
+    },
+    ImmutableParam {
+        source: Source,
+    },
+    ImportFailed {
+        path: String,
+        message: String,
+        source: Source,
+    },
+    CircularImport {
+        path: String,
+        source: Source,
+    },
+    PrivateAccess {
+        name: String,
+        source: Source,
+    },
+    ImportConflict {
+        name: String,
+        source: Source,
+    },
+    TypeMismatch {
+        expect: Type,
+        found: ValueKind,
+        source: Source,
+    },
+    ReturnTypeMismatch {
+        expect: Type,
+        found: ValueKind,
+        source: Source,
+        output_source: Source,
+    },
+}
+
+impl<Source> EvalError<Source> {
+    /// Returns true if a `try` expression may catch this error.
+    ///
+    /// Resource limits and denied capabilities always abort evaluation,
+    /// so that scripts cannot work around the limits set by the embedder.
+    pub fn is_recoverable(&self) -> bool {
+        !matches!(
+            self,
+            Self::AllocationLimit { .. }
+                | Self::StringAllocError { .. }
+                | Self::CapabilityDenied { .. }
+        )
+    }
+
+    /// Describes the error in a single line, without its source.
+    pub fn message(&self) -> String {
+        match self {
+            Self::UnknownVariable { name, .. } => format!("unknown variable {name}"),
+            Self::InvalidUnaryOp { ty, op, .. } => {
+                format!("'{op}' operator is not valid for '{ty}' types")
+            }
+            Self::InvalidBinaryOp { ty1, ty2, op, .. } => {
+                format!("'{ty1}' does not have a valid '{op}' operator for '{ty2}' types")
+            }
+            Self::InvalidAssign { .. } => "cannot assign to this expression".into(),
+            Self::InvalidTupleSize {
+                lhs_count,
+                rhs_count,
+                rest: false,
+                ..
+            } => format!("expected tuple with {lhs_count} parameters, found {rhs_count}"),
+            Self::InvalidTupleSize {
+                lhs_count,
+                rhs_count,
+                rest: true,
+                ..
+            } => format!("expected tuple with at least {lhs_count} items, found {rhs_count}"),
+            Self::InvalidTupleDestructure { lhs_count, .. } => {
+                format!("cannot destructure into tuple with {lhs_count} params")
+            }
+            Self::UnexpectedType { expect, found, .. } => {
+                format!("expected '{expect}', found '{found}'")
+            }
+            Self::InvalidParameters { found, expect, .. } => {
+                format!("function expects {expect} param(s). only {found} were provided")
+            }
+            Self::NativeCall { message, .. } => message.clone(),
+            Self::CapabilityDenied { capability, .. } => {
+                format!("this call requires the '{capability}' capability")
+            }
+            Self::UnknownFunction { name, .. } => format!("unknown function {name}"),
+            Self::NotAFunction { name, found, .. } => {
+                format!("'{name}' is not a function, it is a value with type '{found}'")
+            }
+            Self::UnknownField { field, ty, .. } => {
+                format!("'{ty}' does not have a field '{field}'")
+            }
+            Self::StringAllocError { .. } => "resulting string is too large to allocate".into(),
+            Self::AllocationLimit { size, limit, .. } => {
+                format!("value of size {size} exceeds the allocation limit of {limit}")
+            }
+            Self::ReassignConst { name, .. } => format!("cannot reassign constant '{name}'"),
+            Self::InvalidSplat { found, .. } => {
+                format!("cannot splat '{found}', expected a tuple or list")
+            }
+            Self::InvalidPattern { .. } => "patterns can only be assigned to".into(),
+            Self::DuplicateRest { .. } => "a pattern can only capture one rest item".into(),
+            Self::DivideByZero { .. } => "cannot divide an int by zero".into(),
+            Self::ImmutableParam { .. } => {
+                "this call mutates its first param, which must be a variable".into()
+            }
+            Self::ImportFailed { path, message, .. } => {
+                format!("could not import '{path}': {message}")
+            }
+            Self::CircularImport { path, .. } => {
+                format!("'{path}' is already being imported, so it cannot import itself")
+            }
+            Self::PrivateAccess { name, .. } => {
+                format!("'{name}' is private and cannot be imported")
+            }
+            Self::ImportConflict { name, .. } => {
+                format!("cannot import as '{name}', the name is already defined")
+            }
+            Self::TypeMismatch { expect, found, .. } => {
+                format!("expected '{expect}', found '{found}'")
+            }
+            Self::ReturnTypeMismatch { expect, found, .. } => {
+                format!("function returned '{found}', but is declared to return '{expect}'")
+            }
+        }
+    }
 }
@@ -1,11 +1,22 @@
+#[allow(clippy::module_inception)]
 mod engine;
 
 pub mod builtins;
+pub mod cache;
+pub mod capability;
+pub mod check;
 pub mod error;
+pub mod module;
 pub mod ops;
+pub mod prelude;
+pub mod rng;
 pub mod value;
 
 pub use engine::*;
+pub use module::ModuleLoader;
+pub use prelude::Prelude;
+
+pub use capability::{Capabilities, Capability};
 
 pub use error::EvalError;
 pub use value::Value;
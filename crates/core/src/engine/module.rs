@@ -0,0 +1,121 @@
+use std::{
+    cell::OnceCell,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use fxhash::FxHashMap;
+
+use crate::{
+    ast::{StatementNode, Symbol},
+    engine::Value,
+};
+
+/// Reads and parses the module at a resolved path.
+///
+/// Errors are returned as a message describing why the module could not be loaded.
+pub type ModuleLoader<Source> = dyn FnMut(&Path) -> Result<Vec<StatementNode<Source>>, String>;
+
+/// The top level bindings of a module, along with whether each is a constant.
+pub(crate) type Exports<Source> = Vec<(Symbol, Value<Source>, bool)>;
+
+/// The top level bindings of a module, which the functions it defines resolve names against.
+///
+/// The bindings are set once the module has been evaluated.
+pub struct ModuleScope<Source> {
+    exports: OnceCell<Exports<Source>>,
+}
+
+impl<Source> Default for ModuleScope<Source> {
+    fn default() -> Self {
+        Self {
+            exports: Default::default(),
+        }
+    }
+}
+
+impl<Source> ModuleScope<Source> {
+    pub(crate) fn exports(&self) -> Option<&Exports<Source>> {
+        self.exports.get()
+    }
+
+    pub(crate) fn set_exports(&self, exports: Exports<Source>) {
+        let _ = self.exports.set(exports);
+    }
+
+    /// Gets a top level binding of the module, along with whether it is a constant.
+    pub(crate) fn get(&self, id: Symbol) -> Option<(&Value<Source>, bool)> {
+        let exports = self.exports.get()?;
+        exports
+            .iter()
+            .find(|(name, ..)| *name == id)
+            .map(|(_, value, constant)| (value, *constant))
+    }
+}
+
+pub(crate) struct Modules<Source> {
+    pub loader: Option<Box<ModuleLoader<Source>>>,
+    pub loaded: FxHashMap<PathBuf, Rc<ModuleScope<Source>>>,
+    pub loading: Vec<PathBuf>,
+}
+
+impl<Source> Default for Modules<Source> {
+    fn default() -> Self {
+        Self {
+            loader: None,
+            loaded: Default::default(),
+            loading: Default::default(),
+        }
+    }
+}
+
+impl<Source> Modules<Source> {
+    /// Resolves `path` relative to the directory of the module being imported,
+    /// or the working directory if no module is being imported.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        let base = match self.loading.last() {
+            Some(module) => module.parent().unwrap_or(Path::new("")),
+            None => Path::new(""),
+        };
+
+        normalize(&base.join(path))
+    }
+}
+
+/// Removes `.` and `..` components from a path without touching the file system,
+/// so the same module is always found under the same path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normal.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normal.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normal.push(".."),
+            },
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_relative_paths() {
+        let mut modules = Modules::<()>::default();
+        assert_eq!(modules.resolve("./a/../b.boba"), PathBuf::from("b.boba"));
+        assert_eq!(modules.resolve("../b.boba"), PathBuf::from("../b.boba"));
+
+        modules.loading.push(PathBuf::from("lib/util/mod.boba"));
+        assert_eq!(
+            modules.resolve("../math.boba"),
+            PathBuf::from("lib/math.boba")
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::marker::PhantomData;
 
-use dashu::base::Sign;
+use dashu::{base::Sign, integer::IBig};
 
 use super::Value;
 
@@ -24,7 +24,7 @@ impl<Source> OpManager<Source> {
     pub fn pos(&self, v: &Value<Source>) -> Option<Value<Source>> {
         match v {
             Value::Int(v) => Some(Value::Int(v.clone())),
-            Value::Float(v) => Some(Value::Float(v.clone())),
+            Value::Float(v) => Some(Value::Float(*v)),
             _ => None,
         }
     }
@@ -55,10 +55,14 @@ impl<Source> OpManager<Source> {
             (Value::Float(v1), Value::Float(v2)) => Some(Value::Float(v1 + v2)),
 
             // STRING
+            // strings concatenate with primitives on either side, but never with none
             (Value::String(v1), Value::String(v2)) => Some(Value::String(format!("{v1}{v2}"))),
             (Value::String(v1), Value::Bool(v2)) => Some(Value::String(format!("{v1}{v2}"))),
             (Value::String(v1), Value::Int(v2)) => Some(Value::String(format!("{v1}{v2}"))),
             (Value::String(v1), Value::Float(v2)) => Some(Value::String(format!("{v1}{v2}"))),
+            (Value::Bool(v1), Value::String(v2)) => Some(Value::String(format!("{v1}{v2}"))),
+            (Value::Int(v1), Value::String(v2)) => Some(Value::String(format!("{v1}{v2}"))),
+            (Value::Float(v1), Value::String(v2)) => Some(Value::String(format!("{v1}{v2}"))),
 
             // FAIL
             _ => None,
@@ -91,22 +95,14 @@ impl<Source> OpManager<Source> {
             (Value::Float(v1), Value::Float(v2)) => Some(Value::Float(v1 * v2)),
 
             // STRING
-            (Value::String(v1), Value::Bool(v2)) => match v2 {
+            // repetition is the same regardless of which side the string is on
+            (Value::String(v1), Value::Bool(v2)) | (Value::Bool(v2), Value::String(v1)) => match v2
+            {
                 false => Some(Value::String("".into())),
                 true => Some(Value::String(v1.clone())),
             },
-            (Value::String(v1), Value::Int(v2)) => {
-                let (sign, ubig) = v2.clone().into_parts();
-                if let Sign::Negative = sign {
-                    return Some(Value::String("".into()));
-                }
-
-                let count = match TryInto::<usize>::try_into(ubig) {
-                    Ok(count) => count,
-                    Err(_) => usize::MAX,
-                };
-
-                Some(Value::String(v1.repeat(count)))
+            (Value::String(v1), Value::Int(v2)) | (Value::Int(v2), Value::String(v1)) => {
+                Some(Value::String(self.repeat(v1, v2)?))
             }
 
             // FAIL
@@ -114,6 +110,30 @@ impl<Source> OpManager<Source> {
         }
     }
 
+    /// Repeats a string `count` times.
+    ///
+    /// Returns `None` if the resulting string could not be allocated.
+    pub fn repeat(&self, string: &str, count: &IBig) -> Option<String> {
+        let (sign, ubig) = count.clone().into_parts();
+        if let Sign::Negative = sign {
+            return Some("".into());
+        }
+
+        if string.is_empty() {
+            return Some("".into());
+        }
+
+        let count = TryInto::<usize>::try_into(ubig).ok()?;
+        let len = string.len().checked_mul(count)?;
+        let mut output = String::new();
+        output.try_reserve_exact(len).ok()?;
+        for _ in 0..count {
+            output.push_str(string);
+        }
+
+        Some(output)
+    }
+
     pub fn div(&self, v1: &Value<Source>, v2: &Value<Source>) -> Option<Value<Source>> {
         match (v1, v2) {
             // INT
@@ -165,25 +185,35 @@ impl<Source> OpManager<Source> {
         }
     }
 
+    /// Compares two values, walking tuples, lists, maps and results item by item.
+    ///
+    /// Any value can be compared with none, and is only equal to none itself.
+    /// Returns `None` if any other pair of items cannot be compared.
     pub fn eq(&self, v1: &Value<Source>, v2: &Value<Source>) -> Option<Value<Source>> {
-        match (v1, v2) {
+        let equal = v1.structural_eq(v2, |v1, v2| match (v1, v2) {
             // INT
-            (Value::Int(v1), Value::Int(v2)) => Some(Value::Bool(v1 == v2)),
-            (Value::Int(v1), Value::Float(v2)) => Some(Value::Bool(v1.to_f64().value_ref() == v2)),
+            (Value::Int(v1), Value::Int(v2)) => Some(v1 == v2),
+            (Value::Int(v1), Value::Float(v2)) => Some(v1.to_f64().value_ref() == v2),
 
             // FLOAT
-            (Value::Float(v1), Value::Int(v2)) => Some(Value::Bool(v1 == v2.to_f64().value_ref())),
-            (Value::Float(v1), Value::Float(v2)) => Some(Value::Bool(v1 == v2)),
+            (Value::Float(v1), Value::Int(v2)) => Some(v1 == v2.to_f64().value_ref()),
+            (Value::Float(v1), Value::Float(v2)) => Some(v1 == v2),
 
             // STRING
-            (Value::String(v1), Value::String(v2)) => Some(Value::Bool(v1 == v2)),
+            (Value::String(v1), Value::String(v2)) => Some(v1 == v2),
 
             // BOOLEAN
-            (Value::Bool(v1), Value::Bool(v2)) => Some(Value::Bool(v1 == v2)),
+            (Value::Bool(v1), Value::Bool(v2)) => Some(v1 == v2),
+
+            // NONE
+            (Value::None, Value::None) => Some(true),
+            (Value::None, _) | (_, Value::None) => Some(false),
 
             // FAIL
             _ => None,
-        }
+        })?;
+
+        Some(Value::Bool(equal))
     }
 
     pub fn lt(&self, v1: &Value<Source>, v2: &Value<Source>) -> Option<Value<Source>> {
@@ -229,22 +259,8 @@ impl<Source> OpManager<Source> {
     }
 
     pub fn neq(&self, v1: &Value<Source>, v2: &Value<Source>) -> Option<Value<Source>> {
-        match (v1, v2) {
-            // INT
-            (Value::Int(v1), Value::Int(v2)) => Some(Value::Bool(v1 != v2)),
-            (Value::Int(v1), Value::Float(v2)) => Some(Value::Bool(v1.to_f64().value_ref() != v2)),
-
-            // FLOAT
-            (Value::Float(v1), Value::Int(v2)) => Some(Value::Bool(v1 != v2.to_f64().value_ref())),
-            (Value::Float(v1), Value::Float(v2)) => Some(Value::Bool(v1 != v2)),
-
-            // STRING
-            (Value::String(v1), Value::String(v2)) => Some(Value::Bool(v1 != v2)),
-
-            // BOOLEAN
-            (Value::Bool(v1), Value::Bool(v2)) => Some(Value::Bool(v1 != v2)),
-
-            // FAIL
+        match self.eq(v1, v2)? {
+            Value::Bool(eq) => Some(Value::Bool(!eq)),
             _ => None,
         }
     }
@@ -311,3 +327,182 @@ impl<Source> OpManager<Source> {
         }
     }
 }
+
+/// A unary operator that can be applied by an [`OpManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Pos,
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOp::Pos => "+",
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "not",
+        }
+    }
+}
+
+/// A binary operator that can be applied by an [`OpManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Modulo,
+    Pow,
+    Eq,
+    Lt,
+    Gt,
+    NEq,
+    LtEq,
+    GtEq,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Pow => "**",
+            BinaryOp::Eq => "==",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::NEq => "!=",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+        }
+    }
+}
+
+impl<Source> OpManager<Source> {
+    pub fn unary(&self, op: UnaryOp, v: &Value<Source>) -> Option<Value<Source>> {
+        match op {
+            UnaryOp::Pos => self.pos(v),
+            UnaryOp::Neg => self.neg(v),
+            UnaryOp::Not => self.not(v),
+        }
+    }
+
+    pub fn binary(
+        &self,
+        op: BinaryOp,
+        v1: &Value<Source>,
+        v2: &Value<Source>,
+    ) -> Option<Value<Source>> {
+        match op {
+            BinaryOp::Add => self.add(v1, v2),
+            BinaryOp::Sub => self.sub(v1, v2),
+            BinaryOp::Mul => self.mul(v1, v2),
+            BinaryOp::Div => self.div(v1, v2),
+            BinaryOp::Modulo => self.modulo(v1, v2),
+            BinaryOp::Pow => self.pow(v1, v2),
+            BinaryOp::Eq => self.eq(v1, v2),
+            BinaryOp::Lt => self.lt(v1, v2),
+            BinaryOp::Gt => self.gt(v1, v2),
+            BinaryOp::NEq => self.neq(v1, v2),
+            BinaryOp::LtEq => self.lteq(v1, v2),
+            BinaryOp::GtEq => self.gteq(v1, v2),
+            BinaryOp::And => self.and(v1, v2),
+            BinaryOp::Or => self.or(v1, v2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::value::{Map, MapKey};
+
+    #[test]
+    fn string_repeat() {
+        let ops = OpManager::<()>::new();
+        assert_eq!(ops.repeat("ab", &3.into()), Some("ababab".into()));
+        assert_eq!(ops.repeat("ab", &(-3).into()), Some("".into()));
+        assert_eq!(ops.repeat("", &IBig::from(u128::MAX)), Some("".into()));
+
+        // too large to allocate
+        assert_eq!(ops.repeat("ab", &IBig::from(u128::MAX)), None);
+        assert_eq!(ops.repeat("ab", &IBig::from(usize::MAX)), None);
+    }
+
+    #[test]
+    fn symmetric_string_ops() {
+        let ops = OpManager::<()>::new();
+        let string = |s: &str| Value::String(s.into());
+        let int = |i: i32| Value::Int(i.into());
+
+        assert_eq!(ops.add(&string("a"), &int(1)), Some(string("a1")));
+        assert_eq!(ops.add(&int(1), &string("a")), Some(string("1a")));
+        assert_eq!(
+            ops.add(&Value::Bool(true), &string("a")),
+            Some(string("truea"))
+        );
+        assert_eq!(ops.mul(&string("ab"), &int(2)), Some(string("abab")));
+        assert_eq!(ops.mul(&int(2), &string("ab")), Some(string("abab")));
+        assert_eq!(
+            ops.mul(&Value::Bool(false), &string("ab")),
+            Some(string(""))
+        );
+
+        // none is never implicitly converted to a string
+        assert_eq!(ops.add(&string("a"), &Value::None), None);
+        assert_eq!(ops.add(&Value::None, &string("a")), None);
+        assert_eq!(ops.add(&Value::None, &Value::None), None);
+    }
+
+    #[test]
+    fn unit_equality() {
+        let ops = OpManager::<()>::new();
+        let tuple = |items: Vec<Value<()>>| Value::Tuple(items.into_iter().collect());
+        let unit = tuple(vec![]);
+
+        assert_eq!(ops.eq(&unit, &unit), Some(Value::Bool(true)));
+        assert_eq!(ops.neq(&unit, &unit), Some(Value::Bool(false)));
+        assert_eq!(ops.eq(&Value::None, &Value::None), Some(Value::Bool(true)));
+        assert_eq!(ops.eq(&unit, &Value::None), Some(Value::Bool(false)));
+        assert_eq!(ops.neq(&Value::None, &unit), Some(Value::Bool(true)));
+
+        let pair = tuple(vec![Value::Int(1.into()), unit.clone()]);
+        assert_eq!(ops.eq(&pair, &pair), Some(Value::Bool(true)));
+        assert_eq!(ops.eq(&pair, &unit), Some(Value::Bool(false)));
+        assert_eq!(ops.neq(&pair, &unit), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn collection_equality() {
+        let ops = OpManager::<()>::new();
+        let int = |i: i32| Value::Int(i.into());
+        let list = |items: Vec<Value<()>>| Value::List(items.into_iter().collect());
+
+        let a = list(vec![int(1), list(vec![Value::Float(2.)])]);
+        let b = list(vec![Value::Float(1.), list(vec![int(2)])]);
+        assert_eq!(ops.eq(&a, &b), Some(Value::Bool(true)));
+        assert_eq!(ops.neq(&a, &b), Some(Value::Bool(false)));
+        assert_eq!(ops.eq(&a, &list(vec![int(1)])), Some(Value::Bool(false)));
+        assert_eq!(
+            ops.eq(&list(vec![int(1)]), &list(vec![Value::Bool(true)])),
+            None
+        );
+
+        let mut map = Map::new();
+        map.insert(MapKey::new(Value::String("a".into())).unwrap(), a);
+        let map = Value::Map(map);
+        assert_eq!(ops.eq(&map, &map.clone()), Some(Value::Bool(true)));
+        assert_eq!(
+            ops.eq(&map, &Value::Map(Map::new())),
+            Some(Value::Bool(false))
+        );
+    }
+}
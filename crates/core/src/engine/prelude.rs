@@ -0,0 +1,236 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+};
+
+use fxhash::FxHashMap;
+
+use crate::Engine;
+
+use super::{Capabilities, Value};
+
+/// The builtins every program can use without importing them, grouped into modules.
+///
+/// A prelude can be assembled once and cloned into any number of engines,
+/// which then share its functions instead of building their own.
+pub struct Prelude<Source> {
+    modules: Vec<PreludeModule<Source>>,
+}
+
+/// A named group of related builtins, like `math` or `string`.
+pub struct PreludeModule<Source> {
+    name: &'static str,
+    values: Vec<(&'static str, Value<Source>)>,
+}
+
+impl<Source> Clone for Prelude<Source> {
+    fn clone(&self) -> Self {
+        Self {
+            modules: self.modules.clone(),
+        }
+    }
+}
+
+impl<Source> Clone for PreludeModule<Source> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<Source> Default for Prelude<Source> {
+    fn default() -> Self {
+        Self {
+            modules: Vec::new(),
+        }
+    }
+}
+
+impl<Source> Prelude<Source> {
+    /// Creates a prelude without any modules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assembles a new copy of the standard prelude.
+    ///
+    /// Use [`Prelude::shared`] to reuse the functions of one that is already assembled.
+    pub fn standard() -> Self {
+        super::builtins::prelude()
+    }
+
+    /// Adds a value to `module`, creating the module if it does not exist yet.
+    ///
+    /// A value with the same name in the same module is replaced.
+    pub fn insert(&mut self, module: &'static str, name: &'static str, value: Value<Source>) {
+        let index = match self.modules.iter().position(|m| m.name == module) {
+            Some(index) => index,
+            None => {
+                self.modules.push(PreludeModule {
+                    name: module,
+                    values: Vec::new(),
+                });
+                self.modules.len() - 1
+            }
+        };
+
+        let values = &mut self.modules[index].values;
+        match values.iter_mut().find(|(old, _)| *old == name) {
+            Some(old) => old.1 = value,
+            None => values.push((name, value)),
+        }
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &PreludeModule<Source>> {
+        self.modules.iter()
+    }
+
+    pub fn module(&self, name: &str) -> Option<&PreludeModule<Source>> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+
+    /// Gets a value from whichever module defines it.
+    pub fn get(&self, name: &str) -> Option<&Value<Source>> {
+        self.modules.iter().find_map(|module| module.get(name))
+    }
+
+    /// Removes every function that needs a capability outside of `capabilities`,
+    /// so sandboxed scripts do not even see the names of what they cannot call.
+    pub fn retain_capabilities(&mut self, capabilities: Capabilities) {
+        for module in &mut self.modules {
+            module.values.retain(|(_, value)| match value {
+                Value::Func(func) => func
+                    .capability()
+                    .is_none_or(|capability| capabilities.contains(capability)),
+                _ => true,
+            });
+        }
+        self.modules.retain(|module| !module.values.is_empty());
+    }
+
+    /// Defines every value in the prelude as a global of `engine`.
+    pub fn load_into(self, engine: &mut Engine<Source>) {
+        for module in self.modules {
+            for (name, value) in module.values {
+                engine.vars_mut().init_global(name, value);
+            }
+        }
+    }
+}
+
+impl<Source: 'static> Prelude<Source> {
+    /// Clones the standard prelude that [`Engine::new`] loads.
+    ///
+    /// It is only assembled the first time it is used on each thread,
+    /// so every engine on that thread shares the same functions.
+    pub fn shared() -> Self {
+        thread_local! {
+            static STANDARD: RefCell<FxHashMap<TypeId, Box<dyn Any>>> = Default::default();
+        }
+
+        STANDARD.with_borrow_mut(|preludes| {
+            let prelude = preludes
+                .entry(TypeId::of::<Source>())
+                .or_insert_with(|| Box::new(Self::standard()));
+            match prelude.downcast_ref::<Self>() {
+                Some(prelude) => prelude.clone(),
+                None => unreachable!("preludes are stored by their source type"),
+            }
+        })
+    }
+}
+
+impl<Source> PreludeModule<Source> {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.values.iter().map(|(name, _)| *name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value<Source>> {
+        let (_, value) = self.values.iter().find(|(old, _)| *old == name)?;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::Capability;
+
+    use super::*;
+
+    #[test]
+    fn standard_modules() {
+        let prelude = Prelude::<()>::standard();
+        let math = prelude.module("math").unwrap();
+        assert!(math.names().any(|name| name == "clamp"));
+        assert!(prelude.get("print").is_some());
+        assert!(prelude.get("missing").is_none());
+
+        // every builtin is in exactly one module
+        let mut names = prelude
+            .modules()
+            .flat_map(|m| m.names())
+            .collect::<Vec<_>>();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn shared_between_engines() {
+        let prelude = Prelude::<()>::standard();
+        let first = Engine::with_prelude(prelude.clone());
+        let second = Engine::with_prelude(prelude);
+
+        let (Some(Value::Func(a)), Some(Value::Func(b))) =
+            (first.get_variable("print"), second.get_variable("print"))
+        else {
+            panic!("expected print in both engines");
+        };
+        assert!(a.ptr_eq(b));
+        assert!(Engine::<()>::empty().get_variable("print").is_none());
+    }
+
+    #[test]
+    fn shared_standard_prelude() {
+        let first = Engine::<()>::new();
+        let second = Engine::<()>::new();
+
+        let (Some(Value::Func(a)), Some(Value::Func(b))) =
+            (first.get_variable("print"), second.get_variable("print"))
+        else {
+            panic!("expected print in both engines");
+        };
+        assert!(a.ptr_eq(b));
+
+        // a standard prelude is always a fresh copy
+        let fresh = Prelude::<()>::standard();
+        let Some(Value::Func(c)) = fresh.get("print") else {
+            panic!("expected print in the prelude");
+        };
+        assert!(!a.ptr_eq(c));
+    }
+
+    #[test]
+    fn retained_capabilities() {
+        let mut prelude = Prelude::<()>::standard();
+        let mut capabilities = Capabilities::none();
+        capabilities.insert(Capability::Time);
+        prelude.retain_capabilities(capabilities);
+
+        assert!(prelude.get("now").is_some());
+        assert!(prelude.get("read_file").is_none());
+        assert!(prelude.get("random").is_none());
+        assert!(prelude.get("print").is_some());
+
+        // modules left without any values are removed
+        assert!(prelude.module("io").is_none());
+        assert!(prelude.module("time").is_some());
+    }
+}
@@ -2,25 +2,48 @@ use std::{
     fmt::{self, Debug},
     marker::PhantomData,
     ops::Deref,
-    rc::Rc,
+    rc::{Rc, Weak},
 };
 
 use derive_more::Display;
 
-use crate::{ast::func::Func, engine::EvalError, Engine};
+use crate::{
+    ast::{func::Func, Visibility},
+    engine::{module::ModuleScope, Capability, EvalError},
+    vm::Chunk,
+    Engine,
+};
 
 use super::Value;
 
+pub type NativeFn<Source> = fn(Vec<Value<Source>>) -> Result<Value<Source>, String>;
+
+/// A native function that can access the engine calling it.
+pub type EngineFn<Source> =
+    fn(&mut Engine<Source>, Vec<Value<Source>>) -> Result<Value<Source>, String>;
+
+/// A native function that mutates its first parameter in place.
+///
+/// Collections have value semantics, so the first parameter must be a variable,
+/// which is updated with the mutated value once the call returns.
+pub type MutFn<Source> =
+    fn(&mut Value<Source>, Vec<Value<Source>>) -> Result<Value<Source>, String>;
+
+/// A native function that can access the engine and the source of the call.
+pub type SourceFn<Source> =
+    fn(&mut Engine<Source>, &Source, Vec<Value<Source>>) -> Result<Value<Source>, String>;
+
 enum FuncDef<Source> {
     Native(NativeFunc<Source>),
-    Custom(Func<Source>),
+    /// A function written in script, along with its body compiled by the vm if it was defined there
+    Custom(Func<Source>, Option<Rc<Chunk<Source>>>),
 }
 
 impl<Source: Debug> Debug for FuncDef<Source> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Native(arg0) => f.debug_tuple("Native").field(arg0).finish(),
-            Self::Custom(arg0) => f.debug_tuple("Custom").field(arg0).finish(),
+            Self::Custom(arg0, arg1) => f.debug_tuple("Custom").field(arg0).field(arg1).finish(),
         }
     }
 }
@@ -29,7 +52,7 @@ impl<Source: Clone> Clone for FuncDef<Source> {
     fn clone(&self) -> Self {
         match self {
             Self::Native(arg0) => Self::Native(arg0.clone()),
-            Self::Custom(arg0) => Self::Custom(arg0.clone()),
+            Self::Custom(arg0, arg1) => Self::Custom(arg0.clone(), arg1.clone()),
         }
     }
 }
@@ -38,7 +61,7 @@ impl<Source: PartialEq> PartialEq for FuncDef<Source> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Native(l0), Self::Native(r0)) => l0 == r0,
-            (Self::Custom(l0), Self::Custom(r0)) => l0 == r0,
+            (Self::Custom(l0, _), Self::Custom(r0, _)) => l0 == r0,
             _ => false,
         }
     }
@@ -46,6 +69,8 @@ impl<Source: PartialEq> PartialEq for FuncDef<Source> {
 
 pub struct FuncPtr<Source> {
     def: Rc<FuncDef<Source>>,
+    // the module is owned by the engine that loaded it, which also owns this function
+    module: Option<Weak<ModuleScope<Source>>>,
 }
 
 impl<Source: Debug> Debug for FuncPtr<Source> {
@@ -58,13 +83,14 @@ impl<Source> Clone for FuncPtr<Source> {
     fn clone(&self) -> Self {
         Self {
             def: self.def.clone(),
+            module: self.module.clone(),
         }
     }
 }
 
 impl<Source: PartialEq> PartialEq for FuncPtr<Source> {
     fn eq(&self, other: &Self) -> bool {
-        self.def == other.def
+        self.ptr_eq(other) || self.def == other.def
     }
 }
 
@@ -78,33 +104,122 @@ impl<Source> FuncPtr<Source> {
     pub fn params(&self) -> usize {
         match self.def.deref() {
             FuncDef::Native(native) => native.params(),
-            FuncDef::Custom(custom) => custom.params.len(),
+            FuncDef::Custom(custom, _) => custom.params.len(),
         }
     }
 }
 
 impl<Source> FuncPtr<Source> {
-    pub fn native(
+    pub fn native(params: usize, native: NativeFn<Source>) -> Self {
+        Self::from_native(params, false, None, NativeKind::Pure(native))
+    }
+
+    /// Creates a native function that accepts `params` or more parameters.
+    pub fn native_variadic(params: usize, native: NativeFn<Source>) -> Self {
+        Self::from_native(params, true, None, NativeKind::Pure(native))
+    }
+
+    /// Creates a native function that is given access to the engine calling it.
+    pub fn native_engine(params: usize, native: EngineFn<Source>) -> Self {
+        Self::from_native(params, false, None, NativeKind::Engine(native))
+    }
+
+    /// Creates a native function that is given the engine and the source of its call.
+    pub fn native_source(params: usize, native: SourceFn<Source>) -> Self {
+        Self::from_native(params, false, None, NativeKind::Source(native))
+    }
+
+    /// Creates a native function that mutates its first parameter in place.
+    ///
+    /// `params` includes the mutated parameter.
+    pub fn native_mut(params: usize, native: MutFn<Source>) -> Self {
+        Self::from_native(params, false, None, NativeKind::Mut(native))
+    }
+
+    /// Creates a native function that may only run if the engine allows `capability`.
+    pub fn native_capability(
+        params: usize,
+        capability: Capability,
+        native: EngineFn<Source>,
+    ) -> Self {
+        Self::from_native(params, false, Some(capability), NativeKind::Engine(native))
+    }
+
+    fn from_native(
         params: usize,
-        native: fn(Vec<Value<Source>>) -> Result<Value<Source>, String>,
+        variadic: bool,
+        capability: Option<Capability>,
+        native: NativeKind<Source>,
     ) -> Self {
         let native = NativeFunc {
             params,
+            variadic,
+            capability,
             native,
             _source: PhantomData,
         };
 
         FuncPtr {
             def: Rc::new(FuncDef::Native(native)),
+            module: None,
         }
     }
 
     pub fn custom(func: Func<Source>) -> Self {
         Self {
-            def: Rc::new(FuncDef::Custom(func)),
+            def: Rc::new(FuncDef::Custom(func, None)),
+            module: None,
+        }
+    }
+
+    /// Creates a function whose body runs as the compiled `body` chunk instead of being walked.
+    pub fn compiled(func: Func<Source>, body: Rc<Chunk<Source>>) -> Self {
+        Self {
+            def: Rc::new(FuncDef::Custom(func, Some(body))),
+            module: None,
+        }
+    }
+
+    /// Makes the function resolve names against the top level of `module` when called.
+    pub(crate) fn within(mut self, module: Option<&Rc<ModuleScope<Source>>>) -> Self {
+        self.module = module.map(Rc::downgrade);
+        self
+    }
+
+    /// Returns true if calls to this function mutate their first parameter.
+    pub fn mutates(&self) -> bool {
+        matches!(
+            self.def.deref(),
+            FuncDef::Native(NativeFunc {
+                native: NativeKind::Mut(_),
+                ..
+            })
+        )
+    }
+
+    /// Returns the visibility of a custom function.
+    ///
+    /// Native functions are always public.
+    pub fn vis(&self) -> Visibility {
+        match self.def.deref() {
+            FuncDef::Native(_) => Visibility::Public,
+            FuncDef::Custom(custom, _) => custom.vis.item,
         }
     }
 
+    /// Returns the capability the engine must allow for this function to run.
+    pub fn capability(&self) -> Option<Capability> {
+        match self.def.deref() {
+            FuncDef::Native(native) => native.capability,
+            FuncDef::Custom(..) => None,
+        }
+    }
+
+    /// Returns true if both pointers refer to the same function.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.def, &other.def)
+    }
+
     pub fn kind(&self) -> FuncKind {
         FuncKind {
             params: self.params(),
@@ -113,6 +228,24 @@ impl<Source> FuncPtr<Source> {
 }
 
 impl<Source: Clone> FuncPtr<Source> {
+    /// Calls a function that [mutates](Self::mutates) its first parameter,
+    /// passing `target` as that parameter.
+    ///
+    /// # Panics
+    /// Panics if the function does not mutate its first parameter.
+    pub fn call_mut(
+        &self,
+        call_source: &Source,
+        target: &mut Value<Source>,
+        values: Vec<Value<Source>>,
+        engine: &mut Engine<Source>,
+    ) -> Result<Value<Source>, EvalError<Source>> {
+        match self.def.deref() {
+            FuncDef::Native(native) => native.call_mut(call_source, target, values, engine),
+            FuncDef::Custom(..) => unreachable!("custom functions never mutate their params"),
+        }
+    }
+
     pub fn call(
         &self,
         call_source: &Source,
@@ -120,8 +253,8 @@ impl<Source: Clone> FuncPtr<Source> {
         engine: &mut Engine<Source>,
     ) -> Result<Value<Source>, EvalError<Source>> {
         match self.def.deref() {
-            FuncDef::Native(native) => native.call(call_source, values),
-            FuncDef::Custom(custom) => {
+            FuncDef::Native(native) => native.call(call_source, values, engine),
+            FuncDef::Custom(custom, body) => {
                 if custom.params.len() != values.len() {
                     return Err(EvalError::InvalidParameters {
                         found: values.len(),
@@ -130,23 +263,33 @@ impl<Source: Clone> FuncPtr<Source> {
                     });
                 }
 
-                engine.vars_mut().stash();
-                for (name, value) in custom.params.iter().zip(values.into_iter()) {
-                    engine.vars_mut().init_local(name, value);
+                let module = self.module.as_ref().and_then(Weak::upgrade);
+                engine.vars_mut().stash_in(module);
+                for (name, value) in custom.params.iter().zip(values) {
+                    engine.vars_mut().init_local(*name, value);
                 }
 
-                let mut output = Value::None;
-                for statement in custom.body.iter() {
-                    output = match engine.eval(statement) {
-                        Ok(value) => value,
-                        Err(error) => {
-                            engine.vars_mut().unstash();
-                            return Err(error);
-                        }
-                    };
-                }
+                let output = match body {
+                    Some(chunk) => chunk.run(engine),
+                    None => custom
+                        .body
+                        .iter()
+                        .try_fold(Value::None, |_, statement| engine.eval(statement)),
+                };
 
                 engine.vars_mut().unstash();
+                let output = output?;
+                if let Some(ty) = &custom.output {
+                    if !ty.item.matches(&output.kind()) {
+                        return Err(EvalError::ReturnTypeMismatch {
+                            expect: ty.item,
+                            found: output.kind(),
+                            source: call_source.clone(),
+                            output_source: ty.source.clone(),
+                        });
+                    }
+                }
+
                 Ok(output)
             }
         }
@@ -165,9 +308,49 @@ impl FuncKind {
     }
 }
 
+enum NativeKind<Source> {
+    Pure(NativeFn<Source>),
+    Engine(EngineFn<Source>),
+    Source(SourceFn<Source>),
+    Mut(MutFn<Source>),
+}
+
+impl<Source> Debug for NativeKind<Source> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pure(arg0) => f.debug_tuple("Pure").field(arg0).finish(),
+            Self::Engine(arg0) => f.debug_tuple("Engine").field(arg0).finish(),
+            Self::Source(arg0) => f.debug_tuple("Source").field(arg0).finish(),
+            Self::Mut(arg0) => f.debug_tuple("Mut").field(arg0).finish(),
+        }
+    }
+}
+
+impl<Source> Clone for NativeKind<Source> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Source> Copy for NativeKind<Source> {}
+
+impl<Source> PartialEq for NativeKind<Source> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Pure(l0), Self::Pure(r0)) => std::ptr::fn_addr_eq(*l0, *r0),
+            (Self::Engine(l0), Self::Engine(r0)) => std::ptr::fn_addr_eq(*l0, *r0),
+            (Self::Source(l0), Self::Source(r0)) => std::ptr::fn_addr_eq(*l0, *r0),
+            (Self::Mut(l0), Self::Mut(r0)) => std::ptr::fn_addr_eq(*l0, *r0),
+            _ => false,
+        }
+    }
+}
+
 struct NativeFunc<Source> {
     params: usize,
-    native: fn(Vec<Value<Source>>) -> Result<Value<Source>, String>,
+    variadic: bool,
+    capability: Option<Capability>,
+    native: NativeKind<Source>,
     _source: PhantomData<*const Source>,
 }
 
@@ -175,6 +358,8 @@ impl<Source> Debug for NativeFunc<Source> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("NativeFunc")
             .field("params", &self.params)
+            .field("variadic", &self.variadic)
+            .field("capability", &self.capability)
             .field("native", &self.native)
             .field("_source", &self._source)
             .finish()
@@ -184,16 +369,22 @@ impl<Source> Debug for NativeFunc<Source> {
 impl<Source> Clone for NativeFunc<Source> {
     fn clone(&self) -> Self {
         Self {
-            params: self.params.clone(),
-            native: self.native.clone(),
-            _source: self._source.clone(),
+            params: self.params,
+            variadic: self.variadic,
+            capability: self.capability,
+            native: self.native,
+            _source: self._source,
         }
     }
 }
 
 impl<Source> PartialEq for NativeFunc<Source> {
     fn eq(&self, other: &Self) -> bool {
-        self.params == other.params && self.native == other.native && self._source == other._source
+        self.params == other.params
+            && self.variadic == other.variadic
+            && self.capability == other.capability
+            && self.native == other.native
+            && self._source == other._source
     }
 }
 
@@ -207,22 +398,84 @@ impl<Source: Clone> NativeFunc<Source> {
     pub fn call(
         &self,
         call_source: &Source,
-        values: Vec<Value<Source>>,
+        mut values: Vec<Value<Source>>,
+        engine: &mut Engine<Source>,
     ) -> Result<Value<Source>, EvalError<Source>> {
-        if values.len() != self.params {
-            return Err(EvalError::InvalidParameters {
-                found: values.len(),
-                expect: self.params,
-                source: call_source.clone(),
-            });
-        }
+        self.check(call_source, values.len(), engine)?;
+        let output = match self.native {
+            NativeKind::Pure(native) => native(values),
+            NativeKind::Engine(native) => native(engine, values),
+            NativeKind::Source(native) => native(engine, call_source, values),
+            // without a variable to update, the mutation is applied to a temporary
+            NativeKind::Mut(native) => {
+                let mut target = values.remove(0);
+                native(&mut target, values)
+            }
+        };
 
-        match (self.native)(values) {
-            Ok(value) => Ok(value),
+        // every builtin that builds a value is held to the allocation limit
+        match output {
+            Ok(value) => {
+                engine.check_alloc(value.alloc_size(), call_source)?;
+                Ok(value)
+            }
             Err(message) => Err(EvalError::NativeCall {
                 message,
                 source: call_source.clone(),
             }),
         }
     }
+
+    pub fn call_mut(
+        &self,
+        call_source: &Source,
+        target: &mut Value<Source>,
+        values: Vec<Value<Source>>,
+        engine: &mut Engine<Source>,
+    ) -> Result<Value<Source>, EvalError<Source>> {
+        let NativeKind::Mut(native) = self.native else {
+            unreachable!("only mutating natives are called in place");
+        };
+
+        self.check(call_source, values.len() + 1, engine)?;
+        let output = native(target, values).map_err(|message| EvalError::NativeCall {
+            message,
+            source: call_source.clone(),
+        })?;
+
+        engine.check_alloc(target.alloc_size(), call_source)?;
+        engine.check_alloc(output.alloc_size(), call_source)?;
+        Ok(output)
+    }
+
+    /// Checks the capability and parameter count of a call.
+    fn check(
+        &self,
+        call_source: &Source,
+        count: usize,
+        engine: &Engine<Source>,
+    ) -> Result<(), EvalError<Source>> {
+        if let Some(capability) = self.capability {
+            if !engine.allows(capability) {
+                return Err(EvalError::CapabilityDenied {
+                    capability,
+                    source: call_source.clone(),
+                });
+            }
+        }
+
+        let valid = match self.variadic {
+            true => count >= self.params,
+            false => count == self.params,
+        };
+
+        match valid {
+            true => Ok(()),
+            false => Err(EvalError::InvalidParameters {
+                found: count,
+                expect: self.params,
+                source: call_source.clone(),
+            }),
+        }
+    }
 }
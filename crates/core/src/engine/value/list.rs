@@ -0,0 +1,68 @@
+use std::{fmt::Display, mem, ops::Deref};
+
+use super::{value::drop_items, Value};
+
+/// A growable sequence of values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct List<Source> {
+    items: Vec<Value<Source>>,
+}
+
+impl<Source> Default for List<Source> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<Source> Display for List<Source> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items = self
+            .items
+            .iter()
+            .map(|v| format!("{v}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "[{items}]")
+    }
+}
+
+impl<Source> Drop for List<Source> {
+    fn drop(&mut self) {
+        drop_items(self.take_items());
+    }
+}
+
+impl<Source> Deref for List<Source> {
+    type Target = [Value<Source>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<Source> FromIterator<Value<Source>> for List<Source> {
+    fn from_iter<T: IntoIterator<Item = Value<Source>>>(iter: T) -> Self {
+        Self {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<Source> List<Source> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, value: Value<Source>) {
+        self.items.push(value);
+    }
+
+    pub fn pop(&mut self) -> Option<Value<Source>> {
+        self.items.pop()
+    }
+
+    /// Moves the items out, leaving the list empty.
+    pub(super) fn take_items(&mut self) -> Vec<Value<Source>> {
+        mem::take(&mut self.items)
+    }
+}
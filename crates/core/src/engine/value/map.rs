@@ -0,0 +1,212 @@
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+    mem,
+};
+
+use indexmap::{Equivalent, IndexMap};
+
+use super::{value::drop_items, Value};
+
+/// A value that can be used as a key in a [`Map`].
+///
+/// Only values with a [`Value::hash`] can be keys.
+#[derive(Debug)]
+pub struct MapKey<Source> {
+    value: Value<Source>,
+    hash: u64,
+}
+
+impl<Source> Clone for MapKey<Source> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            hash: self.hash,
+        }
+    }
+}
+
+impl<Source> PartialEq for MapKey<Source> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.value.total_eq(&other.value)
+    }
+}
+
+impl<Source> Eq for MapKey<Source> {}
+
+impl<Source> Hash for MapKey<Source> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
+    }
+}
+
+impl<Source> MapKey<Source> {
+    /// Returns `Err` with the original value if it is not hashable.
+    pub fn new(value: Value<Source>) -> Result<Self, Value<Source>> {
+        match value.hash() {
+            Some(hash) => Ok(Self { value, hash }),
+            None => Err(value),
+        }
+    }
+
+    pub fn value(&self) -> &Value<Source> {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value<Source> {
+        self.value
+    }
+}
+
+/// A borrowed key used to look up entries without cloning.
+struct KeyRef<'a, Source> {
+    value: &'a Value<Source>,
+    hash: u64,
+}
+
+impl<Source> Hash for KeyRef<'_, Source> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
+    }
+}
+
+impl<Source> Equivalent<MapKey<Source>> for KeyRef<'_, Source> {
+    fn equivalent(&self, key: &MapKey<Source>) -> bool {
+        self.hash == key.hash && self.value.total_eq(&key.value)
+    }
+}
+
+/// An insertion ordered mapping from keys to values.
+///
+/// Entries always iterate in the order their keys were first inserted,
+/// so printing, `to_json` and any other traversal is deterministic.
+/// Replacing the value of an existing key keeps the key in its original position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Map<Source> {
+    entries: IndexMap<MapKey<Source>, Value<Source>>,
+}
+
+impl<Source> Default for Map<Source> {
+    fn default() -> Self {
+        Self {
+            entries: IndexMap::new(),
+        }
+    }
+}
+
+impl<Source> Display for Map<Source> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self
+            .iter()
+            .map(|(k, v)| format!("{k}: {v}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{{{entries}}}")
+    }
+}
+
+impl<Source> Drop for Map<Source> {
+    fn drop(&mut self) {
+        drop_items(self.take_items());
+    }
+}
+
+impl<Source> FromIterator<(MapKey<Source>, Value<Source>)> for Map<Source> {
+    fn from_iter<T: IntoIterator<Item = (MapKey<Source>, Value<Source>)>>(iter: T) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<Source> IntoIterator for Map<Source> {
+    type Item = (Value<Source>, Value<Source>);
+    type IntoIter = std::iter::Map<
+        indexmap::map::IntoIter<MapKey<Source>, Value<Source>>,
+        fn((MapKey<Source>, Value<Source>)) -> Self::Item,
+    >;
+
+    /// Consumes the map, yielding its entries in insertion order.
+    fn into_iter(mut self) -> Self::IntoIter {
+        mem::take(&mut self.entries)
+            .into_iter()
+            .map(|(k, v)| (k.value, v))
+    }
+}
+
+impl<Source> Map<Source> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the value for `key`, or `None` if it is missing or unhashable.
+    pub fn get(&self, key: &Value<Source>) -> Option<&Value<Source>> {
+        let hash = key.hash()?;
+        self.entries.get(&KeyRef { value: key, hash })
+    }
+
+    /// Inserts a value, returning the one it replaced.
+    ///
+    /// Replacing a value keeps the position of its key.
+    pub fn insert(&mut self, key: MapKey<Source>, value: Value<Source>) -> Option<Value<Source>> {
+        self.entries.insert(key, value)
+    }
+
+    /// Removes the entry for `key`, returning its value.
+    ///
+    /// The remaining entries keep their order.
+    pub fn remove(&mut self, key: &Value<Source>) -> Option<Value<Source>> {
+        let hash = key.hash()?;
+        self.entries.shift_remove(&KeyRef { value: key, hash })
+    }
+
+    /// Iterates over the entries in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Value<Source>, &Value<Source>)> {
+        self.entries.iter().map(|(k, v)| (&k.value, v))
+    }
+
+    /// Iterates over the keys in insertion order.
+    pub(super) fn keys(&self) -> impl Iterator<Item = &MapKey<Source>> {
+        self.entries.keys()
+    }
+
+    /// Moves the keys and values out, leaving the map empty.
+    pub(super) fn take_items(&mut self) -> Vec<Value<Source>> {
+        mem::take(&mut self.entries)
+            .into_iter()
+            .flat_map(|(k, v)| [k.value, v])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> MapKey<()> {
+        MapKey::new(Value::String(name.into())).unwrap()
+    }
+
+    #[test]
+    fn insertion_order() {
+        let mut map = Map::new();
+        map.insert(key("b"), Value::Int(1.into()));
+        map.insert(key("a"), Value::Int(2.into()));
+        assert_eq!(map.to_string(), "{b: 1, a: 2}");
+
+        // replacing a value keeps the position of its key
+        let old = map.insert(key("b"), Value::Int(3.into()));
+        assert_eq!(old, Some(Value::Int(1.into())));
+        let keys = map.iter().map(|(k, _)| k.to_string()).collect::<Vec<_>>();
+        assert_eq!(keys, ["b", "a"]);
+        assert_eq!(map.to_string(), "{b: 3, a: 2}");
+    }
+}
@@ -1,10 +1,15 @@
+#[allow(clippy::module_inception)]
 mod value;
 
 pub mod func;
+pub mod list;
+pub mod map;
 pub mod store;
 pub mod tuple;
 
 pub use value::*;
 
 pub use func::FuncPtr;
+pub use list::List;
+pub use map::{Map, MapKey};
 pub use store::ValueStore;
@@ -1,4 +1,14 @@
-use std::mem::replace;
+use std::{
+    mem::{replace, take},
+    rc::Rc,
+};
+
+use fxhash::FxHashSet;
+
+use crate::{
+    ast::{Symbol, SymbolQuery},
+    engine::module::ModuleScope,
+};
 
 use super::Value;
 
@@ -14,10 +24,30 @@ enum StoreType {
     },
 }
 
+struct Entry<Source> {
+    id: Symbol,
+    value: Value<Source>,
+    constant: bool,
+}
+
+type Scope<Source> = Vec<Entry<Source>>;
+
+/// The locals hidden by a function call, along with the module they could see.
+type Stashed<Source> = (Vec<Scope<Source>>, Option<Rc<ModuleScope<Source>>>);
+
+/// The locals, stash and module hidden while a module is evaluated.
+type Hidden<Source> = (
+    Vec<Scope<Source>>,
+    Vec<Stashed<Source>>,
+    Option<Rc<ModuleScope<Source>>>,
+);
+
 pub struct ValueStore<Source> {
-    globals: Vec<Vec<(String, Value<Source>)>>,
-    locals: Vec<Vec<(String, Value<Source>)>>,
-    stash: Vec<Vec<Vec<(String, Value<Source>)>>>,
+    globals: Vec<Scope<Source>>,
+    locals: Vec<Scope<Source>>,
+    stash: Vec<Stashed<Source>>,
+    isolated: Vec<Hidden<Source>>,
+    module: Option<Rc<ModuleScope<Source>>>,
 }
 
 impl<Source> Default for ValueStore<Source> {
@@ -26,6 +56,8 @@ impl<Source> Default for ValueStore<Source> {
             globals: Default::default(),
             locals: Default::default(),
             stash: Default::default(),
+            isolated: Default::default(),
+            module: None,
         }
     }
 }
@@ -44,44 +76,101 @@ impl<Source> ValueStore<Source> {
     }
 
     pub fn stash(&mut self) {
-        let values = replace(&mut self.locals, Vec::new());
-        self.stash.push(values);
+        self.stash_in(None);
+    }
+
+    /// Stashes the locals for a call to a function defined in `module`,
+    /// whose top level bindings stay visible until the call is [unstashed](Self::unstash).
+    pub(crate) fn stash_in(&mut self, module: Option<Rc<ModuleScope<Source>>>) {
+        let values = take(&mut self.locals);
+        let outer = replace(&mut self.module, module);
+        self.stash.push((values, outer));
         self.push_scope();
     }
 
     pub fn unstash(&mut self) {
-        let values = match self.stash.pop() {
-            Some(stash) => stash,
-            None => Vec::new(),
-        };
+        let (values, module) = self.stash.pop().unwrap_or_default();
 
         self.locals = values;
+        self.module = module;
+    }
+
+    /// Hides every local and stashed value until [`restore`](Self::restore) is called,
+    /// leaving only the globals visible.
+    ///
+    /// Modules are evaluated in isolation so they cannot see the program importing them.
+    /// Functions defined until then belong to `module`.
+    pub(crate) fn isolate(&mut self, module: Rc<ModuleScope<Source>>) {
+        let locals = take(&mut self.locals);
+        let stash = take(&mut self.stash);
+        let outer = self.module.replace(module);
+        self.isolated.push((locals, stash, outer));
     }
 
-    pub fn init_local(&mut self, id: impl Into<String>, value: Value<Source>) {
-        let entry = (id.into(), value);
-        match self.locals.last_mut() {
-            Some(scope) => scope.push(entry),
-            None => self.locals.push(vec![entry]),
+    /// Drops the current locals and brings back the values hidden by [`isolate`](Self::isolate).
+    pub fn restore(&mut self) {
+        let (locals, stash, module) = self.isolated.pop().unwrap_or_default();
+        self.locals = locals;
+        self.stash = stash;
+        self.module = module;
+    }
+
+    /// Returns the module that functions defined in the current scope belong to.
+    pub(crate) fn module(&self) -> Option<&Rc<ModuleScope<Source>>> {
+        self.module.as_ref()
+    }
+
+    pub fn init_local(&mut self, id: impl Into<Symbol>, value: Value<Source>) {
+        self.init_local_entry(id.into(), value, false);
+    }
+
+    /// Initializes a local value that cannot be reassigned with `set`.
+    pub fn init_local_const(&mut self, id: impl Into<Symbol>, value: Value<Source>) {
+        self.init_local_entry(id.into(), value, true);
+    }
+
+    pub fn init_global(&mut self, id: impl Into<Symbol>, value: Value<Source>) {
+        if self.globals.is_empty() {
+            self.globals.push(Vec::new());
         }
+
+        let scope = self.globals.last_mut().unwrap();
+        init_scope(scope, id.into(), value, false);
     }
 
-    pub fn init_global(&mut self, id: impl Into<String>, value: Value<Source>) {
-        let entry = (id.into(), value);
-        match self.globals.last_mut() {
-            Some(scope) => scope.push(entry),
-            None => self.globals.push(vec![entry]),
+    fn init_local_entry(&mut self, id: Symbol, value: Value<Source>, constant: bool) {
+        if self.locals.is_empty() {
+            self.push_scope();
         }
+
+        let scope = self.locals.last_mut().unwrap();
+        init_scope(scope, id, value, constant);
+    }
+
+    /// Returns true if the value is defined as a constant.
+    pub fn is_const(&self, id: impl SymbolQuery) -> bool {
+        let Some(id) = id.symbol() else {
+            return false;
+        };
+        self.entry(id).is_some_and(|(_, constant)| constant)
     }
 
     pub fn set(
         &mut self,
-        id: impl AsRef<str>,
+        id: impl SymbolQuery,
         value: Value<Source>,
     ) -> Result<Value<Source>, Value<Source>> {
-        let id = id.as_ref();
+        let Some(id) = id.symbol() else {
+            return Err(value);
+        };
         let entry = match self.find(id) {
             StoreType::None => return Err(value),
+            // module bindings are read only once the module has been evaluated
+            StoreType::Global { .. }
+                if self.module_exports().is_some_and(|m| m.get(id).is_some()) =>
+            {
+                return Err(value)
+            }
             StoreType::Global {
                 scope_index,
                 value_index,
@@ -92,27 +181,105 @@ impl<Source> ValueStore<Source> {
             } => &mut self.locals[scope_index][value_index],
         };
 
-        Ok(replace(entry, (id.to_string(), value)).1)
+        Ok(replace(&mut entry.value, value))
     }
 
-    pub fn get(&self, id: impl AsRef<str>) -> Option<&Value<Source>> {
-        match self.find(id.as_ref()) {
-            StoreType::None => todo!(),
-            StoreType::Global {
-                scope_index,
-                value_index,
-            } => Some(&self.globals[scope_index][value_index].1),
+    pub fn get(&self, id: impl SymbolQuery) -> Option<&Value<Source>> {
+        Some(self.entry(id.symbol()?)?.0)
+    }
+
+    /// Gets a value that is being called as a function.
+    ///
+    /// Calls look through the visible scopes first, then fall back to the
+    /// top level scopes that the outermost function call stashed away.
+    /// This lets top level functions call themselves and each other,
+    /// no matter which one was defined first.
+    /// Functions from an evaluated module see that module's top level instead.
+    pub fn get_callable(&self, id: impl SymbolQuery) -> Option<&Value<Source>> {
+        let id = id.symbol()?;
+        if let Some(value) = self.get(id) {
+            return Some(value);
+        }
+
+        if self.module_exports().is_some() {
+            return None;
+        }
+
+        let (top, _) = self.stash.first()?;
+        top.iter()
+            .rev()
+            .find_map(|scope| scope.iter().rev().find(|entry| entry.id == id))
+            .map(|entry| &entry.value)
+    }
+
+    /// Iterates over every visible binding in the order they are looked up.
+    ///
+    /// Locals come before globals and inner scopes before outer ones,
+    /// so a shadowed binding is skipped in favor of the one that shadows it.
+    /// Bindings stashed away by a function call are not visible.
+    pub fn visible(&self) -> impl Iterator<Item = (Symbol, &Value<Source>)> {
+        let mut seen = FxHashSet::default();
+        let scopes = self.locals.iter().rev().chain(self.globals.iter().rev());
+        scopes
+            .flat_map(|scope| scope.iter().rev())
+            .filter(move |entry| seen.insert(entry.id))
+            .map(|entry| (entry.id, &entry.value))
+    }
+
+    /// Takes the top level locals out of the store, leaving it empty.
+    ///
+    /// Each name is listed once with its latest value, in the order names were first defined,
+    /// along with whether it was defined as a constant.
+    pub fn take_locals(&mut self) -> Vec<(Symbol, Value<Source>, bool)> {
+        let mut bindings: Vec<(Symbol, Value<Source>, bool)> = Vec::new();
+        for entry in take(&mut self.locals).into_iter().flatten() {
+            match bindings.iter_mut().find(|(id, ..)| *id == entry.id) {
+                Some(binding) => *binding = (entry.id, entry.value, entry.constant),
+                None => bindings.push((entry.id, entry.value, entry.constant)),
+            }
+        }
+        bindings
+    }
+
+    /// Finds a visible value, along with whether it is a constant.
+    ///
+    /// The top level of the current module is searched after the locals and before the globals.
+    fn entry(&self, id: Symbol) -> Option<(&Value<Source>, bool)> {
+        let entry = match self.find(id) {
             StoreType::Local {
                 scope_index,
                 value_index,
-            } => Some(&self.locals[scope_index][value_index].1),
-        }
+            } => &self.locals[scope_index][value_index],
+            store_type => {
+                let module = self.module_exports().and_then(|module| module.get(id));
+                if module.is_some() {
+                    return module;
+                }
+
+                let StoreType::Global {
+                    scope_index,
+                    value_index,
+                } = store_type
+                else {
+                    return None;
+                };
+                &self.globals[scope_index][value_index]
+            }
+        };
+
+        Some((&entry.value, entry.constant))
+    }
+
+    /// Returns the current module if it has finished evaluating.
+    fn module_exports(&self) -> Option<&ModuleScope<Source>> {
+        let module = self.module.as_deref()?;
+        module.exports().map(|_| module)
     }
 
-    fn find(&self, id: &str) -> StoreType {
+    fn find(&self, id: Symbol) -> StoreType {
         for (scope_index, scope) in self.locals.iter().enumerate().rev() {
-            for (value_index, (value_id, _)) in scope.iter().enumerate().rev() {
-                if value_id.as_str() == id {
+            for (value_index, entry) in scope.iter().enumerate().rev() {
+                if entry.id == id {
                     return StoreType::Local {
                         scope_index,
                         value_index,
@@ -122,8 +289,8 @@ impl<Source> ValueStore<Source> {
         }
 
         for (scope_index, scope) in self.globals.iter().enumerate().rev() {
-            for (value_index, (value_id, _)) in scope.iter().enumerate().rev() {
-                if value_id.as_str() == id {
+            for (value_index, entry) in scope.iter().enumerate().rev() {
+                if entry.id == id {
                     return StoreType::Global {
                         scope_index,
                         value_index,
@@ -135,3 +302,17 @@ impl<Source> ValueStore<Source> {
         StoreType::None
     }
 }
+
+fn init_scope<Source>(scope: &mut Scope<Source>, id: Symbol, value: Value<Source>, constant: bool) {
+    // redefining a value in the same scope replaces the old value
+    let entry = Entry {
+        id,
+        value,
+        constant,
+    };
+
+    match scope.iter_mut().find(|old| old.id == entry.id) {
+        Some(old) => *old = entry,
+        None => scope.push(entry),
+    }
+}
@@ -1,6 +1,6 @@
-use std::{fmt::Display, ops::Deref};
+use std::{fmt::Display, mem, ops::Deref};
 
-use super::{Value, ValueKind};
+use super::{value::drop_items, Value, ValueKind};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Tuple<Source> {
@@ -15,7 +15,24 @@ impl<Source> Display for Tuple<Source> {
             .map(|v| format!("{v}"))
             .collect::<Vec<_>>()
             .join(", ");
-        write!(f, "({items})")
+        match self.items.len() {
+            1 => write!(f, "({items},)"),
+            _ => write!(f, "({items})"),
+        }
+    }
+}
+
+impl<Source> Drop for Tuple<Source> {
+    fn drop(&mut self) {
+        drop_items(self.take_items());
+    }
+}
+
+impl<Source> Deref for Tuple<Source> {
+    type Target = [Value<Source>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
     }
 }
 
@@ -31,6 +48,11 @@ impl<Source> Tuple<Source> {
     pub fn kind(&self) -> TupleKind {
         self.items.deref().into()
     }
+
+    /// Moves the items out, leaving the tuple empty.
+    pub(super) fn take_items(&mut self) -> Vec<Value<Source>> {
+        mem::take(&mut self.items).into_vec()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1,15 +1,27 @@
-use std::fmt;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
 
 use dashu::integer::IBig;
 use derive_more::Display;
+use fxhash::FxHasher64;
 
 use super::{
     func::FuncKind,
     tuple::{Tuple, TupleKind},
-    FuncPtr,
+    FuncPtr, List, Map,
 };
 
-#[derive(Debug, Clone, PartialEq)]
+/// A runtime value.
+///
+/// Collections own their items, so a value can never contain itself.
+/// Functions are shared by reference, so cloning or comparing a value
+/// never walks into the body of a function it holds.
+///
+/// Comparing, cloning and dropping walk nested collections with an explicit
+/// stack rather than recursion, so deeply nested values cannot overflow the call stack.
+#[derive(Debug)]
 pub enum Value<Source> {
     None,
     Bool(bool),
@@ -17,9 +29,117 @@ pub enum Value<Source> {
     Float(f64),
     String(String),
     Tuple(Tuple<Source>),
+    List(List<Source>),
+    Map(Map<Source>),
+    /// The outcome of a fallible operation, either `ok(value)` or `err(value)`.
+    Result(Box<ValueResult<Source>>),
     Func(FuncPtr<Source>),
 }
 
+/// The payload of a [`Value::Result`].
+pub type ValueResult<Source> = Result<Value<Source>, Value<Source>>;
+
+impl<Source: PartialEq> PartialEq for Value<Source> {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_by(other, |v1, v2| v1 == v2, |v1, v2| v1 == v2)
+    }
+}
+
+impl<Source> Clone for Value<Source> {
+    fn clone(&self) -> Self {
+        let mut frames = match CloneFrame::new(self) {
+            Ok(value) => return value,
+            Err(frame) => vec![frame],
+        };
+
+        loop {
+            let frame = frames.last_mut().expect("the outermost frame returns");
+            match frame.pending.next() {
+                Some(item) => match CloneFrame::new(item) {
+                    Ok(value) => frame.cloned.push(value),
+                    Err(frame) => frames.push(frame),
+                },
+                None => {
+                    let value = frames.pop().expect("frame exists").finish();
+                    match frames.last_mut() {
+                        Some(parent) => parent.cloned.push(value),
+                        None => return value,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A collection that is partway through being cloned.
+struct CloneFrame<'a, Source> {
+    original: &'a Value<Source>,
+    pending: Box<dyn Iterator<Item = &'a Value<Source>> + 'a>,
+    cloned: Vec<Value<Source>>,
+}
+
+impl<'a, Source> CloneFrame<'a, Source> {
+    /// Clones `value` directly if it holds no other values,
+    /// otherwise starts a frame to clone its items.
+    fn new(value: &'a Value<Source>) -> Result<Value<Source>, Self> {
+        let pending: Box<dyn Iterator<Item = &'a Value<Source>> + 'a> = match value {
+            Value::None => return Ok(Value::None),
+            Value::Bool(v) => return Ok(Value::Bool(*v)),
+            Value::Int(v) => return Ok(Value::Int(v.clone())),
+            Value::Float(v) => return Ok(Value::Float(*v)),
+            Value::String(v) => return Ok(Value::String(v.clone())),
+            Value::Func(v) => return Ok(Value::Func(v.clone())),
+            Value::Tuple(v) => Box::new(v.iter()),
+            Value::List(v) => Box::new(v.iter()),
+            Value::Map(v) => Box::new(v.iter().map(|(_, value)| value)),
+            Value::Result(v) => {
+                let (Ok(v) | Err(v)) = v.as_ref();
+                Box::new(std::iter::once(v))
+            }
+        };
+
+        Err(Self {
+            original: value,
+            pending,
+            cloned: Vec::with_capacity(value.alloc_size()),
+        })
+    }
+
+    fn finish(self) -> Value<Source> {
+        let mut cloned = self.cloned;
+        match self.original {
+            Value::Tuple(_) => Value::Tuple(cloned.into_iter().collect()),
+            Value::List(_) => Value::List(cloned.into_iter().collect()),
+            Value::Map(v) => Value::Map(v.keys().cloned().zip(cloned).collect()),
+            Value::Result(v) => {
+                let value = cloned.pop().expect("results hold one value");
+                match v.as_ref() {
+                    Ok(_) => Value::Result(Box::new(Ok(value))),
+                    Err(_) => Value::Result(Box::new(Err(value))),
+                }
+            }
+            _ => unreachable!("only collections start a frame"),
+        }
+    }
+}
+
+/// Drops the items taken out of a collection one level at a time,
+/// so dropping a deeply nested value cannot overflow the call stack.
+pub(super) fn drop_items<Source>(mut items: Vec<Value<Source>>) {
+    while let Some(value) = items.pop() {
+        match value {
+            Value::Tuple(mut v) => items.extend(v.take_items()),
+            Value::List(mut v) => items.extend(v.take_items()),
+            Value::Map(mut v) => items.extend(v.take_items()),
+            Value::Result(v) => {
+                let (Ok(v) | Err(v)) = *v;
+                items.push(v);
+            }
+            _ => {}
+        }
+    }
+}
+
 impl<Source> fmt::Display for Value<Source> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -29,6 +149,12 @@ impl<Source> fmt::Display for Value<Source> {
             Value::Float(v) => write!(f, "{v}"),
             Value::String(v) => write!(f, "{v}"),
             Value::Tuple(v) => write!(f, "{v}"),
+            Value::List(v) => write!(f, "{v}"),
+            Value::Map(v) => write!(f, "{v}"),
+            Value::Result(v) => match v.as_ref() {
+                Ok(v) => write!(f, "ok({v})"),
+                Err(v) => write!(f, "err({v})"),
+            },
             Value::Func(v) => write!(f, "{v}"),
         }
     }
@@ -44,8 +170,237 @@ impl<Source> Value<Source> {
             Value::String(_) => ValueKind::String,
             Value::Func(v) => ValueKind::Func(v.kind()),
             Value::Tuple(v) => ValueKind::Tuple(v.kind()),
+            Value::List(_) => ValueKind::List,
+            Value::Map(_) => ValueKind::Map,
+            Value::Result(_) => ValueKind::Result,
+        }
+    }
+
+    /// Returns the number of bytes in a string or items in a collection.
+    ///
+    /// All other values have a size of zero.
+    pub fn alloc_size(&self) -> usize {
+        match self {
+            Value::String(v) => v.len(),
+            Value::Tuple(v) => v.len(),
+            Value::List(v) => v.len(),
+            Value::Map(v) => v.len(),
+            _ => 0,
         }
     }
+
+    /// Returns a source-like representation of this value for debugging.
+    ///
+    /// Unlike the display format, strings are quoted, single item tuples
+    /// keep their trailing comma and floats always have a decimal point.
+    /// Simple values parse back into the same value.
+    pub fn repr(&self) -> String {
+        let mut output = String::new();
+        self.repr_into(&mut output);
+        output
+    }
+
+    fn repr_into(&self, output: &mut String) {
+        fn items<'a, Source: 'a>(
+            output: &mut String,
+            items: impl Iterator<Item = &'a Value<Source>>,
+        ) {
+            for (index, item) in items.enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                item.repr_into(output);
+            }
+        }
+
+        match self {
+            Value::None => output.push_str("none"),
+            Value::Bool(v) => output.push_str(&v.to_string()),
+            Value::Int(v) => output.push_str(&v.to_string()),
+            Value::Float(v) => {
+                let float = v.to_string();
+                output.push_str(&float);
+                if float.bytes().all(|b| b == b'-' || b.is_ascii_digit()) {
+                    output.push_str(".0");
+                }
+            }
+            Value::String(v) => {
+                // prefer the quote that needs no escaping
+                let quote = match v.contains('"') && !v.contains('\'') {
+                    true => '\'',
+                    false => '"',
+                };
+
+                output.push(quote);
+                for c in v.chars() {
+                    match c {
+                        '\n' => output.push_str("\\n"),
+                        '\r' => output.push_str("\\r"),
+                        '\t' => output.push_str("\\t"),
+                        '\\' => output.push_str("\\\\"),
+                        c if c == quote => {
+                            output.push('\\');
+                            output.push(c);
+                        }
+                        c if c.is_control() => output.push_str(&c.escape_unicode().to_string()),
+                        c => output.push(c),
+                    }
+                }
+                output.push(quote);
+            }
+            Value::Tuple(v) => {
+                output.push('(');
+                items(output, v.iter());
+                if v.len() == 1 {
+                    output.push(',');
+                }
+                output.push(')');
+            }
+            Value::List(v) => {
+                output.push('[');
+                items(output, v.iter());
+                output.push(']');
+            }
+            Value::Map(v) => {
+                output.push('{');
+                for (index, (key, value)) in v.iter().enumerate() {
+                    if index > 0 {
+                        output.push_str(", ");
+                    }
+                    key.repr_into(output);
+                    output.push_str(": ");
+                    value.repr_into(output);
+                }
+                output.push('}');
+            }
+            Value::Result(v) => {
+                let (name, value) = match v.as_ref() {
+                    Ok(value) => ("ok(", value),
+                    Err(value) => ("err(", value),
+                };
+                output.push_str(name);
+                value.repr_into(output);
+                output.push(')');
+            }
+            Value::Func(v) => output.push_str(&format!("<{}>", v.kind())),
+        }
+    }
+
+    /// Compares two values without needing to compare their sources.
+    ///
+    /// Floats are compared by their total ordering, so `nan` equals itself,
+    /// and functions are only equal if they are the same function.
+    pub fn total_eq(&self, other: &Self) -> bool {
+        self.eq_by(other, |v1, v2| v1.total_cmp(v2).is_eq(), FuncPtr::ptr_eq)
+    }
+
+    /// Compares two values structurally using the provided leaf comparisons.
+    fn eq_by(
+        &self,
+        other: &Self,
+        float_eq: impl Fn(&f64, &f64) -> bool,
+        func_eq: impl Fn(&FuncPtr<Source>, &FuncPtr<Source>) -> bool,
+    ) -> bool {
+        let equal = self.structural_eq(other, |v1, v2| {
+            Some(match (v1, v2) {
+                (Value::None, Value::None) => true,
+                (Value::Bool(v1), Value::Bool(v2)) => v1 == v2,
+                (Value::Int(v1), Value::Int(v2)) => v1 == v2,
+                (Value::Float(v1), Value::Float(v2)) => float_eq(v1, v2),
+                (Value::String(v1), Value::String(v2)) => v1 == v2,
+                (Value::Func(v1), Value::Func(v2)) => func_eq(v1, v2),
+                _ => false,
+            })
+        });
+
+        equal == Some(true)
+    }
+
+    /// Compares two values by walking collections of the same kind item by item,
+    /// using `leaf_eq` for every other pair of values.
+    ///
+    /// Returns `None` as soon as `leaf_eq` finds a pair it cannot compare.
+    /// Collections are compared in order, so the first unequal or
+    /// incomparable pair decides the result.
+    pub(crate) fn structural_eq(
+        &self,
+        other: &Self,
+        leaf_eq: impl Fn(&Self, &Self) -> Option<bool>,
+    ) -> Option<bool> {
+        let mut pending = vec![(self, other)];
+        while let Some(pair) = pending.pop() {
+            let equal = match pair {
+                (Value::Tuple(v1), Value::Tuple(v2)) => {
+                    pending.extend(v1.iter().zip(v2.iter()).rev());
+                    v1.len() == v2.len()
+                }
+                (Value::List(v1), Value::List(v2)) => {
+                    pending.extend(v1.iter().zip(v2.iter()).rev());
+                    v1.len() == v2.len()
+                }
+                (Value::Map(v1), Value::Map(v2)) => {
+                    let start = pending.len();
+                    let equal = v1.len() == v2.len()
+                        && v1.iter().all(|(key, v1)| match v2.get(key) {
+                            Some(v2) => {
+                                pending.push((v1, v2));
+                                true
+                            }
+                            None => false,
+                        });
+                    pending[start..].reverse();
+                    equal
+                }
+                (Value::Result(v1), Value::Result(v2)) => match (v1.as_ref(), v2.as_ref()) {
+                    (Ok(v1), Ok(v2)) | (Err(v1), Err(v2)) => {
+                        pending.push((v1, v2));
+                        true
+                    }
+                    _ => false,
+                },
+                (v1, v2) => leaf_eq(v1, v2)?,
+            };
+
+            if !equal {
+                return Some(false);
+            }
+        }
+
+        Some(true)
+    }
+
+    /// Returns a hash of this value that is stable between runs.
+    ///
+    /// Only none, bool, int, string and tuples or results of those are hashable.
+    /// Returns `None` for any other value.
+    pub fn hash(&self) -> Option<u64> {
+        let mut hasher = FxHasher64::default();
+        self.hash_into(&mut hasher)?;
+        Some(hasher.finish())
+    }
+
+    fn hash_into(&self, state: &mut impl Hasher) -> Option<()> {
+        match self {
+            Value::None => 0u8.hash(state),
+            Value::Bool(v) => (1u8, v).hash(state),
+            Value::Int(v) => (2u8, v).hash(state),
+            Value::String(v) => (3u8, v).hash(state),
+            Value::Tuple(v) => {
+                (4u8, v.len()).hash(state);
+                for item in v.iter() {
+                    item.hash_into(state)?;
+                }
+            }
+            Value::Result(v) => {
+                (5u8, v.is_ok()).hash(state);
+                let (Ok(v) | Err(v)) = v.as_ref();
+                v.hash_into(state)?;
+            }
+            Value::Float(_) | Value::List(_) | Value::Map(_) | Value::Func(_) => return None,
+        }
+
+        Some(())
+    }
 }
 
 #[derive(Debug, Display, Clone, PartialEq)]
@@ -62,6 +417,95 @@ pub enum ValueKind {
     String,
     #[display(fmt = "{}", _0)]
     Tuple(TupleKind),
+    #[display(fmt = "list")]
+    List,
+    #[display(fmt = "map")]
+    Map,
+    #[display(fmt = "result")]
+    Result,
     #[display(fmt = "{}", _0)]
     Func(FuncKind),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashable_values() {
+        let tuple = |items: Vec<Value<()>>| Value::Tuple(items.into_iter().collect());
+        let a = tuple(vec![Value::Int(1.into()), Value::String("a".into())]);
+        let b = tuple(vec![Value::Int(1.into()), Value::String("a".into())]);
+        assert_eq!(a.hash(), b.hash());
+        assert!(a.hash().is_some());
+
+        // same payload, different kinds
+        assert_ne!(
+            Value::<()>::Bool(true).hash(),
+            Value::<()>::Int(1.into()).hash()
+        );
+
+        assert_eq!(Value::<()>::Float(1.).hash(), None);
+        assert_eq!(tuple(vec![Value::None, Value::Float(1.)]).hash(), None);
+    }
+
+    #[test]
+    fn deeply_nested_eq() {
+        let nest = |depth: usize| {
+            let mut value = Value::<()>::None;
+            for i in 0..depth {
+                let items = [Value::Int(i.into()), value];
+                value = match i % 2 {
+                    0 => Value::Tuple(items.into_iter().collect()),
+                    _ => Value::List(items.into_iter().collect()),
+                };
+            }
+            value
+        };
+
+        // deep enough to overflow a recursive clone, compare or drop
+        let a = nest(100_000);
+        let b = a.clone();
+        assert_eq!(a, b);
+        assert!(a.total_eq(&b));
+        assert_ne!(a, nest(99_999));
+
+        // floats only equal themselves under the total ordering
+        let nan = Value::<()>::Tuple([Value::Float(f64::NAN)].into_iter().collect());
+        assert_ne!(nan, nan.clone());
+        assert!(nan.total_eq(&nan.clone()));
+    }
+
+    #[test]
+    fn shared_func_eq() {
+        let func = Value::<()>::Func(FuncPtr::native(0, |_| Ok(Value::None)));
+        let copy = Value::Tuple([func.clone()].into_iter().collect());
+        assert_eq!(copy, Value::Tuple([func.clone()].into_iter().collect()));
+        assert!(func.total_eq(&func.clone()));
+
+        let other = Value::<()>::Func(FuncPtr::native(0, |_| Ok(Value::None)));
+        assert!(!func.total_eq(&other));
+    }
+
+    #[test]
+    fn repr_values() {
+        let string = |s: &str| Value::<()>::String(s.to_string());
+        assert_eq!(string("a b").repr(), r#""a b""#);
+        assert_eq!(string(r#"say "hi""#).repr(), r#"'say "hi"'"#);
+        assert_eq!(string("'\"\n").repr(), r#""'\"\n""#);
+        assert_eq!(string(r"a\nb").repr(), r#""a\\nb""#);
+        assert_eq!(Value::<()>::Float(1.).repr(), "1.0");
+        assert_eq!(Value::<()>::Float(-0.25).repr(), "-0.25");
+        assert_eq!(Value::<()>::Float(f64::NAN).repr(), "NaN");
+
+        let single = Value::<()>::Tuple([string("a")].into_iter().collect());
+        assert_eq!(single.repr(), r#"("a",)"#);
+        let nested = Value::Tuple(
+            [Value::Int(1.into()), Value::None, single, Value::Bool(true)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(nested.repr(), r#"(1, none, ("a",), true)"#);
+        assert_eq!(nested.to_string(), "(1, none, (a,), true)");
+    }
+}
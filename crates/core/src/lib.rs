@@ -1,5 +1,6 @@
 pub mod ast;
 pub mod engine;
+pub mod vm;
 
 pub use engine::Engine;
 
@@ -0,0 +1,764 @@
+use crate::{
+    ast::{
+        expr::{call_mut, destructure_value, get_field, splat},
+        StatementNode, Symbol,
+    },
+    engine::{
+        value::{FuncPtr, ValueKind},
+        EvalError, Value,
+    },
+    Engine,
+};
+
+use super::{compile::Compiler, op::BindMode, Op};
+
+/// A compiled sequence of statements.
+#[derive(Debug, Clone)]
+pub struct Chunk<Source> {
+    ops: Vec<Op<Source>>,
+    sources: Vec<Source>,
+}
+
+impl<Source> Chunk<Source> {
+    pub(crate) fn new(ops: Vec<Op<Source>>, sources: Vec<Source>) -> Self {
+        Self { ops, sources }
+    }
+
+    pub fn ops(&self) -> &[Op<Source>] {
+        &self.ops
+    }
+}
+
+impl<Source: Clone> Chunk<Source> {
+    pub fn compile<'a>(statements: impl IntoIterator<Item = &'a StatementNode<Source>>) -> Self
+    where
+        Source: 'a,
+    {
+        let mut compiler = Compiler::new();
+        for statement in statements {
+            compiler.statement(statement);
+        }
+        compiler.finish()
+    }
+
+    /// Runs the chunk and returns the value of the last statement.
+    pub fn run(&self, engine: &mut Engine<Source>) -> Result<Value<Source>, EvalError<Source>> {
+        let mut state = State {
+            stack: Vec::new(),
+            result: Value::None,
+            index: 0,
+            catches: Vec::new(),
+            scopes: 0,
+        };
+
+        while let Some(op) = self.ops.get(state.index) {
+            let source = &self.sources[state.index];
+            state.index += 1;
+
+            if let Err(error) = self.step(op, source, &mut state, engine) {
+                // recoverable errors continue at the innermost catch handler
+                match state.catches.pop() {
+                    Some(catch) if error.is_recoverable() => {
+                        state.pop_scopes(catch.scopes, engine);
+                        state.stack.truncate(catch.height);
+                        state.stack.push(Value::String(error.message()));
+                        state.index = catch.handler;
+                    }
+                    _ => {
+                        state.pop_scopes(0, engine);
+                        return Err(error);
+                    }
+                }
+            }
+        }
+
+        Ok(state.result)
+    }
+
+    fn step(
+        &self,
+        op: &Op<Source>,
+        source: &Source,
+        state: &mut State<Source>,
+        engine: &mut Engine<Source>,
+    ) -> Result<(), EvalError<Source>> {
+        match op {
+            Op::Push(value) => state.stack.push(value.clone()),
+            Op::Func { func, body } => {
+                let func = FuncPtr::compiled(func.clone(), body.clone());
+                let func = func.within(engine.vars().module());
+                state.stack.push(Value::Func(func));
+            }
+            Op::Load(id) => match engine.vars().get(*id) {
+                Some(value) => state.stack.push(value.clone()),
+                None => {
+                    return Err(EvalError::UnknownVariable {
+                        name: id.to_string(),
+                        source: source.clone(),
+                    })
+                }
+            },
+            Op::LoadFunc(name) => match engine.vars().get_callable(*name) {
+                Some(Value::Func(func)) => state.stack.push(Value::Func(func.clone())),
+                Some(value) => {
+                    return Err(EvalError::NotAFunction {
+                        name: name.to_string(),
+                        found: value.kind(),
+                        source: source.clone(),
+                    })
+                }
+                None => {
+                    return Err(EvalError::UnknownFunction {
+                        name: name.to_string(),
+                        source: source.clone(),
+                    })
+                }
+            },
+            Op::Pop => {
+                pop(&mut state.stack);
+            }
+            Op::SetResult => state.result = pop(&mut state.stack),
+            Op::ClearResult => state.result = Value::None,
+            Op::CheckAlloc(size) => engine.check_alloc(*size, source)?,
+            Op::Tuple(count) => {
+                let items = state.stack.split_off(state.stack.len() - count);
+                state.stack.push(Value::Tuple(items.into_iter().collect()));
+            }
+            Op::Splat => {
+                let items = splat(pop(&mut state.stack), source)?;
+                state.stack.push(Value::Tuple(items.into_iter().collect()));
+            }
+            Op::Concat(count) => {
+                let mut items = Vec::new();
+                for value in state.stack.split_off(state.stack.len() - count) {
+                    let Value::Tuple(tuple) = value else {
+                        unreachable!("concat is compiled after tuples");
+                    };
+                    items.extend(tuple.iter().cloned());
+                }
+                engine.check_alloc(items.len(), source)?;
+                state.stack.push(Value::Tuple(items.into_iter().collect()));
+            }
+            Op::Unary(op) => {
+                let value = pop(&mut state.stack);
+                state.stack.push(engine.apply_unary(*op, &value, source)?);
+            }
+            Op::Binary(op) => {
+                let v2 = pop(&mut state.stack);
+                let v1 = pop(&mut state.stack);
+                state
+                    .stack
+                    .push(engine.apply_binary(*op, &v1, &v2, source)?);
+            }
+            Op::Field { field, optional } => match pop(&mut state.stack) {
+                Value::None if *optional => state.stack.push(Value::None),
+                value => state.stack.push(get_field(&value, field)?),
+            },
+            Op::Walrus(id) => {
+                let value = state.stack.last().expect("walrus has a value").clone();
+                if engine.vars().is_const(*id) {
+                    return Err(EvalError::ReassignConst {
+                        name: id.to_string(),
+                        source: source.clone(),
+                    });
+                }
+
+                if engine.vars_mut().set(*id, value).is_err() {
+                    return Err(EvalError::UnknownVariable {
+                        name: id.to_string(),
+                        source: source.clone(),
+                    });
+                }
+            }
+            Op::Unpack(pattern) => {
+                let value = pop(&mut state.stack);
+                let mut values = Vec::new();
+                destructure_value(pattern, value, source, &mut values)?;
+                state.stack.extend(values);
+            }
+            Op::Bind { mode, ids } => {
+                let values = state.stack.split_off(state.stack.len() - ids.len());
+                for ((id, source), value) in ids.iter().zip(values) {
+                    match mode {
+                        BindMode::Assign => engine.set_var(*id, value, source)?,
+                        BindMode::Init => engine.vars_mut().init_local(*id, value),
+                        BindMode::Const => engine.vars_mut().init_local_const(*id, value),
+                    }
+                }
+            }
+            Op::Use { path, import } => engine.use_module(path, import.as_ref())?,
+            Op::CheckType(ty) => {
+                let value = state.stack.last().expect("type check has a value");
+                if !ty.matches(&value.kind()) {
+                    return Err(EvalError::TypeMismatch {
+                        expect: *ty,
+                        found: value.kind(),
+                        source: source.clone(),
+                    });
+                }
+            }
+            Op::Call { count, target } => {
+                let params = state.stack.split_off(state.stack.len() - count);
+                let Value::Func(func) = pop(&mut state.stack) else {
+                    unreachable!("calls are compiled after their function");
+                };
+                state
+                    .stack
+                    .push(call(engine, func, *target, params, source)?);
+            }
+            Op::CallSpread { target } => {
+                let Value::Tuple(params) = pop(&mut state.stack) else {
+                    unreachable!("spread calls are compiled after a tuple");
+                };
+                let Value::Func(func) = pop(&mut state.stack) else {
+                    unreachable!("calls are compiled after their function");
+                };
+                let params = params.to_vec();
+                state
+                    .stack
+                    .push(call(engine, func, *target, params, source)?);
+            }
+            Op::Jump(target) => state.index = *target,
+            Op::JumpIfFalse(target) => match pop(&mut state.stack) {
+                Value::Bool(true) => (),
+                Value::Bool(false) => state.index = *target,
+                value => {
+                    return Err(EvalError::UnexpectedType {
+                        expect: ValueKind::Bool,
+                        found: value.kind(),
+                        source: source.clone(),
+                    })
+                }
+            },
+            Op::Catch(handler) => state.catches.push(Catch {
+                height: state.stack.len(),
+                scopes: state.scopes,
+                handler: *handler,
+            }),
+            Op::EndCatch => {
+                state.catches.pop();
+            }
+            Op::PushScope => {
+                engine.vars_mut().push_scope();
+                state.scopes += 1;
+            }
+            Op::PopScope => state.pop_scopes(state.scopes - 1, engine),
+            Op::Fail(error) => return Err(error.clone()),
+        }
+
+        Ok(())
+    }
+}
+
+/// The mutable state of a running chunk.
+struct State<Source> {
+    stack: Vec<Value<Source>>,
+    result: Value<Source>,
+    index: usize,
+    catches: Vec<Catch>,
+    scopes: usize,
+}
+
+impl<Source> State<Source> {
+    /// Pops the scopes pushed by the chunk until only `count` remain.
+    fn pop_scopes(&mut self, count: usize, engine: &mut Engine<Source>) {
+        while self.scopes > count {
+            engine.vars_mut().pop_scope();
+            self.scopes -= 1;
+        }
+    }
+}
+
+/// Where to continue when a recoverable error is caught.
+struct Catch {
+    height: usize,
+    scopes: usize,
+    handler: usize,
+}
+
+/// Calls a function, updating the target variable if it mutates its first param.
+fn call<Source: Clone>(
+    engine: &mut Engine<Source>,
+    func: FuncPtr<Source>,
+    target: Option<Symbol>,
+    mut params: Vec<Value<Source>>,
+    source: &Source,
+) -> Result<Value<Source>, EvalError<Source>> {
+    if !func.mutates() {
+        return func.call(source, params, engine);
+    }
+
+    // the first param is moved out of the target variable instead
+    let values = params.split_off(params.len().min(1));
+    call_mut(engine, &func, target, values, source)
+}
+
+fn pop<Source>(stack: &mut Vec<Value<Source>>) -> Value<Source> {
+    stack.pop().expect("compiled ops are balanced")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{
+        func::Func,
+        node::{Builder, Node},
+        Expr, ExprNode, Statement, Visibility,
+    };
+
+    use super::*;
+
+    type BinaryExpr = fn(Box<ExprNode<()>>, Box<ExprNode<()>>) -> Expr<()>;
+
+    fn var(name: &str) -> ExprNode<()> {
+        Expr::Var(name.into()).build_node(())
+    }
+
+    fn int(value: i32) -> ExprNode<()> {
+        Expr::Int(value.into()).build_node(())
+    }
+
+    fn binary(op: BinaryExpr, lhs: ExprNode<()>, rhs: ExprNode<()>) -> ExprNode<()> {
+        op(Box::new(lhs), Box::new(rhs)).build_node(())
+    }
+
+    fn assign(init: bool, lhs: ExprNode<()>, rhs: ExprNode<()>) -> StatementNode<()> {
+        let ty = None;
+        Statement::Assign { init, lhs, ty, rhs }.build_node(())
+    }
+
+    fn tuple(items: Vec<ExprNode<()>>) -> ExprNode<()> {
+        Expr::Tuple(items).build_node(())
+    }
+
+    /// Evaluates with both the tree walker and the vm and checks they agree
+    fn run_both(script: &[StatementNode<()>]) -> (Engine<()>, Value<()>) {
+        let mut tree = Engine::new();
+        let mut expect = Value::None;
+        for statement in script {
+            expect = tree.eval(statement).unwrap();
+        }
+
+        let mut engine = Engine::new();
+        let found = Chunk::compile(script).run(&mut engine).unwrap();
+        assert!(found.total_eq(&expect), "expected {expect}, found {found}");
+        (engine, found)
+    }
+
+    #[test]
+    fn matches_tree_walker() {
+        // let total, i = 0, 0
+        // while i < 10:
+        //     total, i = total + i * i, i + 1
+        //     total
+        let square = binary(Expr::Mul, var("i"), var("i"));
+        let script = [
+            assign(
+                true,
+                tuple(vec![var("total"), var("i")]),
+                tuple(vec![int(0), int(0)]),
+            ),
+            Statement::While {
+                cond: binary(Expr::Lt, var("i"), int(10)),
+                body: vec![
+                    assign(
+                        false,
+                        tuple(vec![var("total"), var("i")]),
+                        tuple(vec![
+                            binary(Expr::Add, var("total"), square),
+                            binary(Expr::Add, var("i"), int(1)),
+                        ]),
+                    ),
+                    Statement::Expr {
+                        expr: var("total"),
+                        closed: false,
+                    }
+                    .build_node(()),
+                ],
+            }
+            .build_node(()),
+        ];
+
+        let (engine, value) = run_both(&script);
+        assert!(matches!(value, Value::Int(i) if i == 285.into()));
+        assert!(matches!(engine.vars().get("i"), Some(Value::Int(i)) if *i == 10.into()));
+    }
+
+    #[test]
+    fn splat_matches_tree_walker() {
+        // let a = (1, 2)
+        // (0, *a, join("", chars(format("{}{}", *a))))
+        let splat = |expr| Expr::Splat(Box::new(expr)).build_node(());
+        let string = |s: &str| Expr::String(s.into()).build_node(());
+        let call = |name: &str, params| Expr::Call {
+            name: name.into(),
+            params,
+        };
+        let format = call("format", vec![string("{}{}"), splat(var("a"))]);
+        let chars = call("chars", vec![format.build_node(())]);
+        let join = call("join", vec![string(""), chars.build_node(())]);
+        let script = [
+            assign(true, var("a"), tuple(vec![int(1), int(2)])),
+            Statement::Expr {
+                expr: tuple(vec![int(0), splat(var("a")), join.build_node(())]),
+                closed: false,
+            }
+            .build_node(()),
+        ];
+
+        let (_, value) = run_both(&script);
+        assert_eq!(value.to_string(), "(0, 1, 2, 12)");
+
+        let mut engine = Engine::<()>::new();
+        let script = [Statement::Expr {
+            expr: tuple(vec![splat(int(1))]),
+            closed: false,
+        }
+        .build_node(())];
+        assert!(matches!(
+            Chunk::compile(&script).run(&mut engine),
+            Err(EvalError::InvalidSplat {
+                found: ValueKind::Int,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn at_pattern_matches_tree_walker() {
+        // let t = (1, 2)
+        // let whole @ (a, b) = t
+        // (whole, a, b)
+        let pattern = tuple(vec![var("a"), var("b")]);
+        let at = Expr::At {
+            name: "whole".into(),
+            pattern: Box::new(pattern),
+        };
+        let script = [
+            assign(true, var("t"), tuple(vec![int(1), int(2)])),
+            assign(true, at.build_node(()), var("t")),
+            Statement::Expr {
+                expr: tuple(vec![var("whole"), var("a"), var("b")]),
+                closed: false,
+            }
+            .build_node(()),
+        ];
+
+        let (_, value) = run_both(&script);
+        assert_eq!(value.to_string(), "((1, 2), 1, 2)");
+    }
+
+    #[test]
+    fn try_matches_tree_walker() {
+        // (1, try (2, 3 % 0) catch e: e, try 4 catch e: 5)
+        let try_expr = |body, handler| {
+            Expr::Try {
+                body: Box::new(body),
+                name: "e".into(),
+                handler: Box::new(handler),
+            }
+            .build_node(())
+        };
+        let fails = tuple(vec![int(2), binary(Expr::Modulo, int(3), int(0))]);
+        let script = [Statement::Expr {
+            expr: tuple(vec![
+                int(1),
+                try_expr(fails, var("e")),
+                try_expr(int(4), int(5)),
+            ]),
+            closed: false,
+        }
+        .build_node(())];
+
+        let (_, value) = run_both(&script);
+        assert_eq!(value.to_string(), "(1, cannot divide an int by zero, 4)");
+
+        // resource limits are never caught
+        let repeat = binary(Expr::Mul, Expr::String("ab".into()).build_node(()), int(8));
+        let script = [Statement::Expr {
+            expr: try_expr(repeat, int(0)),
+            closed: false,
+        }
+        .build_node(())];
+        let mut engine = Engine::<()>::new();
+        engine.set_alloc_limit(Some(8));
+        assert!(matches!(
+            Chunk::compile(&script).run(&mut engine),
+            Err(EvalError::AllocationLimit { .. })
+        ));
+        assert!(matches!(
+            engine.eval(&script[0]),
+            Err(EvalError::AllocationLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn catch_names_are_scoped() {
+        let try_expr = |body, name: &str, handler| {
+            Expr::Try {
+                body: Box::new(body),
+                name: name.into(),
+                handler: Box::new(handler),
+            }
+            .build_node(())
+        };
+        let fails = |value| binary(Expr::Modulo, int(value), int(0));
+
+        // const e = 5
+        // let a = try 1 % 0 catch e: e
+        // let b = try (try 2 % 0 catch zz: 3 % 0) catch y: 4
+        // (e, a, b)
+        let script = [
+            Statement::Const {
+                lhs: var("e"),
+                ty: None,
+                rhs: int(5),
+            }
+            .build_node(()),
+            assign(true, var("a"), try_expr(fails(1), "e", var("e"))),
+            assign(
+                true,
+                var("b"),
+                try_expr(try_expr(fails(2), "zz", fails(3)), "y", int(4)),
+            ),
+            Statement::Expr {
+                expr: tuple(vec![var("e"), var("a"), var("b")]),
+                closed: false,
+            }
+            .build_node(()),
+        ];
+
+        let (engine, value) = run_both(&script);
+        assert_eq!(value.to_string(), "(5, cannot divide an int by zero, 4)");
+
+        // handler names shadow outer names without leaking out of the handler
+        let mut tree = Engine::new();
+        for statement in &script {
+            tree.eval(statement).unwrap();
+        }
+        for engine in [engine, tree] {
+            assert!(engine.vars().is_const("e"));
+            assert!(engine.get_variable("zz").is_none());
+            assert!(engine.get_variable("y").is_none());
+        }
+    }
+
+    #[test]
+    fn mutation_matches_tree_walker() {
+        // let a = unwrap(parse_json("[1]"))
+        // let b = a
+        // push(a, 2)
+        // (a, b, pop(a), a)
+        let call = |name: &str, params| {
+            Expr::Call {
+                name: name.into(),
+                params,
+            }
+            .build_node(())
+        };
+        let list = call(
+            "unwrap",
+            vec![call(
+                "parse_json",
+                vec![Expr::String("[1]".into()).build_node(())],
+            )],
+        );
+        let script = [
+            assign(true, var("a"), list),
+            assign(true, var("b"), var("a")),
+            Statement::Expr {
+                expr: call("push", vec![var("a"), int(2)]),
+                closed: true,
+            }
+            .build_node(()),
+            Statement::Expr {
+                expr: tuple(vec![
+                    var("a"),
+                    var("b"),
+                    call("pop", vec![var("a")]),
+                    var("a"),
+                ]),
+                closed: false,
+            }
+            .build_node(()),
+        ];
+
+        let (_, value) = run_both(&script);
+        assert_eq!(value.to_string(), "([1, 2], [1], 2, [1])");
+
+        // push((1,), 2)
+        let script = [Statement::Expr {
+            expr: call("push", vec![tuple(vec![int(1)]), int(2)]),
+            closed: false,
+        }
+        .build_node(())];
+        assert!(matches!(
+            Chunk::compile(&script).run(&mut Engine::new()),
+            Err(EvalError::ImmutableParam { .. })
+        ));
+        assert!(matches!(
+            Engine::new().eval(&script[0]),
+            Err(EvalError::ImmutableParam { .. })
+        ));
+    }
+
+    #[test]
+    fn map_fields_match_tree_walker() {
+        // let m = unwrap(parse_json('{"a": 1, "0": [2]}'))
+        // (m.a, m.0)
+        let json = Expr::String(r#"{"a": 1, "0": [2]}"#.into()).build_node(());
+        let parse = Expr::Call {
+            name: "parse_json".into(),
+            params: vec![json],
+        };
+        let map = Expr::Call {
+            name: "unwrap".into(),
+            params: vec![parse.build_node(())],
+        };
+        let field = |name: &str| {
+            Expr::Field {
+                target: Box::new(var("m")),
+                field: Node::new(name.into(), ()),
+            }
+            .build_node(())
+        };
+        let script = [
+            assign(true, var("m"), map.build_node(())),
+            Statement::Expr {
+                expr: tuple(vec![field("a"), field("0")]),
+                closed: false,
+            }
+            .build_node(()),
+        ];
+
+        let (mut engine, value) = run_both(&script);
+        assert_eq!(value.to_string(), "(1, [2])");
+
+        // m.b
+        let script = [Statement::Expr {
+            expr: field("b"),
+            closed: false,
+        }
+        .build_node(())];
+        assert!(matches!(
+            Chunk::compile(&script).run(&mut engine),
+            Err(EvalError::UnknownField { field, ty: ValueKind::Map, .. }) if field == "b"
+        ));
+    }
+
+    #[test]
+    fn repeat_runs_at_least_once() {
+        // let i = 5
+        // repeat:
+        //     i = i + 1
+        // until i > 3
+        // i
+        let script = [
+            assign(true, var("i"), int(5)),
+            Statement::Repeat {
+                body: vec![assign(false, var("i"), binary(Expr::Add, var("i"), int(1)))],
+                cond: binary(Expr::Gt, var("i"), int(3)),
+            }
+            .build_node(()),
+            Statement::Expr {
+                expr: var("i"),
+                closed: false,
+            }
+            .build_node(()),
+        ];
+
+        let (_, value) = run_both(&script);
+        assert!(matches!(value, Value::Int(i) if i == 6.into()));
+    }
+
+    #[test]
+    fn functions_match_tree_walker() {
+        // let fact = fn(n): n < 2 ? 1 : n * fact(n - 1)
+        // (fact(5), try fact(none) catch e: 0)
+        let call = |name: &str, param| {
+            Expr::Call {
+                name: name.into(),
+                params: vec![param],
+            }
+            .build_node(())
+        };
+        let body = Expr::Ternary {
+            cond: Box::new(binary(Expr::Lt, var("n"), int(2))),
+            pass: Box::new(int(1)),
+            fail: Box::new(binary(
+                Expr::Mul,
+                var("n"),
+                call("fact", binary(Expr::Sub, var("n"), int(1))),
+            )),
+        };
+        let func = Func {
+            vis: Visibility::Private.build_node(()),
+            params: vec!["n".into()],
+            output: None,
+            body: vec![Statement::Expr {
+                expr: body.build_node(()),
+                closed: false,
+            }
+            .build_node(())],
+        };
+        let fails = Expr::Try {
+            body: Box::new(call("fact", Expr::None.build_node(()))),
+            name: "e".into(),
+            handler: Box::new(int(0)),
+        };
+        let script = [
+            assign(
+                true,
+                var("fact"),
+                Expr::Func(func.build_node(())).build_node(()),
+            ),
+            Statement::Expr {
+                expr: tuple(vec![call("fact", int(5)), fails.build_node(())]),
+                closed: false,
+            }
+            .build_node(()),
+        ];
+
+        let (mut engine, value) = run_both(&script);
+        assert_eq!(value.to_string(), "(120, 0)");
+
+        // the params of the failed call do not leak out of it
+        assert!(engine.get_variable("n").is_none());
+        let chunk = Chunk::compile(&script[1..]);
+        assert!(chunk.run(&mut engine).unwrap().total_eq(&value));
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let mut engine = Engine::<()>::new();
+        let walrus = Expr::Walrus(Box::new(var("x")), Box::new(int(1))).build_node(());
+        let script = [Statement::Expr {
+            expr: walrus,
+            closed: true,
+        }
+        .build_node(())];
+        let chunk = Chunk::compile(&script);
+        assert!(matches!(
+            chunk.run(&mut engine),
+            Err(EvalError::UnknownVariable { name, .. }) if name == "x"
+        ));
+
+        let cond = Expr::Ternary {
+            cond: Box::new(int(1)),
+            pass: Box::new(int(2)),
+            fail: Box::new(int(3)),
+        };
+        let script = [Statement::Expr {
+            expr: cond.build_node(()),
+            closed: false,
+        }
+        .build_node(())];
+        assert!(matches!(
+            Chunk::compile(&script).run(&mut engine),
+            Err(EvalError::UnexpectedType {
+                expect: ValueKind::Bool,
+                found: ValueKind::Int,
+                ..
+            })
+        ));
+    }
+}
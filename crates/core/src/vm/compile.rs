@@ -0,0 +1,397 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::{
+        expr::{self, has_splat},
+        Expr, ExprNode, Node, Statement, StatementNode, Symbol, Type,
+    },
+    engine::{
+        ops::{BinaryOp, UnaryOp},
+        EvalError, Value,
+    },
+};
+
+use super::{op::BindMode, Chunk, Op};
+
+/// Compiles statements into a [`Chunk`].
+pub struct Compiler<Source> {
+    ops: Vec<Op<Source>>,
+    sources: Vec<Source>,
+}
+
+impl<Source> Default for Compiler<Source> {
+    fn default() -> Self {
+        Self {
+            ops: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl<Source: Clone> Compiler<Source> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn finish(self) -> Chunk<Source> {
+        Chunk::new(self.ops, self.sources)
+    }
+
+    fn emit(&mut self, op: Op<Source>, source: &Source) -> usize {
+        self.ops.push(op);
+        self.sources.push(source.clone());
+        self.ops.len() - 1
+    }
+
+    /// Points the jump at `index` to the next op that will be emitted
+    fn patch(&mut self, index: usize) {
+        let next = self.ops.len();
+        match &mut self.ops[index] {
+            Op::Jump(target) | Op::JumpIfFalse(target) | Op::Catch(target) => *target = next,
+            _ => unreachable!("only jumps can be patched"),
+        }
+    }
+
+    pub fn statement(&mut self, statement: &StatementNode<Source>) {
+        let source = &statement.source;
+        match &statement.item {
+            Statement::Expr { expr, closed } => {
+                self.expr(expr);
+                match closed {
+                    false => self.emit(Op::SetResult, source),
+                    true => {
+                        self.emit(Op::Pop, source);
+                        self.emit(Op::ClearResult, source)
+                    }
+                };
+            }
+            Statement::Assign { init, lhs, ty, rhs } => {
+                let mode = match init {
+                    false => BindMode::Assign,
+                    true => BindMode::Init,
+                };
+                self.bind(mode, lhs, ty.as_ref(), rhs);
+                self.emit(Op::ClearResult, source);
+            }
+            Statement::Const { lhs, ty, rhs } => {
+                self.bind(BindMode::Const, lhs, ty.as_ref(), rhs);
+                self.emit(Op::ClearResult, source);
+            }
+            Statement::While { cond, body } => {
+                self.emit(Op::ClearResult, source);
+                let start = self.ops.len();
+                self.expr(cond);
+                let exit = self.emit(Op::JumpIfFalse(0), &cond.source);
+                for statement in body {
+                    self.statement(statement);
+                }
+                self.emit(Op::Jump(start), source);
+                self.patch(exit);
+            }
+            Statement::Repeat { body, cond } => {
+                self.emit(Op::ClearResult, source);
+                let start = self.ops.len();
+                for statement in body {
+                    self.statement(statement);
+                }
+                self.expr(cond);
+                self.emit(Op::JumpIfFalse(start), &cond.source);
+            }
+            Statement::If { cond, pass, fail } => {
+                self.expr(cond);
+                let skip_pass = self.emit(Op::JumpIfFalse(0), &cond.source);
+                self.emit(Op::ClearResult, source);
+                for statement in pass {
+                    self.statement(statement);
+                }
+                let skip_fail = self.emit(Op::Jump(0), source);
+                self.patch(skip_pass);
+                self.emit(Op::ClearResult, source);
+                for statement in fail {
+                    self.statement(statement);
+                }
+                self.patch(skip_fail);
+            }
+            Statement::Use { path, import } => {
+                let path = path.clone();
+                let import = import.clone();
+                self.emit(Op::Use { path, import }, source);
+                self.emit(Op::ClearResult, source);
+            }
+            Statement::Commented { statement, .. } => self.statement(statement),
+        }
+    }
+
+    pub fn expr(&mut self, expr: &ExprNode<Source>) {
+        let source = &expr.source;
+        match &expr.item {
+            // SIMPLE VALUES
+            Expr::None => self.push(Value::None, source),
+            Expr::Bool(value) => self.push(Value::Bool(*value), source),
+            Expr::Int(value) => self.push(Value::Int(value.clone()), source),
+            Expr::Float(value) => self.push(Value::Float(*value), source),
+            Expr::String(value) => self.push(Value::String(value.clone()), source),
+            Expr::Func(func) => {
+                let body = Rc::new(Chunk::compile(&func.body));
+                let func = func.item.clone();
+                self.emit(Op::Func { func, body }, source);
+            }
+            Expr::Tuple(exprs) => {
+                self.emit(Op::CheckAlloc(exprs.len()), source);
+                if !self.items(exprs, source) {
+                    self.emit(Op::Tuple(exprs.len()), source);
+                }
+            }
+
+            // SPLAT
+            Expr::Splat(inner) => {
+                self.expr(inner);
+                self.emit(Op::Splat, source);
+            }
+
+            // PATTERNS
+            Expr::At { .. } => self.fail(
+                EvalError::InvalidPattern {
+                    source: source.clone(),
+                },
+                source,
+            ),
+
+            // VARIABLES
+            Expr::Var(id) => {
+                self.emit(Op::Load(*id), source);
+            }
+
+            // FUNCTION CALL
+            Expr::Call { name, params } => {
+                self.emit(Op::LoadFunc(*name), source);
+                let target = match params.first().map(|param| &param.item) {
+                    Some(Expr::Var(id)) => Some(*id),
+                    _ => None,
+                };
+                match self.items(params, source) {
+                    true => self.emit(Op::CallSpread { target }, source),
+                    false => {
+                        let count = params.len();
+                        self.emit(Op::Call { count, target }, source)
+                    }
+                };
+            }
+
+            // FIELD ACCESS
+            Expr::Field { target, field } => self.field(target, field, false),
+            Expr::OptField { target, field } => self.field(target, field, true),
+
+            // WALRUS
+            Expr::Walrus(lhs, rhs) => {
+                self.expr(rhs);
+                match &lhs.item {
+                    Expr::Var(id) => self.emit(Op::Walrus(*id), &lhs.source),
+                    _ => self.emit(
+                        Op::Fail(EvalError::InvalidAssign {
+                            source: lhs.source.clone(),
+                        }),
+                        &lhs.source,
+                    ),
+                };
+            }
+
+            // TERNARY
+            Expr::Ternary { cond, pass, fail } => {
+                self.expr(cond);
+                let skip_pass = self.emit(Op::JumpIfFalse(0), &cond.source);
+                self.expr(pass);
+                let skip_fail = self.emit(Op::Jump(0), source);
+                self.patch(skip_pass);
+                self.expr(fail);
+                self.patch(skip_fail);
+            }
+
+            // ERROR HANDLING
+            Expr::Try {
+                body,
+                name,
+                handler,
+            } => {
+                let catch = self.emit(Op::Catch(0), source);
+                self.expr(body);
+                self.emit(Op::EndCatch, source);
+                let skip_handler = self.emit(Op::Jump(0), source);
+                self.patch(catch);
+                self.emit(Op::PushScope, source);
+                let ids = vec![(*name, source.clone())];
+                let mode = BindMode::Init;
+                self.emit(Op::Bind { mode, ids }, source);
+                self.expr(handler);
+                self.emit(Op::PopScope, source);
+                self.patch(skip_handler);
+            }
+
+            // UNARY OPS
+            Expr::Pos(inner) => self.unary(UnaryOp::Pos, inner, source),
+            Expr::Neg(inner) => self.unary(UnaryOp::Neg, inner, source),
+            Expr::Not(inner) => self.unary(UnaryOp::Not, inner, source),
+
+            // BINARY OPS
+            Expr::Add(lhs, rhs) => self.binary(BinaryOp::Add, lhs, rhs, source),
+            Expr::Sub(lhs, rhs) => self.binary(BinaryOp::Sub, lhs, rhs, source),
+            Expr::Mul(lhs, rhs) => self.binary(BinaryOp::Mul, lhs, rhs, source),
+            Expr::Div(lhs, rhs) => self.binary(BinaryOp::Div, lhs, rhs, source),
+            Expr::Modulo(lhs, rhs) => self.binary(BinaryOp::Modulo, lhs, rhs, source),
+            Expr::Pow(lhs, rhs) => self.binary(BinaryOp::Pow, lhs, rhs, source),
+            Expr::Eq(lhs, rhs) => self.binary(BinaryOp::Eq, lhs, rhs, source),
+            Expr::Lt(lhs, rhs) => self.binary(BinaryOp::Lt, lhs, rhs, source),
+            Expr::Gt(lhs, rhs) => self.binary(BinaryOp::Gt, lhs, rhs, source),
+            Expr::NEq(lhs, rhs) => self.binary(BinaryOp::NEq, lhs, rhs, source),
+            Expr::LtEq(lhs, rhs) => self.binary(BinaryOp::LtEq, lhs, rhs, source),
+            Expr::GtEq(lhs, rhs) => self.binary(BinaryOp::GtEq, lhs, rhs, source),
+            Expr::And(lhs, rhs) => self.binary(BinaryOp::And, lhs, rhs, source),
+            Expr::Or(lhs, rhs) => self.binary(BinaryOp::Or, lhs, rhs, source),
+        }
+    }
+
+    /// Destructures the value of `rhs` at runtime with a pattern.
+    fn unpack(
+        &mut self,
+        pattern: &ExprNode<Source>,
+        rhs: &ExprNode<Source>,
+        ids: &mut Vec<(Symbol, Source)>,
+    ) {
+        self.expr(rhs);
+        self.emit(Op::Unpack(pattern.clone()), &rhs.source);
+
+        let mut pattern_ids = Vec::new();
+        expr::pattern_ids(pattern, &mut pattern_ids);
+        ids.extend(pattern_ids.into_iter().map(|id| (id, rhs.source.clone())));
+    }
+
+    /// Compiles the items of a tuple or call.
+    ///
+    /// Without splats each item is pushed on its own and `false` is returned.
+    /// Otherwise every item is collected into a single tuple and `true` is returned.
+    fn items(&mut self, exprs: &[ExprNode<Source>], source: &Source) -> bool {
+        if !has_splat(exprs) {
+            for expr in exprs {
+                self.expr(expr);
+            }
+            return false;
+        }
+
+        for expr in exprs {
+            match &expr.item {
+                Expr::Splat(inner) => {
+                    self.expr(inner);
+                    self.emit(Op::Splat, &expr.source);
+                }
+                _ => {
+                    self.expr(expr);
+                    self.emit(Op::Tuple(1), &expr.source);
+                }
+            }
+        }
+        self.emit(Op::Concat(exprs.len()), source);
+        true
+    }
+
+    fn push(&mut self, value: Value<Source>, source: &Source) {
+        self.emit(Op::Push(value), source);
+    }
+
+    fn fail(&mut self, error: EvalError<Source>, source: &Source) {
+        self.emit(Op::Fail(error), source);
+    }
+
+    fn field(&mut self, target: &ExprNode<Source>, field: &Node<String, Source>, optional: bool) {
+        self.expr(target);
+        let field = field.clone();
+        let source = field.source.clone();
+        self.emit(Op::Field { field, optional }, &source);
+    }
+
+    fn unary(&mut self, op: UnaryOp, inner: &ExprNode<Source>, source: &Source) {
+        self.expr(inner);
+        self.emit(Op::Unary(op), source);
+    }
+
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        lhs: &ExprNode<Source>,
+        rhs: &ExprNode<Source>,
+        source: &Source,
+    ) {
+        self.expr(lhs);
+        self.expr(rhs);
+        self.emit(Op::Binary(op), source);
+    }
+
+    fn bind(
+        &mut self,
+        mode: BindMode,
+        lhs: &ExprNode<Source>,
+        ty: Option<&Node<Type, Source>>,
+        rhs: &ExprNode<Source>,
+    ) {
+        // single variables are checked against their annotation
+        if let Expr::Var(id) = &lhs.item {
+            self.expr(rhs);
+            if let Some(ty) = ty {
+                self.emit(Op::CheckType(ty.item), &rhs.source);
+            }
+            let ids = vec![(*id, rhs.source.clone())];
+            self.emit(Op::Bind { mode, ids }, &lhs.source);
+            return;
+        }
+
+        // otherwise all values are destructured before any are bound
+        let mut ids = Vec::new();
+        self.destructure(lhs, rhs, &mut ids);
+        self.emit(Op::Bind { mode, ids }, &lhs.source);
+    }
+
+    fn destructure(
+        &mut self,
+        lhs: &ExprNode<Source>,
+        rhs: &ExprNode<Source>,
+        ids: &mut Vec<(Symbol, Source)>,
+    ) {
+        match &lhs.item {
+            Expr::Var(id) => {
+                self.expr(rhs);
+                ids.push((*id, rhs.source.clone()));
+            }
+            Expr::Tuple(lhs_exprs) => match &rhs.item {
+                Expr::Tuple(rhs_exprs) if has_splat(lhs_exprs) || has_splat(rhs_exprs) => {
+                    self.unpack(lhs, rhs, ids)
+                }
+                Expr::Tuple(rhs_exprs) if lhs_exprs.len() == rhs_exprs.len() => {
+                    for (lhs, rhs) in lhs_exprs.iter().zip(rhs_exprs) {
+                        self.destructure(lhs, rhs, ids);
+                    }
+                }
+                Expr::Tuple(rhs_exprs) => {
+                    self.fail(
+                        EvalError::InvalidTupleSize {
+                            lhs_count: lhs_exprs.len(),
+                            rhs_count: rhs_exprs.len(),
+                            rest: false,
+                            lhs_source: lhs.source.clone(),
+                            rhs_source: rhs.source.clone(),
+                        },
+                        &lhs.source,
+                    );
+                }
+                _ => self.unpack(lhs, rhs, ids),
+            },
+            Expr::At { .. } => self.unpack(lhs, rhs, ids),
+            _ => {
+                self.fail(
+                    EvalError::InvalidAssign {
+                        source: lhs.source.clone(),
+                    },
+                    &lhs.source,
+                );
+            }
+        }
+    }
+}
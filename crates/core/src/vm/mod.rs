@@ -0,0 +1,16 @@
+//! A stack based bytecode target for statements.
+//!
+//! A [`Chunk`] is compiled once from the AST and can then be run many times
+//! without walking the tree again. Running a chunk has the same behaviour as
+//! evaluating its statements with [`Engine::eval`](crate::Engine::eval).
+//!
+//! Functions defined in a chunk have their bodies compiled along with it,
+//! so calling them runs the compiled body rather than walking the tree.
+//! Functions defined by [`Engine::eval`](crate::Engine::eval) are still walked when called.
+
+pub mod chunk;
+pub mod compile;
+pub mod op;
+
+pub use chunk::Chunk;
+pub use op::Op;
@@ -0,0 +1,100 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::{func::Func, ExprNode, Import, Node, Symbol, Type},
+    engine::{
+        ops::{BinaryOp, UnaryOp},
+        EvalError, Value,
+    },
+};
+
+use super::Chunk;
+
+/// How an [`Op::Bind`] stores its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindMode {
+    Assign,
+    Init,
+    Const,
+}
+
+#[derive(Debug, Clone)]
+pub enum Op<Source> {
+    /// Pushes a constant value
+    Push(Value<Source>),
+    /// Pushes a new function built from its definition,
+    /// which runs its compiled body when called
+    Func {
+        func: Func<Source>,
+        body: Rc<Chunk<Source>>,
+    },
+    /// Pushes the value of a variable
+    Load(Symbol),
+    /// Pushes a function that will be called by [`Op::Call`]
+    LoadFunc(Symbol),
+    /// Discards the top value
+    Pop,
+    /// Moves the top value into the result of the chunk
+    SetResult,
+    /// Resets the result of the chunk to none
+    ClearResult,
+    /// Errors if a value of this size is larger than the alloc limit
+    CheckAlloc(usize),
+    /// Pops values into a tuple
+    Tuple(usize),
+    /// Replaces the top tuple or list with a tuple of its items
+    Splat,
+    /// Pops tuples and joins their items into one tuple
+    Concat(usize),
+    Unary(UnaryOp),
+    Binary(BinaryOp),
+    /// Replaces the top value with one of its fields
+    ///
+    /// Optional fields leave a none value in place.
+    Field {
+        field: Node<String, Source>,
+        optional: bool,
+    },
+    /// Sets a variable to the top value without popping it
+    Walrus(Symbol),
+    /// Pops a value and pushes the values a pattern destructures it into
+    Unpack(ExprNode<Source>),
+    /// Pops values and binds them to variables in order
+    Bind {
+        mode: BindMode,
+        ids: Vec<(Symbol, Source)>,
+    },
+    /// Errors if the top value does not match the type
+    CheckType(Type),
+    /// Pops parameters and calls the function below them
+    ///
+    /// The target is the variable the first parameter was loaded from,
+    /// which is updated if the function mutates its first parameter.
+    Call {
+        count: usize,
+        target: Option<Symbol>,
+    },
+    /// Pops a tuple of parameters and calls the function below it
+    CallSpread {
+        target: Option<Symbol>,
+    },
+    /// Imports the top level values of the module at a path
+    Use {
+        path: Node<String, Source>,
+        import: Option<Import<Source>>,
+    },
+    /// Continues at the target op
+    Jump(usize),
+    /// Pops a bool and continues at the target op if it is false
+    JumpIfFalse(usize),
+    /// Continues at the target op with the error message pushed
+    /// if a recoverable error occurs before the matching [`Op::EndCatch`]
+    Catch(usize),
+    /// Stops catching errors for the latest [`Op::Catch`]
+    EndCatch,
+    /// Starts a scope for variables that only live until the matching [`Op::PopScope`]
+    PushScope,
+    PopScope,
+    /// Fails with an error that was found while compiling
+    Fail(EvalError<Source>),
+}
@@ -15,6 +15,14 @@ pub enum LexError {
     InvalidSymbol,
     #[display(fmt = "indentation contains invalid {} characters", _0)]
     InvalidIndent(IndentType),
+    #[display(fmt = "indentation exceeds the maximum depth of {}", _0)]
+    IndentTooDeep(usize),
     #[display(fmt = "unclosed string")]
     UnclosedString,
+    #[display(fmt = "invalid escape sequence")]
+    InvalidEscape,
+    #[display(fmt = "invalid base {} integer", _0)]
+    InvalidInteger(u32),
+    #[display(fmt = "invalid float")]
+    InvalidFloat,
 }
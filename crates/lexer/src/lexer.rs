@@ -1,6 +1,7 @@
 use std::{cmp::Ordering, iter::Peekable};
 
 use boba_script_parser::{core::dashu::integer::IBig, token::Span, Token};
+use unicode_ident::{is_xid_continue, is_xid_start};
 use unicode_segmentation::{Graphemes, UnicodeSegmentation};
 
 use crate::{error::IndentType, LexError};
@@ -15,20 +16,92 @@ enum TabStyle {
 pub struct Lexer {
     levels: Vec<usize>,
     style: TabStyle,
+    tab_width: Option<usize>,
     level: usize,
     indent: bool,
+    max_depth: usize,
+    whitespace: bool,
+    comments: bool,
+    unicode: bool,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Lexer {
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
     pub fn new() -> Self {
+        Self::with_max_depth(Self::DEFAULT_MAX_DEPTH)
+    }
+
+    pub fn with_max_depth(max_depth: usize) -> Self {
         Self {
             levels: Vec::new(),
             style: TabStyle::None,
+            tab_width: None,
             level: 0,
             indent: true,
+            max_depth,
+            whitespace: false,
+            comments: false,
+            unicode: false,
         }
     }
 
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sets if inline whitespace should be produced as whitespace tokens.
+    ///
+    /// Indentation is always produced as indent and dedent tokens.
+    pub fn preserve_whitespace(&mut self, preserve: bool) {
+        self.whitespace = preserve;
+    }
+
+    pub fn preserves_whitespace(&self) -> bool {
+        self.whitespace
+    }
+
+    /// Sets if comments should be produced as comment tokens.
+    ///
+    /// Lines holding only a comment never change the indentation level.
+    pub fn preserve_comments(&mut self, preserve: bool) {
+        self.comments = preserve;
+    }
+
+    pub fn preserves_comments(&self) -> bool {
+        self.comments
+    }
+
+    /// Sets if identifiers may contain any unicode XID characters.
+    ///
+    /// By default identifiers are restricted to ascii letters, digits and underscores.
+    pub fn unicode_idents(&mut self, unicode: bool) {
+        self.unicode = unicode;
+    }
+
+    pub fn allows_unicode_idents(&self) -> bool {
+        self.unicode
+    }
+
+    /// Sets if tabs in indentation should be expanded to the next multiple of `width` columns.
+    ///
+    /// Expanded indentation may freely mix tabs and spaces, and levels are compared by column.
+    /// By default a block may only be indented with one kind of character.
+    /// A width of zero is treated as one.
+    pub fn expand_tabs(&mut self, width: Option<usize>) {
+        self.tab_width = width.map(|width| width.max(1));
+    }
+
+    pub fn tab_width(&self) -> Option<usize> {
+        self.tab_width
+    }
+
     pub fn close_blocks(&mut self) -> usize {
         let levels = self.levels.len();
         self.levels.clear();
@@ -36,14 +109,83 @@ impl Lexer {
         levels
     }
 
+    /// Lexes a single line of source.
+    ///
+    /// Lexing stops at the first line ending, so sources with many lines
+    /// should be split with [`split_lines`] and lexed one line at a time.
     pub fn lex<'source>(&mut self, source: &'source str) -> LexTokens<'_, 'source> {
         LexTokens {
             lexer: self,
             symbols: source.graphemes(true).peekable(),
             source,
             span: Span::from(0..0),
+            newline: false,
+            field: false,
+            last: false,
+            ending: None,
         }
     }
+
+    /// Lexes the last line of a source, closing all open blocks.
+    ///
+    /// Once the line is exhausted, a dedent token is produced for every
+    /// indentation level that is still open before the tokens end.
+    pub fn lex_final<'source>(&mut self, source: &'source str) -> LexTokens<'_, 'source> {
+        LexTokens {
+            last: true,
+            ..self.lex(source)
+        }
+    }
+}
+
+/// Lexes every line of `source` with a default [`Lexer`].
+///
+/// Spans are offsets into the whole source, and all blocks are closed after the last line.
+/// Lexing stops at the first error, which is returned with its span.
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, (LexError, Span)> {
+    let mut lexer = Lexer::new();
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    let mut lines = split_lines(source).peekable();
+    while let Some(line) = lines.next() {
+        let mut line_tokens = match lines.peek() {
+            Some(_) => lexer.lex(line),
+            None => lexer.lex_final(line),
+        };
+
+        while let Some(result) = line_tokens.next() {
+            let span = line_tokens.token_span();
+            let span = Span::from(span.start + offset..span.end + offset);
+            match result {
+                Ok(token) => tokens.push((token, span)),
+                Err(error) => return Err((error, span)),
+            }
+        }
+        offset += line.len();
+    }
+    Ok(tokens)
+}
+
+/// Splits `source` into lines that each keep their line ending.
+///
+/// Unlike [`str::lines`], a lone `\r` also ends a line, since the lexer
+/// treats it as a line ending and would skip any text after it.
+pub fn split_lines(source: &str) -> impl Iterator<Item = &str> {
+    let mut rest = source;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let end = match rest.find(['\n', '\r']) {
+            Some(index) if rest[index..].starts_with("\r\n") => index + 2,
+            Some(index) => index + 1,
+            None => rest.len(),
+        };
+        let (line, remaining) = rest.split_at(end);
+        rest = remaining;
+        Some(line)
+    })
 }
 
 pub struct LexTokens<'lexer, 'source> {
@@ -51,6 +193,10 @@ pub struct LexTokens<'lexer, 'source> {
     symbols: Peekable<Graphemes<'source>>,
     source: &'source str,
     span: Span,
+    newline: bool,
+    field: bool,
+    last: bool,
+    ending: Option<Span>,
 }
 
 impl LexTokens<'_, '_> {
@@ -71,6 +217,27 @@ impl Iterator for LexTokens<'_, '_> {
     type Item = Result<Token, LexError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        match self.lex_token() {
+            Some(result) => {
+                // a line that is too deep is skipped entirely, so it is not closed with a newline
+                self.newline = !matches!(result, Err(LexError::IndentTooDeep(_)));
+                if !matches!(result, Ok(Token::Whitespace(_))) {
+                    self.field = matches!(result, Ok(Token::Period | Token::QuestionPeriod));
+                }
+                Some(result)
+            }
+            None => match self.close_line() {
+                Some(result) => Some(result),
+                None => self.close_block(),
+            },
+        }
+    }
+}
+
+impl LexTokens<'_, '_> {
+    const RECOVERY_TAB_WIDTH: usize = 4;
+
+    fn lex_token(&mut self) -> Option<Result<Token, LexError>> {
         // check if an indent has to be scanned
         if self.lexer.indent {
             self.lexer.indent = false;
@@ -89,14 +256,33 @@ impl Iterator for LexTokens<'_, '_> {
 
                 // match the symbol with the stored indent style
                 match symbol {
+                    // COMMENT LINE CASE
+                    // if comments are preserved, produce the comment without updating indent
+                    "#" if self.lexer.comments => {
+                        self.span.start = self.span.end;
+                        self.consume_symbol();
+                        return Some(Ok(self.lex_comment()));
+                    }
+
                     // EMPTY LINE CASE
                     // if a newline or comment is found
                     // consume the rest of the line
-                    "\n" | "\r" | "\r\n" | "#" => {
+                    symbol if symbol == "#" || is_newline(symbol) => {
                         self.consume_line();
                         return None;
                     }
 
+                    // EXPANDED TAB CASES
+                    // if tabs are expanded, the style is never enforced
+                    // spaces take one column and tabs move to the next tab stop
+                    " " | "\t" if self.lexer.tab_width.is_some() => {
+                        self.consume_symbol();
+                        new_level = match (symbol, self.lexer.tab_width) {
+                            ("\t", Some(width)) => (new_level / width + 1) * width,
+                            _ => new_level + 1,
+                        };
+                    }
+
                     // ARBITRARY STYLE CASES
                     // if the indent style has not been decided yet
                     // define the indent style, consume the symbol, and increment the level
@@ -126,10 +312,10 @@ impl Iterator for LexTokens<'_, '_> {
                     // INVALID STYLE CASES
                     // if the indent character doesnt match the internal style, return a tab error
                     " " if self.lexer.style == TabStyle::Tabs => {
-                        return self.tab_error(true);
+                        return self.tab_error(true, new_level);
                     }
                     "\t" if self.lexer.style == TabStyle::Spaces => {
-                        return self.tab_error(false);
+                        return self.tab_error(false, new_level);
                     }
 
                     // END CASE
@@ -156,6 +342,14 @@ impl Iterator for LexTokens<'_, '_> {
             // if the indent level is greater than the last
             // then store the new level and produce an indent token
             Ordering::Greater => {
+                // if the max depth would be exceeded, skip the line and produce an error
+                // the symbols are drained directly so the span still covers the indentation
+                if self.lexer.levels.len() >= self.lexer.max_depth {
+                    self.lexer.indent = true;
+                    for _ in self.symbols.by_ref() {}
+                    return Some(Err(LexError::IndentTooDeep(self.lexer.max_depth)));
+                }
+
                 self.lexer.levels.push(self.lexer.level);
                 return Some(Ok(Token::Indent));
             }
@@ -174,11 +368,24 @@ impl Iterator for LexTokens<'_, '_> {
             // then match the symbol to a token
             return match symbol {
                 // WHITESPACE
-                " " | "\t" => continue, // skip whitespace
+                " " | "\t" if !self.lexer.whitespace => continue, // skip whitespace
+                " " | "\t" => {
+                    while let Some(" " | "\t") = self.peek_symbol() {
+                        self.consume_symbol();
+                    }
+                    let whitespace = &self.source[self.span.range()];
+                    Some(Ok(Token::Whitespace(whitespace.to_string())))
+                }
 
                 // NEWLINE / COMMENT
                 // if a comment or newline is found, consume the line
-                "\n" | "\r" | "\r\n" | "#" => {
+                symbol if is_newline(symbol) => {
+                    self.ending = Some(self.span);
+                    self.consume_line();
+                    return None;
+                }
+                "#" if self.lexer.comments => Some(Ok(self.lex_comment())),
+                "#" => {
                     self.consume_line();
                     return None;
                 }
@@ -189,8 +396,8 @@ impl Iterator for LexTokens<'_, '_> {
                 "%" => Some(Ok(Token::Modulo)),
                 "." => Some(Ok(Token::Period)),
                 "," => Some(Ok(Token::Comma)),
+                "@" => Some(Ok(Token::At)),
                 ";" => Some(Ok(Token::SemiColon)),
-                "?" => Some(Ok(Token::Question)),
                 "(" => Some(Ok(Token::OpenParen)),
                 ")" => Some(Ok(Token::CloseParen)),
                 "{" => Some(Ok(Token::OpenCurly)),
@@ -206,6 +413,13 @@ impl Iterator for LexTokens<'_, '_> {
                     }
                     _ => Some(Ok(Token::Sub)),
                 },
+                "?" => match self.peek_symbol() {
+                    Some(".") => {
+                        self.consume_symbol();
+                        Some(Ok(Token::QuestionPeriod))
+                    }
+                    _ => Some(Ok(Token::Question)),
+                },
                 "*" => match self.peek_symbol() {
                     Some("*") => {
                         self.consume_symbol();
@@ -250,14 +464,18 @@ impl Iterator for LexTokens<'_, '_> {
                         self.consume_symbol();
                         Some(Ok(Token::Walrus))
                     }
+                    Some(":") => {
+                        self.consume_symbol();
+                        Some(Ok(Token::DoubleColon))
+                    }
                     _ => Some(Ok(Token::Colon)),
                 },
 
                 // IDENTIFIERS
-                symbol if is_ident_start(symbol) => {
+                symbol if is_ident_start(symbol, self.lexer.unicode) => {
                     loop {
                         match self.peek_symbol() {
-                            Some(symbol) if is_ident_end(symbol) => {
+                            Some(symbol) if is_ident_end(symbol, self.lexer.unicode) => {
                                 self.consume_symbol(); // consume symbol
                             }
                             _ => {
@@ -268,23 +486,46 @@ impl Iterator for LexTokens<'_, '_> {
                     }
                 }
 
+                // RADIX INTEGERS
+                // prefixed integers take all following ident symbols as digits
+                "0" if matches!(self.peek_symbol(), Some("x" | "o" | "b")) => {
+                    let radix = match self.take_symbol() {
+                        Some("x") => 16,
+                        Some("o") => 8,
+                        _ => 2,
+                    };
+
+                    while let Some(symbol) = self.peek_symbol() {
+                        match is_ident_end(symbol, false) {
+                            true => self.consume_symbol(),
+                            false => break,
+                        }
+                    }
+
+                    let digits = &self.source[self.span.start + 2..self.span.end];
+                    match IBig::from_str_radix(digits, radix) {
+                        Ok(int) => Some(Ok(Token::Int(int))),
+                        Err(_) => Some(Err(LexError::InvalidInteger(radix))),
+                    }
+                }
+
                 // NUMBERS
                 symbol if is_digit(symbol) => {
                     // start parsing an integer
                     loop {
                         match self.peek_symbol() {
                             // if a period is found then we can break and parse the float
-                            Some(".") => {
+                            // numbers directly after a period are field indices and never floats
+                            Some(".") if !self.field => {
                                 self.consume_symbol();
                                 break;
                             }
 
                             // if an f is found, then we can build and return the float early
                             Some("f") => {
-                                let float = &self.source[self.span.range()];
-                                let float = float.parse::<f64>().expect("valid float");
+                                let float = self.parse_float();
                                 self.consume_symbol(); // take after so 'f' is not included in parsing
-                                return Some(Ok(Token::Float(float)));
+                                return Some(float);
                             }
 
                             // if a digit is found then just increment the end location and continue
@@ -293,8 +534,10 @@ impl Iterator for LexTokens<'_, '_> {
                             // if anything else is found, then build the integer and return
                             _ => {
                                 let int = &self.source[self.span.range()];
-                                let int = int.parse::<IBig>().expect("valid integer");
-                                return Some(Ok(Token::Int(int)));
+                                return match int.parse::<IBig>() {
+                                    Ok(int) => Some(Ok(Token::Int(int))),
+                                    Err(_) => Some(Err(LexError::InvalidInteger(10))),
+                                };
                             }
                         }
                     }
@@ -304,10 +547,9 @@ impl Iterator for LexTokens<'_, '_> {
                         match self.peek_symbol() {
                             // if an f is found, then we can build and return the float
                             Some("f") => {
-                                let float = &self.source[self.span.range()];
-                                let float = float.parse::<f64>().expect("valid float");
+                                let float = self.parse_float();
                                 self.consume_symbol(); // consume after so 'f' is not included in parsing
-                                return Some(Ok(Token::Float(float)));
+                                return Some(float);
                             }
 
                             // if a digit is found then just increment the end location and continue
@@ -315,47 +557,56 @@ impl Iterator for LexTokens<'_, '_> {
 
                             // if anything else is found, then build the float and return
                             _ => {
-                                let float = &self.source[self.span.range()];
-                                let float = float.parse::<f64>().expect("valid float");
-                                return Some(Ok(Token::Float(float)));
+                                return Some(self.parse_float());
                             }
                         }
                     }
                 }
 
                 // STRINGS
-                "'" | "\"" => loop {
-                    let Some(next_symbol) = self.peek_symbol() else {
-                        // if there is no symbol, then the string is unclosed
-                        self.consume_line(); // consume line first
-                        return Some(Err(LexError::UnclosedString));
-                    };
-
-                    match symbol {
-                        // if a newline is found, then the string is unclosed
-                        "\n" | "\r" | "\r\n" => {
-                            self.consume_line(); // consume line first
-                            return Some(Err(LexError::UnclosedString));
-                        }
-                        // if an escape character is found, skip the next symbol
-                        "\\" => {
-                            self.consume_symbol();
-                            if let None = self.take_symbol() {
+                "'" | "\"" => {
+                    let mut string = String::new();
+                    let mut valid = true;
+                    loop {
+                        let next_symbol = match self.peek_symbol() {
+                            // if a newline or the end is found, then the string is unclosed
+                            Some(symbol) if !is_newline(symbol) => symbol,
+                            _ => {
                                 self.consume_line(); // consume line first
                                 return Some(Err(LexError::UnclosedString));
                             }
+                        };
+
+                        match next_symbol {
+                            // if an escape character is found, unescape the symbols after it
+                            "\\" => {
+                                self.consume_symbol();
+                                match self.peek_symbol() {
+                                    Some(symbol) if !is_newline(symbol) => {}
+                                    _ => continue, // let the next loop report the unclosed string
+                                }
+
+                                match self.take_escape() {
+                                    Some(char) => string.push(char),
+                                    None => valid = false,
+                                }
+                            }
+                            // if a matching symbol is found, then it is the end quote
+                            _ if next_symbol == symbol => {
+                                self.consume_symbol();
+                                return match valid {
+                                    true => Some(Ok(Token::String(string))),
+                                    false => Some(Err(LexError::InvalidEscape)),
+                                };
+                            }
+                            // otherwise the symbol is just part of the string
+                            _ => {
+                                string.push_str(next_symbol);
+                                self.consume_symbol();
+                            }
                         }
-                        // if a matching symbol is found, then it is the end quote
-                        _ if next_symbol == symbol => {
-                            self.consume_symbol();
-                            let str_range = self.span.start + 1..self.span.end - 1;
-                            let string = self.source[str_range].to_string();
-                            return Some(Ok(Token::String(string)));
-                        }
-                        // otherwise the symbol is just part of the string
-                        _ => self.consume_symbol(),
                     }
-                },
+                }
 
                 // INVALID SYMBOL
                 _ => Some(Err(LexError::InvalidSymbol)),
@@ -363,12 +614,21 @@ impl Iterator for LexTokens<'_, '_> {
         }
 
         // HELPER FUNCTIONS
-        fn is_ident_start(s: &str) -> bool {
-            s.chars().all(|c| c == '_' || c.is_ascii_alphabetic())
+        fn is_ident_start(s: &str, unicode: bool) -> bool {
+            if !unicode {
+                return s.chars().all(|c| c == '_' || c.is_ascii_alphabetic());
+            }
+
+            // graphemes may carry combining marks after the starting character
+            let mut chars = s.chars();
+            chars.next().is_some_and(|c| c == '_' || is_xid_start(c)) && chars.all(is_xid_continue)
         }
 
-        fn is_ident_end(s: &str) -> bool {
-            s.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+        fn is_ident_end(s: &str, unicode: bool) -> bool {
+            match unicode {
+                false => s.chars().all(|c| c == '_' || c.is_ascii_alphanumeric()),
+                true => s.chars().all(is_xid_continue),
+            }
         }
 
         fn is_digit(s: &str) -> bool {
@@ -379,6 +639,55 @@ impl Iterator for LexTokens<'_, '_> {
 
 // PRIVATE HELPER METHODS
 impl<'source> LexTokens<'_, 'source> {
+    fn close_line(&mut self) -> Option<Result<Token, LexError>> {
+        // only close lines that produced tokens
+        if !self.newline {
+            return None;
+        }
+
+        // produce a single newline at the end of the line
+        // spanning the line ending if the line had one
+        self.newline = false;
+        self.span = match self.ending.take() {
+            Some(ending) => ending,
+            None => (self.span.end..self.span.end).into(),
+        };
+        Some(Ok(Token::Newline))
+    }
+
+    fn close_block(&mut self) -> Option<Result<Token, LexError>> {
+        // blocks are only closed at the end of the last line
+        if !self.last {
+            return None;
+        }
+
+        // produce a dedent for each remaining level
+        self.lexer.level = 0;
+        self.lexer.levels.pop()?;
+        self.span.start = self.span.end;
+        Some(Ok(Token::Dedent))
+    }
+
+    fn lex_comment(&mut self) -> Token {
+        // the comment runs until the line ending
+        while let Some(symbol) = self.peek_symbol() {
+            match is_newline(symbol) {
+                false => self.consume_symbol(),
+                true => break,
+            }
+        }
+
+        let text = &self.source[self.span.start + 1..self.span.end];
+        Token::Comment(text.to_string())
+    }
+
+    fn parse_float(&self) -> Result<Token, LexError> {
+        match self.source[self.span.range()].parse::<f64>() {
+            Ok(float) => Ok(Token::Float(float)),
+            Err(_) => Err(LexError::InvalidFloat),
+        }
+    }
+
     fn consume_symbol(&mut self) {
         self.take_symbol();
     }
@@ -393,19 +702,81 @@ impl<'source> LexTokens<'_, 'source> {
         Some(*self.symbols.peek()?)
     }
 
+    /// Consumes the escape sequence following a `\` in a string,
+    /// returning the char it stands for or `None` if it is not a valid escape.
+    ///
+    /// Supports `\n`, `\r`, `\t`, `\0`, `\\`, both quotes and `\u{..}` with up to six hex digits.
+    fn take_escape(&mut self) -> Option<char> {
+        match self.take_symbol()? {
+            "n" => Some('\n'),
+            "r" => Some('\r'),
+            "t" => Some('\t'),
+            "0" => Some('\0'),
+            symbol @ ("\\" | "'" | "\"") => symbol.chars().next(),
+            "u" => {
+                if self.peek_symbol() != Some("{") {
+                    return None;
+                }
+                self.consume_symbol();
+
+                let mut digits = String::new();
+                while let Some(symbol) = self.peek_symbol() {
+                    let hex = symbol.len() == 1 && symbol.as_bytes()[0].is_ascii_hexdigit();
+                    match hex && digits.len() < 6 {
+                        true => digits.push_str(symbol),
+                        false => break,
+                    }
+                    self.consume_symbol();
+                }
+
+                if self.peek_symbol() != Some("}") {
+                    return None;
+                }
+                self.consume_symbol();
+
+                let code = u32::from_str_radix(&digits, 16).ok()?;
+                char::from_u32(code)
+            }
+            _ => None,
+        }
+    }
+
     fn consume_line(&mut self) {
         self.lexer.indent = true;
         self.span.start = self.span.end;
-        while let Some(_) = self.symbols.next() {}
+
+        // store the line ending if one is consumed
+        let mut end = self.span.end;
+        for symbol in self.symbols.by_ref() {
+            if is_newline(symbol) && self.ending.is_none() {
+                self.ending = Some((end..end + symbol.len()).into());
+            }
+            end += symbol.len();
+        }
     }
 
-    fn tab_error(&mut self, space: bool) -> Option<Result<Token, LexError>> {
+    /// Consumes the rest of a mixed indent and produces an error for it.
+    ///
+    /// To recover, the line is given the level of its visual column with
+    /// [`RECOVERY_TAB_WIDTH`](Self::RECOVERY_TAB_WIDTH) wide tabs, so lexing
+    /// can continue with the block the line most likely belongs to.
+    fn tab_error(&mut self, space: bool, level: usize) -> Option<Result<Token, LexError>> {
+        let width = Self::RECOVERY_TAB_WIDTH;
+        let mut column = match self.lexer.style {
+            TabStyle::Tabs => level * width,
+            _ => level,
+        };
         while let Some(symbol) = self.peek_symbol() {
             match symbol {
-                " " | "\t" => {
+                " " => {
                     self.consume_symbol();
+                    column += 1;
                 }
-                "\n" | "\r" | "\r\n" | "#" => {
+                "\t" => {
+                    self.consume_symbol();
+                    column = (column / width + 1) * width;
+                }
+                symbol if symbol == "#" || is_newline(symbol) => {
                     self.consume_line();
                     return None;
                 }
@@ -413,9 +784,520 @@ impl<'source> LexTokens<'_, 'source> {
             }
         }
 
+        self.lexer.level = match self.lexer.style {
+            TabStyle::Tabs => column.div_ceil(width),
+            _ => column,
+        };
         match space {
             false => Some(Err(LexError::InvalidIndent(IndentType::Tab))),
             true => Some(Err(LexError::InvalidIndent(IndentType::Space))),
         }
     }
 }
+
+/// Returns true if the symbol is a line ending.
+///
+/// Graphemes always group `\r\n` together, so it is a single symbol.
+fn is_newline(symbol: &str) -> bool {
+    matches!(symbol, "\n" | "\r" | "\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use boba_script_parser::core::engine::Value;
+
+    use super::*;
+
+    #[test]
+    fn indent_too_deep() {
+        let mut lexer = Lexer::with_max_depth(2);
+        assert_eq!(lexer.max_depth(), 2);
+
+        let mut tokens = lexer.lex(" a");
+        assert_eq!(tokens.next(), Some(Ok(Token::Indent)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("a".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.next(), None);
+
+        let mut tokens = lexer.lex("  b");
+        assert_eq!(tokens.next(), Some(Ok(Token::Indent)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("b".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.next(), None);
+
+        let mut tokens = lexer.lex("   c");
+        assert_eq!(tokens.next(), Some(Err(LexError::IndentTooDeep(2))));
+        assert_eq!(tokens.token_span(), Span::from(0..3));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn expanded_tabs() {
+        let mut lexer = Lexer::new();
+        lexer.expand_tabs(Some(4));
+        assert_eq!(lexer.tab_width(), Some(4));
+
+        let mut first = |line| lexer.lex(line).collect::<Vec<_>>().remove(0);
+        assert_eq!(first("a"), Ok(Token::Ident("a".into())));
+        assert_eq!(first("\tb"), Ok(Token::Indent));
+
+        // tabs and spaces mix by column
+        assert_eq!(first("    c"), Ok(Token::Ident("c".into())));
+        assert_eq!(first("  \td"), Ok(Token::Ident("d".into())));
+        assert_eq!(first("  \t e"), Ok(Token::Indent));
+        let tokens = lexer.lex("f").collect::<Vec<_>>();
+        assert_eq!(tokens[..2], [Ok(Token::Dedent), Ok(Token::Dedent)]);
+
+        // without expansion the same indentation is an error
+        let mut lexer = Lexer::new();
+        assert_eq!(lexer.lex("\ta").count(), 3);
+        let mut tokens = lexer.lex("  \tb");
+        assert_eq!(
+            tokens.next(),
+            Some(Err(LexError::InvalidIndent(IndentType::Space)))
+        );
+    }
+
+    #[test]
+    fn mixed_indent_recovery() {
+        let mut lexer = Lexer::new();
+        let mut lines = |line| lexer.lex(line).collect::<Vec<_>>();
+        assert_eq!(lines("a").len(), 2);
+        assert_eq!(lines("    b")[0], Ok(Token::Indent));
+
+        // the error is produced once, then the line is lexed by its visual column
+        let tokens = lines("\tc");
+        assert_eq!(tokens[0], Err(LexError::InvalidIndent(IndentType::Tab)));
+        assert_eq!(tokens[1], Ok(Token::Ident("c".into())));
+        let tokens = lines("  \t  d");
+        assert_eq!(tokens[0], Err(LexError::InvalidIndent(IndentType::Tab)));
+        assert_eq!(tokens[1], Ok(Token::Indent));
+        assert_eq!(tokens[2], Ok(Token::Ident("d".into())));
+        let tokens = lines("e");
+        assert_eq!(tokens[..2], [Ok(Token::Dedent), Ok(Token::Dedent)]);
+
+        // spaces in a tab indented block round up to the next tab
+        let mut lexer = Lexer::new();
+        assert_eq!(lexer.lex("\ta").count(), 3);
+        let tokens = lexer.lex("\t  b").collect::<Vec<_>>();
+        assert_eq!(tokens[0], Err(LexError::InvalidIndent(IndentType::Space)));
+        assert_eq!(tokens[1], Ok(Token::Indent));
+    }
+
+    #[test]
+    fn tokenize_source() {
+        let tokens = tokenize("a\n  b # c\n\nd").unwrap();
+        let expect = [
+            (Token::Ident("a".into()), 0..1),
+            (Token::Newline, 1..2),
+            (Token::Indent, 2..4),
+            (Token::Ident("b".into()), 4..5),
+            (Token::Newline, 9..10),
+            (Token::Dedent, 11..11),
+            (Token::Ident("d".into()), 11..12),
+            (Token::Newline, 12..12),
+        ];
+        let expect = expect.map(|(token, span)| (token, Span::from(span)));
+        assert_eq!(tokens, expect);
+
+        assert_eq!(
+            tokenize("a\n  \"b"),
+            Err((LexError::UnclosedString, Span::from(6..6)))
+        );
+    }
+
+    #[test]
+    fn final_line_closes_blocks() {
+        let mut lexer = Lexer::new();
+        assert_eq!(lexer.lex("a").count(), 2);
+        assert_eq!(lexer.lex(" b").count(), 3);
+        assert_eq!(lexer.lex("  c").count(), 3);
+
+        let mut tokens = lexer.lex_final("  d");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("d".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Dedent)));
+        assert_eq!(tokens.token_span(), Span::from(3..3));
+        assert_eq!(tokens.next(), Some(Ok(Token::Dedent)));
+        assert_eq!(tokens.next(), None);
+
+        let mut tokens = lexer.lex("e");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("e".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn newline_at_line_end() {
+        let mut lexer = Lexer::new();
+        let mut tokens = lexer.lex("a + 1 # comment");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("a".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Add)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Int(1.into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.token_span(), Span::from(7..7));
+        assert_eq!(tokens.next(), None);
+
+        // blank lines do not produce any tokens
+        assert_eq!(lexer.lex("").next(), None);
+        assert_eq!(lexer.lex("  # comment").next(), None);
+    }
+
+    #[test]
+    fn preserved_comments() {
+        let mut lexer = Lexer::new();
+        lexer.preserve_comments(true);
+        assert!(lexer.preserves_comments());
+
+        let mut tokens = lexer.lex("a # trailing\n");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("a".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Comment(" trailing".into()))));
+        assert_eq!(tokens.token_span(), Span::from(2..12));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.token_span(), Span::from(12..13));
+        assert_eq!(tokens.next(), None);
+
+        // comment lines never produce indentation tokens
+        let mut tokens = lexer.lex("    # leading");
+        assert_eq!(tokens.next(), Some(Ok(Token::Comment(" leading".into()))));
+        assert_eq!(tokens.token_span(), Span::from(4..13));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.next(), None);
+        assert_eq!(lexer.lex("b").next(), Some(Ok(Token::Ident("b".into()))));
+    }
+
+    #[test]
+    fn field_indices() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.lex("a.0.1 + 0.1").collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Ident("a".into())),
+                Ok(Token::Period),
+                Ok(Token::Int(0.into())),
+                Ok(Token::Period),
+                Ok(Token::Int(1.into())),
+                Ok(Token::Add),
+                Ok(Token::Float(0.1)),
+                Ok(Token::Newline),
+            ]
+        );
+    }
+
+    #[test]
+    fn optional_fields() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.lex("a?.0?.b ? 1 : 2").collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Ident("a".into())),
+                Ok(Token::QuestionPeriod),
+                Ok(Token::Int(0.into())),
+                Ok(Token::QuestionPeriod),
+                Ok(Token::Ident("b".into())),
+                Ok(Token::Question),
+                Ok(Token::Int(1.into())),
+                Ok(Token::Colon),
+                Ok(Token::Int(2.into())),
+                Ok(Token::Newline),
+            ]
+        );
+    }
+
+    #[test]
+    fn preserved_whitespace() {
+        let mut lexer = Lexer::new();
+        lexer.preserve_whitespace(true);
+        assert!(lexer.preserves_whitespace());
+
+        let mut tokens = lexer.lex("a \t+ 1 ");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("a".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Whitespace(" \t".into()))));
+        assert_eq!(tokens.token_span(), Span::from(1..3));
+        assert_eq!(tokens.next(), Some(Ok(Token::Add)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Whitespace(" ".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Int(1.into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Whitespace(" ".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.next(), None);
+
+        // indentation is still produced as indent tokens
+        let mut tokens = lexer.lex("  b");
+        assert_eq!(tokens.next(), Some(Ok(Token::Indent)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("b".into()))));
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let mut lexer = Lexer::new();
+        let mut tokens = lexer.lex("a\r\n");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("a".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.token_span(), Span::from(1..3));
+        assert_eq!(tokens.next(), None);
+
+        // a lone carriage return is also a line ending
+        let mut tokens = lexer.lex("b\r");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("b".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.token_span(), Span::from(1..2));
+        assert_eq!(tokens.next(), None);
+
+        // the ending after a comment is still found
+        let mut tokens = lexer.lex("c # é\r\n");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("c".into()))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Newline)));
+        assert_eq!(tokens.token_span(), Span::from(6..8));
+        assert_eq!(tokens.next(), None);
+
+        // blank lines still produce nothing
+        assert_eq!(lexer.lex("  \r\n").next(), None);
+    }
+
+    #[test]
+    fn lone_carriage_returns() {
+        let lines = split_lines("a\rb\r\nc\n\rd").collect::<Vec<_>>();
+        assert_eq!(lines, ["a\r", "b\r\n", "c\n", "\r", "d"]);
+        assert_eq!(split_lines("").count(), 0);
+
+        // text after a lone carriage return is still lexed
+        let tokens = tokenize("a\rb\r\nc").unwrap();
+        assert_eq!(
+            tokens,
+            [
+                (Token::Ident("a".into()), Span::from(0..1)),
+                (Token::Newline, Span::from(1..2)),
+                (Token::Ident("b".into()), Span::from(2..3)),
+                (Token::Newline, Span::from(3..5)),
+                (Token::Ident("c".into()), Span::from(5..6)),
+                (Token::Newline, Span::from(6..6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_in_string() {
+        let mut lexer = Lexer::new();
+        let mut tokens = lexer.lex("'ab\r\n");
+        assert_eq!(tokens.next(), Some(Err(LexError::UnclosedString)));
+
+        let mut tokens = lexer.lex(r"'a\'b'");
+        assert_eq!(tokens.next(), Some(Ok(Token::String("a'b".into()))));
+
+        // an escaped line ending still leaves the string unclosed
+        let mut tokens = lexer.lex("'ab\\\n");
+        assert_eq!(tokens.next(), Some(Err(LexError::UnclosedString)));
+    }
+
+    #[test]
+    fn string_escapes() {
+        let string = |source: &str| Lexer::new().lex(source).next();
+        assert_eq!(
+            string(r#"'\n\r\t\0\\\'\"'"#),
+            Some(Ok(Token::String("\n\r\t\0\\'\"".into())))
+        );
+        assert_eq!(
+            string(r"'\u{41}\u{1F600}'"),
+            Some(Ok(Token::String("A\u{1F600}".into())))
+        );
+
+        // invalid escapes error once the string is closed
+        for source in [
+            r"'\q'",
+            r"'\u41'",
+            r"'\u{}'",
+            r"'\u{110000}'",
+            r"'\u{1234567}'",
+        ] {
+            let mut lexer = Lexer::new();
+            let mut tokens = lexer.lex(source);
+            assert_eq!(
+                tokens.next(),
+                Some(Err(LexError::InvalidEscape)),
+                "{source}"
+            );
+            assert_eq!(tokens.next(), Some(Ok(Token::Newline)), "{source}");
+        }
+
+        // repr and token display both lex back into the same string
+        let text = "'\"\\\n\u{7}é";
+        let repr = Value::<()>::String(text.into()).repr();
+        assert_eq!(string(&repr), Some(Ok(Token::String(text.into()))));
+        let token = Token::String(text.into());
+        assert_eq!(string(&token.to_string()), Some(Ok(token)));
+    }
+
+    #[test]
+    fn tokens_display_as_source() {
+        let tokens = [
+            Token::Ident("abc_1".into()),
+            Token::None,
+            Token::Bool(true),
+            Token::Bool(false),
+            Token::Int(42.into()),
+            Token::Float(1.),
+            Token::Float(0.25),
+            Token::Float(1e20),
+            Token::String("a b".into()),
+            Token::Add,
+            Token::Sub,
+            Token::Not,
+            Token::Mul,
+            Token::Div,
+            Token::Modulo,
+            Token::Pow,
+            Token::Eq,
+            Token::Lt,
+            Token::Gt,
+            Token::NEq,
+            Token::LtEq,
+            Token::GtEq,
+            Token::And,
+            Token::Or,
+            Token::Walrus,
+            Token::Period,
+            Token::Comma,
+            Token::Assign,
+            Token::Colon,
+            Token::DoubleColon,
+            Token::SemiColon,
+            Token::Question,
+            Token::QuestionPeriod,
+            Token::At,
+            Token::OpenParen,
+            Token::CloseParen,
+            Token::OpenCurly,
+            Token::CloseCurly,
+            Token::OpenSquare,
+            Token::CloseSquare,
+            Token::Arrow,
+            Token::FatArrow,
+            Token::Let,
+            Token::Fn,
+            Token::If,
+            Token::Then,
+            Token::Else,
+            Token::While,
+            Token::Repeat,
+            Token::Until,
+            Token::Try,
+            Token::Catch,
+            Token::Static,
+            Token::Const,
+            Token::Pub,
+            Token::Priv,
+            Token::Use,
+            Token::As,
+        ];
+
+        let mut lexer = Lexer::new();
+        for token in tokens {
+            let source = token.to_string();
+            let lexed = lexer.lex(&source).next();
+            assert_eq!(lexed, Some(Ok(token)), "'{source}' did not round trip");
+        }
+
+        // comments only round trip when they are preserved
+        lexer.preserve_comments(true);
+        let comment = Token::Comment(" note".into());
+        assert_eq!(lexer.lex(&comment.to_string()).next(), Some(Ok(comment)));
+
+        // block tokens have no source text so they display by name
+        assert_eq!(Token::Newline.to_string(), "newline");
+        assert_eq!(Token::Indent.to_string(), "indent");
+        assert_eq!(Token::Dedent.to_string(), "dedent");
+        assert_eq!(Token::Whitespace(" \t".into()).to_string(), " \t");
+    }
+
+    #[test]
+    fn radix_integers() {
+        let mut lexer = Lexer::new();
+        let big = IBig::from_str_radix("ffffffffffffffffffffffffffffffff", 16).unwrap();
+        let tokens = lexer
+            .lex("0xff 0o17 0b101 0xffffffffffffffffffffffffffffffff 0 0.5")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Int(255.into())),
+                Ok(Token::Int(15.into())),
+                Ok(Token::Int(5.into())),
+                Ok(Token::Int(big)),
+                Ok(Token::Int(0.into())),
+                Ok(Token::Float(0.5)),
+                Ok(Token::Newline),
+            ]
+        );
+
+        let mut tokens = lexer.lex("0b102 0x");
+        assert_eq!(tokens.next(), Some(Err(LexError::InvalidInteger(2))));
+        assert_eq!(tokens.token_span(), Span::from(0..5));
+        assert_eq!(tokens.next(), Some(Err(LexError::InvalidInteger(16))));
+    }
+
+    #[test]
+    fn big_integers() {
+        let digits = "123456789012345678901234567890";
+        let mut lexer = Lexer::new();
+        let mut tokens = lexer.lex(digits);
+        assert_eq!(tokens.next(), Some(Ok(Token::Int(digits.parse().unwrap()))));
+        assert_eq!(tokens.token_span(), Span::from(0..30));
+    }
+
+    #[test]
+    fn random_input_never_panics() {
+        const SYMBOLS: &[&str] = &[
+            "0", "1", "9", ".", "f", "x", "b", "o", "_", "a", " ", "\t", "\n", "\r", "#", "'",
+            "\"", "\\", "*", "=", "-", ">", "<", "!", ":", "(", ")", "é", "🐱", "\u{301}",
+        ];
+
+        // a small xorshift generator keeps the test deterministic without extra dependencies
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let mut lexer = Lexer::new();
+        for _ in 0..10_000 {
+            let len = next() % 24;
+            let line = (0..len)
+                .map(|_| SYMBOLS[(next() % SYMBOLS.len() as u64) as usize])
+                .collect::<String>();
+
+            let mut tokens = lexer.lex(&line);
+            while tokens.next().is_some() {
+                let span = tokens.token_span();
+                assert!(line.get(span.range()).is_some(), "bad span for {line:?}");
+            }
+        }
+
+        lexer.lex_final("").for_each(drop);
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let mut lexer = Lexer::new();
+        assert!(!lexer.allows_unicode_idents());
+        let mut tokens = lexer.lex("café");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("caf".into()))));
+        assert_eq!(tokens.next(), Some(Err(LexError::InvalidSymbol)));
+
+        lexer.unicode_idents(true);
+        let mut tokens = lexer.lex("café + e\u{301}_1 + 名前 + 🐱");
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("café".into()))));
+        assert_eq!(tokens.token_span(), Span::from(0..5));
+        assert_eq!(tokens.next(), Some(Ok(Token::Add)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("e\u{301}_1".into()))));
+        assert_eq!(tokens.token_span(), Span::from(8..13));
+        assert_eq!(tokens.next(), Some(Ok(Token::Add)));
+        assert_eq!(tokens.next(), Some(Ok(Token::Ident("名前".into()))));
+        assert_eq!(tokens.token_span(), Span::from(16..22));
+        assert_eq!(tokens.next(), Some(Ok(Token::Add)));
+        assert_eq!(tokens.next(), Some(Err(LexError::InvalidSymbol)));
+    }
+}
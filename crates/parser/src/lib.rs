@@ -2,6 +2,7 @@ mod parser;
 
 pub mod error;
 pub mod parsers;
+pub mod precedence;
 pub mod stream;
 pub mod token;
 
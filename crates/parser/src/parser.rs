@@ -1,3 +1,7 @@
+use std::mem::take;
+
+use boba_script_core::ast::{CommentNode, Node};
+
 use crate::{error::PError, stream::StreamExt, token::Span, ParseError, Token, TokenStream};
 
 pub enum ConsumeFlag {
@@ -19,11 +23,11 @@ pub struct ErrorLine<'a, 'source, Stream: TokenStream> {
 
 impl<'a, 'source, Stream: TokenStream> ErrorLine<'a, 'source, Stream> {
     pub fn line(&self) -> &TokenLine<'source, Stream> {
-        &self.line
+        self.line
     }
 
     pub fn line_mut(&mut self) -> &mut TokenLine<'source, Stream> {
-        &mut self.line
+        self.line
     }
 
     pub fn push(&mut self, error: PError<Stream>) {
@@ -34,13 +38,14 @@ impl<'a, 'source, Stream: TokenStream> ErrorLine<'a, 'source, Stream> {
         self.line.consume_line(&mut self.errors);
     }
 
-    pub fn consume_until(&mut self, until: impl Fn(&Token) -> ConsumeFlag) -> ConsumeEnd {
+    pub fn consume_until(&mut self, until: impl Fn(&Token) -> ConsumeFlag) -> ConsumeEnd<'_> {
         self.line.consume_until(&mut self.errors, until)
     }
 }
 
 pub struct TokenLine<'a, Stream: TokenStream> {
     peeked: Option<Result<Token, PError<Stream>>>,
+    comments: Vec<CommentNode<Stream::Source>>,
     stream: &'a mut Stream,
     span: Span,
 }
@@ -49,6 +54,7 @@ impl<'a, Stream: TokenStream> TokenLine<'a, Stream> {
     pub fn new(stream: &'a mut Stream) -> Self {
         Self {
             peeked: None,
+            comments: Vec::new(),
             span: stream.token_start_span(),
             stream,
         }
@@ -90,13 +96,26 @@ impl<'a, Stream: TokenStream> TokenLine<'a, Stream> {
         self.build_source(self.token_end_span())
     }
 
+    /// Takes all comments that have been passed over on this line.
+    pub fn take_comments(&mut self) -> Vec<CommentNode<Stream::Source>> {
+        take(&mut self.comments)
+    }
+
     fn generate(&mut self) -> Option<Result<Token, PError<Stream>>> {
-        match self.stream.next()? {
-            Ok(token) => Some(Ok(token)),
-            Err(error) => Some(Err(ParseError::TokenError {
-                error,
-                source: self.stream.token_source(),
-            })),
+        loop {
+            return match self.stream.next()? {
+                // comments are stored instead of being parsed
+                Ok(Token::Comment(text)) => {
+                    let source = self.stream.token_source();
+                    self.comments.push(Node::new(text, source));
+                    continue;
+                }
+                Ok(token) => Some(Ok(token)),
+                Err(error) => Some(Err(ParseError::TokenError {
+                    error,
+                    source: self.stream.token_source(),
+                })),
+            };
         }
     }
 
@@ -107,12 +126,10 @@ impl<'a, Stream: TokenStream> TokenLine<'a, Stream> {
     pub fn take_token(&mut self) -> Option<Result<Token, PError<Stream>>> {
         // take peeked token, or generate a new one
         let result = match self.peeked.take() {
+            Some(result) => result,
             None => self.generate()?,
-            Some(result) => {
-                self.span = self.stream.token_span();
-                result
-            }
         };
+        self.span = self.stream.token_span();
 
         match result {
             // if the token is a newline,
@@ -173,7 +190,7 @@ impl<'a, Stream: TokenStream> TokenLine<'a, Stream> {
             false => Err(ParseError::UnexpectedInput {
                 expect: match &exact {
                     Some(token) => format!("{token}"),
-                    None => format!("end of line"),
+                    None => "end of line".into(),
                 },
                 found: token,
                 source: self.token_source(),
@@ -193,7 +210,7 @@ impl<'a, Stream: TokenStream> TokenLine<'a, Stream> {
         &mut self,
         store: &mut Vec<PError<Stream>>,
         until: impl Fn(&Token) -> ConsumeFlag,
-    ) -> ConsumeEnd {
+    ) -> ConsumeEnd<'_> {
         while let Some(result) = self.take_token() {
             match result {
                 Err(error) => store.push(error),
@@ -1,6 +1,6 @@
-use std::mem::replace;
+use std::mem::take;
 
-use boba_script_core::ast::StatementNode;
+use boba_script_core::ast::{CommentNode, Comments, StatementNode};
 
 use crate::{error::PError, stream::SourceSpan, ParseError, Token, TokenLine, TokenStream};
 
@@ -20,6 +20,7 @@ pub fn start_parsing<T: TokenStream>(
                 Ok(BlockParser {
                     pending: None,
                     body: Vec::new(),
+                    comments: Vec::new(),
                     complete: false,
                 })
             }
@@ -38,6 +39,7 @@ pub fn start_parsing<T: TokenStream>(
 pub struct BlockParser<Source: SourceSpan> {
     pending: Option<Box<StatementParser<Source>>>,
     body: Vec<StatementNode<Source>>,
+    comments: Vec<CommentNode<Source>>,
     complete: bool,
 }
 
@@ -55,6 +57,15 @@ impl<Source: SourceSpan> BlockParser<Source> {
             return Ok(None);
         }
 
+        // hold comment lines until the statement they lead is parsed
+        if self.pending.is_none() && line.peek_token().is_none() {
+            let comments = line.take_comments();
+            if !comments.is_empty() {
+                self.comments.extend(comments);
+                return Ok(None);
+            }
+        }
+
         // if there is nothing pending and no body, then it is the start
         if self.pending.is_none() && self.body.is_empty() {
             match line.peek_token() {
@@ -77,12 +88,23 @@ impl<Source: SourceSpan> BlockParser<Source> {
             None => match line.peek_token() {
                 // if we find a dedent, then end parsing and return the data
                 Some(Ok(Token::Dedent)) => {
-                    let body = replace(&mut self.body, Vec::new());
+                    let mut body = take(&mut self.body);
+
+                    // comments after the last statement trail it
+                    let trailing = take(&mut self.comments);
+                    if let Some(last) = body.pop() {
+                        let comments = Comments {
+                            leading: Vec::new(),
+                            trailing,
+                        };
+                        body.push(statement::attach_comments(last, comments));
+                    }
+
                     return Ok(Some(body));
                 }
 
                 // if we find anything else, parse the line as a statement
-                _ => match statement::start_parsing(line)? {
+                _ => match statement::start_parsing(line)?.lead_with(take(&mut self.comments)) {
                     StatementType::MultiLine(parser) => self.pending = Some(Box::new(parser)),
                     StatementType::SingleLine(statement) => self.body.push(statement),
                 },
@@ -93,3 +115,174 @@ impl<Source: SourceSpan> BlockParser<Source> {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use boba_script_core::ast::{Expr, Statement};
+
+    use crate::{
+        parsers::test_util::{TestSource, TestStream},
+        token::Span,
+    };
+
+    use super::*;
+
+    fn comment(text: &str, index: usize) -> CommentNode<TestSource> {
+        CommentNode::new(text.into(), TestSource(Span::from(index..index + 1)))
+    }
+
+    #[test]
+    fn preserved_comments() {
+        let ident = |s: &str| Token::Ident(s.into());
+        let note = |s: &str| Token::Comment(s.into());
+        let mut stream = TestStream::new(vec![
+            // fn f(): # header
+            Token::Fn,
+            ident("f"),
+            Token::OpenParen,
+            Token::CloseParen,
+            Token::Colon,
+            note("header"),
+            Token::Newline,
+            // # lead
+            note("lead"),
+            Token::Newline,
+            //     a # trail
+            Token::Indent,
+            ident("a"),
+            note("trail"),
+            Token::Newline,
+            // # end
+            note("end"),
+            Token::Newline,
+            Token::Dedent,
+        ]);
+
+        let mut line = TokenLine::new(&mut stream);
+        let Ok(StatementType::MultiLine(mut parser)) = statement::start_parsing(&mut line) else {
+            panic!("expected a function parser");
+        };
+
+        let statement = loop {
+            let mut line = TokenLine::new(&mut stream);
+            if let Some(statement) = parser.parse_line(&mut line).expect("valid line") {
+                break statement;
+            }
+        };
+
+        let Statement::Commented {
+            comments,
+            statement,
+        } = statement.item
+        else {
+            panic!("expected a commented function");
+        };
+        assert_eq!(comments.leading, vec![]);
+        assert_eq!(comments.trailing, vec![comment("header", 5)]);
+
+        let Statement::Assign { rhs, .. } = statement.item else {
+            panic!("expected a function assignment");
+        };
+        let Expr::Func(func) = rhs.item else {
+            panic!("expected a function");
+        };
+        let Statement::Commented { comments, .. } = &func.body[0].item else {
+            panic!("expected a commented body statement");
+        };
+        assert_eq!(comments.leading, vec![comment("lead", 7)]);
+        assert_eq!(
+            comments.trailing,
+            vec![comment("trail", 11), comment("end", 13)]
+        );
+    }
+
+    #[test]
+    fn repeat_until() {
+        let ident = |s: &str| Token::Ident(s.into());
+        let mut stream = TestStream::new(vec![
+            // repeat:
+            Token::Repeat,
+            Token::Colon,
+            Token::Newline,
+            //     a
+            Token::Indent,
+            ident("a"),
+            Token::Newline,
+            // until b
+            Token::Dedent,
+            Token::Until,
+            ident("b"),
+            Token::Newline,
+        ]);
+
+        let mut line = TokenLine::new(&mut stream);
+        let Ok(StatementType::MultiLine(mut parser)) = statement::start_parsing(&mut line) else {
+            panic!("expected a repeat parser");
+        };
+
+        let statement = loop {
+            let mut line = TokenLine::new(&mut stream);
+            if let Some(statement) = parser.parse_line(&mut line).expect("valid line") {
+                break statement;
+            }
+        };
+
+        let Statement::Repeat { body, cond } = statement.item else {
+            panic!("expected a repeat loop");
+        };
+        assert_eq!(body.len(), 1);
+        assert_eq!(cond.item, Expr::Var("b".into()));
+        assert_eq!(statement.source, TestSource(Span::from(0..9)));
+    }
+
+    #[test]
+    fn use_module() {
+        let mut stream = TestStream::new(vec![
+            // use "util.boba"
+            Token::Use,
+            Token::String("util.boba".into()),
+            Token::Newline,
+            // use "util.boba"::f as g
+            Token::Use,
+            Token::String("util.boba".into()),
+            Token::DoubleColon,
+            Token::Ident("f".into()),
+            Token::As,
+            Token::Ident("g".into()),
+            Token::Newline,
+            // use util
+            Token::Use,
+            Token::Ident("util".into()),
+            Token::Newline,
+        ]);
+
+        let mut line = TokenLine::new(&mut stream);
+        let Ok(StatementType::SingleLine(statement)) = statement::start_parsing(&mut line) else {
+            panic!("expected a use statement");
+        };
+        let Statement::Use { path, import: None } = statement.item else {
+            panic!("expected a use statement");
+        };
+        assert_eq!(path.item, "util.boba");
+        assert_eq!(statement.source, TestSource(Span::from(0..2)));
+
+        let mut line = TokenLine::new(&mut stream);
+        let Ok(StatementType::SingleLine(statement)) = statement::start_parsing(&mut line) else {
+            panic!("expected a use statement");
+        };
+        let Statement::Use {
+            import: Some(import),
+            ..
+        } = statement.item
+        else {
+            panic!("expected a selective import");
+        };
+        assert_eq!(import.name.item, "f".into());
+        assert_eq!(import.binding().item, "g".into());
+        assert_eq!(statement.source, TestSource(Span::from(3..9)));
+
+        // modules are only named by strings
+        let mut line = TokenLine::new(&mut stream);
+        assert!(statement::start_parsing(&mut line).is_err());
+    }
+}
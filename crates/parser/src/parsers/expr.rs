@@ -1,8 +1,10 @@
 use boba_script_core::ast::{node::Builder, Expr, ExprNode};
 
 use crate::{
-    error::PError, stream::SourceSpan, ConsumeEnd, ConsumeFlag, ParseError, Token, TokenLine,
-    TokenStream,
+    error::PError,
+    precedence::{precedence, Assoc},
+    stream::SourceSpan,
+    ConsumeEnd, ConsumeFlag, ParseError, Token, TokenLine, TokenStream,
 };
 
 pub fn parse<T: TokenStream>(
@@ -14,6 +16,55 @@ pub fn parse<T: TokenStream>(
 
 pub fn parse_atom<T: TokenStream>(
     line: &mut TokenLine<T>,
+) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
+    let atom = parse_primary(line)?;
+    parse_fields(atom, line)
+}
+
+pub fn parse_fields<T: TokenStream>(
+    mut target: ExprNode<T::Source>,
+    line: &mut TokenLine<T>,
+) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
+    // keep parsing fields until no period is found
+    while let Some(Ok(Token::Period | Token::QuestionPeriod)) = line.peek_token() {
+        // consume period and check if the access is optional
+        let optional = matches!(line.take_token(), Some(Ok(Token::QuestionPeriod)));
+
+        // parse the field name or index
+        let field = match line.take_some("field").map_err(|e| vec![e])? {
+            Token::Ident(name) => name,
+            Token::Int(index) => index.to_string(),
+            token => {
+                return Err(vec![ParseError::UnexpectedInput {
+                    expect: "field".into(),
+                    found: Some(token),
+                    source: line.token_source(),
+                }])
+            }
+        };
+
+        // build the field access
+        let field = field.build_node(line.token_source());
+        let source = line.build_source(target.source.start()..field.source.end());
+        let target_box = Box::new(target);
+        target = match optional {
+            false => Expr::Field {
+                target: target_box,
+                field,
+            },
+            true => Expr::OptField {
+                target: target_box,
+                field,
+            },
+        }
+        .build_node(source);
+    }
+
+    Ok(target)
+}
+
+pub fn parse_primary<T: TokenStream>(
+    line: &mut TokenLine<T>,
 ) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
     line.take_guard(|token, line| match token {
         // VALUES
@@ -31,7 +82,16 @@ pub fn parse_atom<T: TokenStream>(
                     line.consume_token();
                     line.token_start()
                 }
-                _ => return Ok(Expr::Var(ident).build_node(line.token_source())),
+                // bind the whole value while also destructuring it
+                Some(Ok(Token::At)) => {
+                    line.consume_token();
+                    let pattern = parse_atom(line)?;
+                    let source = line.build_source(source.start()..pattern.source.end());
+                    let pattern = Box::new(pattern);
+                    let name = ident.into();
+                    return Ok(Expr::At { name, pattern }.build_node(source));
+                }
+                _ => return Ok(Expr::Var(ident.into()).build_node(line.token_source())),
             };
 
             let mut params = Vec::new();
@@ -43,7 +103,7 @@ pub fn parse_atom<T: TokenStream>(
                             line.consume_token();
                             break Ok(());
                         }
-                        _ => params.push(parse(line)?),
+                        _ => params.push(parse_item(line)?),
                     }
 
                     // parse comma or closing paren
@@ -77,17 +137,38 @@ pub fn parse_atom<T: TokenStream>(
             )?;
 
             Ok(Expr::Call {
-                name: ident,
+                name: ident.into(),
                 params,
             }
             .build_node(source))
         }
 
+        // IF EXPRESSIONS
+        Some(Token::If) => {
+            let start = line.token_start();
+            let cond = parse(line)?;
+            parse_if_tail(start, cond, line)
+        }
+
+        // TRY EXPRESSIONS
+        Some(Token::Try) => {
+            let start = line.token_start();
+            let body = parse(line)?;
+            parse_catch_tail(start, body, line)
+        }
+
         // PARENTHESIS AND TUPLES
         Some(Token::OpenParen) => {
             // save the open paren span
             let start = line.token_start();
 
+            // empty parens are the unit value, an empty tuple
+            if let Some(Ok(Token::CloseParen)) = line.peek_token() {
+                line.consume_token();
+                let source = line.build_source(start..line.token_end());
+                return Ok(Expr::Tuple(Vec::new()).build_node(source));
+            }
+
             // parse all tuple parts if any
             let mut exprs = Vec::new();
             let expr = loop {
@@ -95,7 +176,7 @@ pub fn parse_atom<T: TokenStream>(
                 let result = line.guard_else(
                     |line| {
                         // parse expression
-                        let inner = parse(line)?;
+                        let inner = parse_item(line)?;
 
                         // then check for a comma or closing paren
                         let end = line.take_guard(|token, line| match token {
@@ -135,7 +216,16 @@ pub fn parse_atom<T: TokenStream>(
                     // immediately return any errors
                     Err(errors) => return Err(errors),
                     // or store tuple parameter
-                    Ok((expr, false)) => exprs.push(expr),
+                    Ok((expr, false)) => {
+                        exprs.push(expr);
+
+                        // a trailing comma closes the tuple, so `(x,)` has one item
+                        if let Some(Ok(Token::CloseParen)) = line.peek_token() {
+                            line.consume_token();
+                            let source = line.build_source(start..line.token_end());
+                            return Ok(Expr::Tuple(exprs).build_node(source));
+                        }
+                    }
                     // or break with the expression
                     Ok((expr, true)) => break expr,
                 }
@@ -163,189 +253,371 @@ pub fn parse_atom<T: TokenStream>(
     })
 }
 
-pub fn parse_with_lhs<T: TokenStream>(
-    mut lhs: ExprNode<T::Source>,
-    line: &mut TokenLine<T>,
-) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    // keep parsing operators until an invalid operator is found
-    loop {
-        lhs = match line.peek_token() {
-            Some(Ok(token)) => match token {
-                Token::Pow => parse_pow(lhs, line)?,
-                Token::Mul | Token::Div | Token::Modulo => parse_mul(lhs, line)?,
-                Token::Add | Token::Sub => parse_add(lhs, line)?,
-                Token::Eq | Token::Lt | Token::Gt | Token::NEq | Token::LtEq | Token::GtEq => {
-                    parse_relation(lhs, line)?
-                }
-                Token::And => parse_and(lhs, line)?,
-                Token::Or => parse_or(lhs, line)?,
-                Token::Question => parse_ternary(lhs, line)?,
-                Token::Walrus => parse_walrus(lhs, line)?,
-                _ => return Ok(lhs),
-            },
-            _ => return Ok(lhs),
-        }
-    }
-}
-
-pub fn parse_pow<T: TokenStream>(
-    lhs: ExprNode<T::Source>,
+/// Parses an item of a tuple or call, which may be splatted with a leading `*`.
+pub fn parse_item<T: TokenStream>(
     line: &mut TokenLine<T>,
 ) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    let op = match line.peek_token() {
-        Some(Ok(Token::Pow)) => Expr::Pow,
-        _ => return Ok(lhs),
+    let Some(Ok(Token::Mul)) = line.peek_token() else {
+        return parse(line);
     };
 
-    line.consume_token(); // consume op
-    let rhs = parse_atom(line)?;
-    let rhs = parse_pow(rhs, line)?; // parse right to left
-    let source = line.build_source(lhs.source.start()..rhs.source.end());
-    Ok(op(Box::new(lhs), Box::new(rhs)).build_node(source))
-}
-
-pub fn parse_mul<T: TokenStream>(
-    lhs: ExprNode<T::Source>,
-    line: &mut TokenLine<T>,
-) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    let op = match line.peek_token() {
-        Some(Ok(Token::Mul)) => Expr::Mul,
-        Some(Ok(Token::Div)) => Expr::Div,
-        Some(Ok(Token::Modulo)) => Expr::Modulo,
-        Some(Err(_)) => return Ok(lhs),
-        // try the next precedence level
-        _ => return parse_pow(lhs, line),
-    };
-
-    line.consume_token(); // consume op
-    let rhs = parse_atom(line)?;
-    let rhs = parse_pow(rhs, line)?; // parse higher precedence on rhs
-    let source = line.build_source(lhs.source.start()..rhs.source.end());
-    Ok(op(Box::new(lhs), Box::new(rhs)).build_node(source))
+    line.consume_token();
+    let start = line.token_start();
+    let inner = parse(line)?;
+    let source = line.build_source(start..line.token_end());
+    Ok(Expr::Splat(Box::new(inner)).build_node(source))
 }
 
-pub fn parse_add<T: TokenStream>(
+pub fn parse_with_lhs<T: TokenStream>(
     lhs: ExprNode<T::Source>,
     line: &mut TokenLine<T>,
 ) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    let op = match line.peek_token() {
-        Some(Ok(Token::Add)) => Expr::Add,
-        Some(Ok(Token::Sub)) => Expr::Sub,
-        Some(Err(_)) => return Ok(lhs),
-        // try the next precedence level
-        _ => return parse_mul(lhs, line),
-    };
-
-    line.consume_token(); // consume op
-    let rhs = parse_atom(line)?;
-    let rhs = parse_mul(rhs, line)?; // parse higher precedence on rhs
-    let source = line.build_source(lhs.source.start()..rhs.source.end());
-    Ok(op(Box::new(lhs), Box::new(rhs)).build_node(source))
+    parse_binary(lhs, 0, line)
 }
 
-pub fn parse_relation<T: TokenStream>(
-    lhs: ExprNode<T::Source>,
+/// Parses the `then a else b` tail of an if expression after its condition.
+///
+/// The else branch binds like the fail branch of a ternary,
+/// so nested if expressions in the else branch are right associative.
+pub fn parse_if_tail<T: TokenStream>(
+    start: usize,
+    cond: ExprNode<T::Source>,
     line: &mut TokenLine<T>,
 ) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    let op = match line.peek_token() {
-        Some(Ok(Token::Eq)) => Expr::Eq,
-        Some(Ok(Token::Lt)) => Expr::Lt,
-        Some(Ok(Token::Gt)) => Expr::Gt,
-        Some(Ok(Token::NEq)) => Expr::NEq,
-        Some(Ok(Token::LtEq)) => Expr::LtEq,
-        Some(Ok(Token::GtEq)) => Expr::GtEq,
-        Some(Err(_)) => return Ok(lhs),
-        // try the next precedence level
-        _ => return parse_add(lhs, line),
-    };
-
-    line.consume_token(); // consume op
-    let rhs = parse_atom(line)?;
-    let rhs = parse_add(rhs, line)?; // parse higher precedence on rhs
-    let source = line.build_source(lhs.source.start()..rhs.source.end());
-    Ok(op(Box::new(lhs), Box::new(rhs)).build_node(source))
+    line.take_exact(Some(&Token::Then)).map_err(|e| vec![e])?;
+    let pass = parse(line)?;
+    line.take_exact(Some(&Token::Else)).map_err(|e| vec![e])?;
+    let fail = parse_atom(line)?;
+    let min = precedence(&Token::Question).map_or(0, |(level, _)| level - 1);
+    let fail = parse_binary(fail, min, line)?;
+    let source = line.build_source(start..fail.source.end());
+    Ok(Expr::Ternary {
+        cond: Box::new(cond),
+        pass: Box::new(pass),
+        fail: Box::new(fail),
+    }
+    .build_node(source))
 }
 
-pub fn parse_and<T: TokenStream>(
-    lhs: ExprNode<T::Source>,
+/// Parses the `catch e: handler` tail of a try expression after its body.
+///
+/// The handler binds like the else branch of an if expression.
+fn parse_catch_tail<T: TokenStream>(
+    start: usize,
+    body: ExprNode<T::Source>,
     line: &mut TokenLine<T>,
 ) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    let op = match line.peek_token() {
-        Some(Ok(Token::And)) => Expr::And,
-        // try the next precedence level
-        _ => return parse_relation(lhs, line),
+    line.take_exact(Some(&Token::Catch)).map_err(|e| vec![e])?;
+    let name = match line.take_some("error name").map_err(|e| vec![e])? {
+        Token::Ident(name) => name,
+        token => {
+            return Err(vec![ParseError::UnexpectedInput {
+                expect: "error name".into(),
+                found: Some(token),
+                source: line.token_source(),
+            }])
+        }
     };
-
-    line.consume_token(); // consume op
-    let rhs = parse_atom(line)?;
-    let rhs = parse_relation(rhs, line)?; // parse higher precedence on rhs
-    let source = line.build_source(lhs.source.start()..rhs.source.end());
-    Ok(op(Box::new(lhs), Box::new(rhs)).build_node(source))
+    line.take_exact(Some(&Token::Colon)).map_err(|e| vec![e])?;
+    let handler = parse_atom(line)?;
+    let min = precedence(&Token::Question).map_or(0, |(level, _)| level - 1);
+    let handler = parse_binary(handler, min, line)?;
+    let source = line.build_source(start..handler.source.end());
+    Ok(Expr::Try {
+        body: Box::new(body),
+        name: name.into(),
+        handler: Box::new(handler),
+    }
+    .build_node(source))
 }
 
-pub fn parse_or<T: TokenStream>(
-    lhs: ExprNode<T::Source>,
+/// Parses binary operators using precedence climbing.
+///
+/// Only operators with a precedence above `min` are consumed,
+/// so that lower precedence operators are left for the caller.
+fn parse_binary<T: TokenStream>(
+    mut lhs: ExprNode<T::Source>,
+    min: u8,
     line: &mut TokenLine<T>,
 ) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    let op = match line.peek_token() {
-        Some(Ok(Token::Or)) => Expr::Or,
-        // try the next precedence level
-        _ => return parse_and(lhs, line),
-    };
+    loop {
+        // find the next operator and its precedence
+        let (op, level, assoc) = match line.peek_token() {
+            Some(Ok(token)) => match precedence(token) {
+                Some((level, assoc)) if level > min => (token.clone(), level, assoc),
+                _ => return Ok(lhs),
+            },
+            _ => return Ok(lhs),
+        };
+
+        line.consume_token(); // consume op
+
+        // left associative operators only take higher precedence on the rhs
+        let rhs_min = match assoc {
+            Assoc::Left => level,
+            Assoc::Right => level - 1,
+        };
+
+        // ternaries parse a full inner expression before the rhs
+        if op == Token::Question {
+            let pass = parse(line)?;
+            line.take_exact(Some(&Token::Colon)).map_err(|e| vec![e])?;
+            let fail = parse_atom(line)?;
+            let fail = parse_binary(fail, rhs_min, line)?;
+            let source = line.build_source(lhs.source.start()..fail.source.end());
+            lhs = Expr::Ternary {
+                cond: Box::new(lhs),
+                pass: Box::new(pass),
+                fail: Box::new(fail),
+            }
+            .build_node(source);
+            continue;
+        }
 
-    line.consume_token(); // consume op
-    let rhs = parse_atom(line)?;
-    let rhs = parse_and(rhs, line)?; // parse higher precedence on rhs
-    let source = line.build_source(lhs.source.start()..rhs.source.end());
-    Ok(op(Box::new(lhs), Box::new(rhs)).build_node(source))
+        let rhs = parse_atom(line)?;
+        let rhs = parse_binary(rhs, rhs_min, line)?;
+        let source = line.build_source(lhs.source.start()..rhs.source.end());
+        let (lhs_box, rhs_box) = (Box::new(lhs), Box::new(rhs));
+        lhs = match op {
+            Token::Walrus => Expr::Walrus(lhs_box, rhs_box),
+            Token::Or => Expr::Or(lhs_box, rhs_box),
+            Token::And => Expr::And(lhs_box, rhs_box),
+            Token::Eq => Expr::Eq(lhs_box, rhs_box),
+            Token::Lt => Expr::Lt(lhs_box, rhs_box),
+            Token::Gt => Expr::Gt(lhs_box, rhs_box),
+            Token::NEq => Expr::NEq(lhs_box, rhs_box),
+            Token::LtEq => Expr::LtEq(lhs_box, rhs_box),
+            Token::GtEq => Expr::GtEq(lhs_box, rhs_box),
+            Token::Add => Expr::Add(lhs_box, rhs_box),
+            Token::Sub => Expr::Sub(lhs_box, rhs_box),
+            Token::Mul => Expr::Mul(lhs_box, rhs_box),
+            Token::Div => Expr::Div(lhs_box, rhs_box),
+            Token::Modulo => Expr::Modulo(lhs_box, rhs_box),
+            Token::Pow => Expr::Pow(lhs_box, rhs_box),
+            token => unreachable!("'{token}' has a precedence but is not a binary operator"),
+        }
+        .build_node(source);
+    }
 }
 
-pub fn parse_ternary<T: TokenStream>(
-    cond: ExprNode<T::Source>,
-    line: &mut TokenLine<T>,
-) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    // parse the question mark
-    match line.peek_token() {
-        Some(Ok(Token::Question)) => (),
-        // try the next precedence level
-        _ => return parse_or(cond, line),
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parsers::test_util::{TestSource, TestStream},
+        token::Span,
     };
 
-    // consume the question mark
-    line.consume_token();
+    use super::*;
+
+    fn parse_str(source: &str) -> ExprNode<TestSource> {
+        let tokens = source.split(' ').map(|s| match s {
+            "+" => Token::Add,
+            "-" => Token::Sub,
+            "*" => Token::Mul,
+            "/" => Token::Div,
+            "%" => Token::Modulo,
+            "**" => Token::Pow,
+            "==" => Token::Eq,
+            "<" => Token::Lt,
+            ">" => Token::Gt,
+            "!=" => Token::NEq,
+            "<=" => Token::LtEq,
+            ">=" => Token::GtEq,
+            ":=" => Token::Walrus,
+            "?" => Token::Question,
+            ":" => Token::Colon,
+            "(" => Token::OpenParen,
+            ")" => Token::CloseParen,
+            "," => Token::Comma,
+            "@" => Token::At,
+            "." => Token::Period,
+            "?." => Token::QuestionPeriod,
+            s => match s.parse() {
+                Ok(int) => Token::Int(int),
+                Err(_) => Token::parse_ident(s),
+            },
+        });
+
+        let mut stream = TestStream::new(tokens.collect());
+        let mut line = TokenLine::new(&mut stream);
+        let expr = parse(&mut line).expect("valid expression");
+        assert!(
+            line.peek_token().is_none(),
+            "expression was not fully parsed"
+        );
+        expr
+    }
 
-    // parse the pass expression
-    let pass = parse(line)?;
+    /// Renders an expression with every operation parenthesized
+    fn render(expr: &ExprNode<TestSource>) -> String {
+        let binary = |op: &str, lhs, rhs| format!("({} {op} {})", render(lhs), render(rhs));
+        let unary = |op: &str, inner| format!("({op}{})", render(inner));
+        let list = |items: &[ExprNode<TestSource>]| {
+            let items = items.iter().map(render).collect::<Vec<_>>();
+            items.join(", ")
+        };
+        match &expr.item {
+            Expr::None => "none".into(),
+            Expr::Bool(v) => v.to_string(),
+            Expr::Int(v) => v.to_string(),
+            Expr::Float(v) => format!("{v:?}"),
+            Expr::String(v) => format!("{v:?}"),
+            Expr::Var(v) => v.to_string(),
+            Expr::Func(func) => func.item.to_string(),
+            Expr::Call { name, params } => format!("{name}({})", list(params)),
+            Expr::Tuple(items) => {
+                let items = items.iter().map(render).collect::<Vec<_>>();
+                match items.len() {
+                    1 => format!("({},)", items[0]),
+                    _ => format!("({})", items.join(", ")),
+                }
+            }
+            Expr::Ternary { cond, pass, fail } => {
+                format!("({} ? {} : {})", render(cond), render(pass), render(fail))
+            }
+            Expr::Splat(inner) => format!("*{}", render(inner)),
+            Expr::Pos(inner) => unary("+", inner),
+            Expr::Neg(inner) => unary("-", inner),
+            Expr::Not(inner) => unary("not ", inner),
+            Expr::At { name, pattern } => format!("{name} @ {}", render(pattern)),
+            Expr::Try {
+                body,
+                name,
+                handler,
+            } => format!("(try {} catch {name}: {})", render(body), render(handler)),
+            Expr::Field { target, field } => format!("({}.{})", render(target), field.item),
+            Expr::OptField { target, field } => format!("({}?.{})", render(target), field.item),
+            Expr::Walrus(lhs, rhs) => binary(":=", lhs, rhs),
+            Expr::Or(lhs, rhs) => binary("or", lhs, rhs),
+            Expr::And(lhs, rhs) => binary("and", lhs, rhs),
+            Expr::Eq(lhs, rhs) => binary("==", lhs, rhs),
+            Expr::Lt(lhs, rhs) => binary("<", lhs, rhs),
+            Expr::Gt(lhs, rhs) => binary(">", lhs, rhs),
+            Expr::NEq(lhs, rhs) => binary("!=", lhs, rhs),
+            Expr::LtEq(lhs, rhs) => binary("<=", lhs, rhs),
+            Expr::GtEq(lhs, rhs) => binary(">=", lhs, rhs),
+            Expr::Add(lhs, rhs) => binary("+", lhs, rhs),
+            Expr::Sub(lhs, rhs) => binary("-", lhs, rhs),
+            Expr::Mul(lhs, rhs) => binary("*", lhs, rhs),
+            Expr::Div(lhs, rhs) => binary("/", lhs, rhs),
+            Expr::Modulo(lhs, rhs) => binary("%", lhs, rhs),
+            Expr::Pow(lhs, rhs) => binary("**", lhs, rhs),
+        }
+    }
 
-    // parse the colon delimiter
-    line.take_exact(Some(&Token::Colon)).map_err(|e| vec![e])?;
+    #[test]
+    fn operator_precedence() {
+        let corpus = [
+            ("1 + 2 + 3", "((1 + 2) + 3)"),
+            ("1 - 2 * 3", "(1 - (2 * 3))"),
+            ("1 * 2 - 3", "((1 * 2) - 3)"),
+            ("1 / 2 % 3 * 4", "(((1 / 2) % 3) * 4)"),
+            ("2 ** 3 ** 4", "(2 ** (3 ** 4))"),
+            ("2 ** 3 ** 4 ** 5", "(2 ** (3 ** (4 ** 5)))"),
+            ("( 2 ** 3 ) ** 4", "((2 ** 3) ** 4)"),
+            ("2 * 3 ** 4", "(2 * (3 ** 4))"),
+            ("1 + 2 * 3 ** 4 * 5", "(1 + ((2 * (3 ** 4)) * 5))"),
+            ("1 + 2 < 3 * 4", "((1 + 2) < (3 * 4))"),
+            ("a == b != c", "((a == b) != c)"),
+            ("a or b and c", "(a or (b and c))"),
+            ("a and b or c and d", "((a and b) or (c and d))"),
+            ("a < b and b < c", "((a < b) and (b < c))"),
+            ("a ? 1 : 2", "(a ? 1 : 2)"),
+            ("a or b ? 1 + 2 : 3 * 4", "((a or b) ? (1 + 2) : (3 * 4))"),
+            ("a ? b ? 1 : 2 : 3", "(a ? (b ? 1 : 2) : 3)"),
+            ("a ? 1 : b ? 2 : 3", "(a ? 1 : (b ? 2 : 3))"),
+            ("x := 1 + 2 * 3 - 4", "(x := ((1 + (2 * 3)) - 4))"),
+            ("x := y := 1", "(x := (y := 1))"),
+            ("x := a ? 1 : 2", "(x := (a ? 1 : 2))"),
+            ("if a then 1 else 2 + 3", "(a ? 1 : (2 + 3))"),
+            (
+                "if a then 1 else if b then 2 else 3",
+                "(a ? 1 : (b ? 2 : 3))",
+            ),
+            (
+                "if if a then b else c then 1 else 2",
+                "((a ? b : c) ? 1 : 2)",
+            ),
+            ("1 + if a then 2 else 3", "(1 + (a ? 2 : 3))"),
+            ("x := if a or b then 1 else 2", "(x := ((a or b) ? 1 : 2))"),
+            ("( 1 + 2 ) * 3", "((1 + 2) * 3)"),
+            ("( 1 , 2 + 3 ) * 4", "((1, (2 + 3)) * 4)"),
+            ("( )", "()"),
+            ("( ( ) )", "()"),
+            ("( ( ) , ( ) )", "((), ())"),
+            ("( a )", "a"),
+            ("( a , )", "(a,)"),
+            ("( ( a , ) )", "(a,)"),
+            ("( 1 , 2 , )", "(1, 2)"),
+            ("( a + 1 , ) * 2", "(((a + 1),) * 2)"),
+            ("( * a , b )", "(*a, b)"),
+            ("( a , * b + c , )", "(a, *(b + c))"),
+            ("( * a , )", "(*a,)"),
+            ("a @ ( b , c )", "a @ (b, c)"),
+            (
+                "try a + 1 catch e : e + 2",
+                "(try (a + 1) catch e: (e + 2))",
+            ),
+            ("1 + try a catch e : b", "(1 + (try a catch e: b))"),
+            (
+                "try try a catch e : b catch f : c",
+                "(try (try a catch e: b) catch f: c)",
+            ),
+            ("( a @ b , c )", "(a @ b, c)"),
+            ("a . b . c", "((a.b).c)"),
+            ("t . 0 . 1", "((t.0).1)"),
+            ("a ?. b . c", "((a?.b).c)"),
+            ("a . b + c . 0", "((a.b) + (c.0))"),
+            ("( a , b ) . 1 ** 2", "(((a, b).1) ** 2)"),
+            ("a > b >= c <= d", "(((a > b) >= c) <= d)"),
+            ("f ( a , b + c ) * 2", "(f(a, (b + c)) * 2)"),
+        ];
+
+        for (source, expect) in corpus {
+            assert_eq!(render(&parse_str(source)), expect, "parsing '{source}'");
+        }
+    }
 
-    // parse the fail expression
-    let fail = parse(line)?;
+    #[test]
+    fn operator_spans() {
+        let expr = parse_str("1 + 2 * 3 - 4");
+        assert_eq!(expr.source.0, Span::from(0..7));
+        let Expr::Sub(lhs, rhs) = &expr.item else {
+            panic!("expected subtraction");
+        };
+        assert_eq!(lhs.source.0, Span::from(0..5));
+        assert_eq!(rhs.source.0, Span::from(6..7));
+    }
 
-    // build source and return the ternary
-    let source = line.build_source(cond.source.start()..fail.source.end());
-    Ok(Expr::Ternary {
-        cond: Box::new(cond),
-        pass: Box::new(pass),
-        fail: Box::new(fail),
+    #[test]
+    fn field_spans() {
+        // each field is its own node, so errors can point at it
+        let expr = parse_str("t . 0 . 1");
+        assert_eq!(expr.source.0, Span::from(0..5));
+        let Expr::Field { target, field } = &expr.item else {
+            panic!("expected field access");
+        };
+        assert_eq!(field.source.0, Span::from(4..5));
+        assert_eq!(target.source.0, Span::from(0..3));
+        let Expr::Field { field, .. } = &target.item else {
+            panic!("expected field access");
+        };
+        assert_eq!(field.source.0, Span::from(2..3));
     }
-    .build_node(source))
-}
 
-pub fn parse_walrus<T: TokenStream>(
-    lhs: ExprNode<T::Source>,
-    line: &mut TokenLine<T>,
-) -> Result<ExprNode<T::Source>, Vec<PError<T>>> {
-    let op = match line.peek_token() {
-        Some(Ok(Token::Walrus)) => Expr::Walrus,
-        // try the next precedence level
-        _ => return parse_ternary(lhs, line),
-    };
+    #[test]
+    fn taken_token_spans() {
+        let mut stream =
+            TestStream::new(vec![Token::Ident("a".into()), Token::Add, Token::Newline]);
+        let mut line = TokenLine::new(&mut stream);
+
+        // taking a token that was never peeked still moves the span to it
+        assert!(matches!(line.take_token(), Some(Ok(Token::Ident(_)))));
+        assert_eq!(line.token_span(), Span::from(0..1));
 
-    line.consume_token(); // consume op
-    let rhs = parse_atom(line)?;
-    let rhs = parse_ternary(rhs, line)?; // parse higher precedence on rhs
-    let source = line.build_source(lhs.source.start()..rhs.source.end());
-    Ok(op(Box::new(lhs), Box::new(rhs)).build_node(source))
+        // as does taking a peeked token
+        assert!(matches!(line.peek_token(), Some(Ok(Token::Add))));
+        assert!(matches!(line.take_token(), Some(Ok(Token::Add))));
+        assert_eq!(line.token_span(), Span::from(1..2));
+    }
 }
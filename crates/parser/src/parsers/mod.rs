@@ -2,3 +2,7 @@ pub mod block;
 pub mod expr;
 pub mod line;
 pub mod statement;
+pub mod ty;
+
+#[cfg(test)]
+mod test_util;
@@ -1,5 +1,8 @@
+use std::mem::take;
+
 use boba_script_core::ast::{
-    func::Func, node::Builder, Expr, ExprNode, Node, Statement, StatementNode,
+    func::Func, node::Builder, CommentNode, Comments, Expr, ExprNode, Import, Node, Statement,
+    StatementNode, Symbol, Type, Visibility,
 };
 
 use crate::{
@@ -10,7 +13,7 @@ use crate::{
 
 use super::{
     block::{self, BlockParser},
-    expr, line,
+    expr, line, ty,
 };
 
 pub enum StatementType<Source: SourceSpan> {
@@ -18,22 +21,95 @@ pub enum StatementType<Source: SourceSpan> {
     MultiLine(StatementParser<Source>),
 }
 
+impl<Source: SourceSpan> StatementType<Source> {
+    /// Attaches comments from the lines directly before the statement.
+    pub fn lead_with(self, leading: Vec<CommentNode<Source>>) -> Self {
+        self.attach(Comments {
+            leading,
+            trailing: Vec::new(),
+        })
+    }
+
+    fn attach(self, comments: Comments<Source>) -> Self {
+        match self {
+            Self::SingleLine(statement) => Self::SingleLine(attach_comments(statement, comments)),
+            Self::MultiLine(mut parser) => {
+                let mut leading = comments.leading;
+                leading.append(&mut parser.comments.leading);
+                parser.comments.leading = leading;
+                parser.comments.trailing.extend(comments.trailing);
+                Self::MultiLine(parser)
+            }
+        }
+    }
+}
+
+/// Wraps a statement in its comments, merging them with any it already has.
+///
+/// Statements without any comments are returned unchanged.
+pub fn attach_comments<Source: Clone>(
+    statement: StatementNode<Source>,
+    comments: Comments<Source>,
+) -> StatementNode<Source> {
+    if comments.is_empty() {
+        return statement;
+    }
+
+    match statement.item {
+        Statement::Commented {
+            comments: existing,
+            statement: inner,
+        } => {
+            let mut leading = comments.leading;
+            leading.extend(existing.leading);
+            let mut trailing = existing.trailing;
+            trailing.extend(comments.trailing);
+            Statement::Commented {
+                comments: Comments { leading, trailing },
+                statement: inner,
+            }
+            .build_node(statement.source)
+        }
+        item => Statement::Commented {
+            comments,
+            statement: Box::new(item.build_node(statement.source.clone())),
+        }
+        .build_node(statement.source),
+    }
+}
+
 enum ParseKind<Source: SourceSpan> {
     While {
         source: Source,
         cond: ExprNode<Source>,
         block: BlockParser<Source>,
     },
+    If {
+        source: Source,
+        cond: ExprNode<Source>,
+        block: BlockParser<Source>,
+    },
+    Repeat {
+        source: Source,
+        block: BlockParser<Source>,
+    },
+    Until {
+        source: Source,
+        body: Vec<StatementNode<Source>>,
+    },
     Func {
         source: Source,
-        name: String,
-        params: Vec<String>,
+        vis: Node<Visibility, Source>,
+        name: Symbol,
+        params: Vec<Symbol>,
+        output: Option<Node<Type, Source>>,
         block: BlockParser<Source>,
     },
 }
 
 pub struct StatementParser<Source: SourceSpan> {
     kind: Option<ParseKind<Source>>,
+    comments: Comments<Source>,
 }
 
 impl<Source: SourceSpan> StatementParser<Source> {
@@ -42,12 +118,23 @@ impl<Source: SourceSpan> StatementParser<Source> {
     }
 
     pub fn none() -> Self {
-        Self { kind: None }
+        Self {
+            kind: None,
+            comments: Comments::new(),
+        }
     }
 
     pub fn parse_line<T: TokenStream<Source = Source>>(
         &mut self,
         line: &mut TokenLine<T>,
+    ) -> Result<Option<StatementNode<Source>>, Vec<PError<T>>> {
+        let statement = self.parse_kind(line)?;
+        Ok(statement.map(|statement| attach_comments(statement, take(&mut self.comments))))
+    }
+
+    fn parse_kind<T: TokenStream<Source = Source>>(
+        &mut self,
+        line: &mut TokenLine<T>,
     ) -> Result<Option<StatementNode<Source>>, Vec<PError<T>>> {
         match self.kind.take() {
             None => Ok(None),
@@ -74,21 +161,86 @@ impl<Source: SourceSpan> StatementParser<Source> {
                     Err(errors)
                 }
             },
+            Some(ParseKind::If {
+                source,
+                cond,
+                mut block,
+            }) => match block.parse_line(line) {
+                Ok(Some(pass)) => Ok(Some(
+                    Statement::If {
+                        cond,
+                        pass,
+                        fail: Vec::new(),
+                    }
+                    .build_node(source),
+                )),
+                Ok(None) => {
+                    self.kind = Some(ParseKind::If {
+                        source,
+                        cond,
+                        block,
+                    });
+                    Ok(None)
+                }
+                Err(errors) => {
+                    self.kind = Some(ParseKind::If {
+                        source,
+                        cond,
+                        block,
+                    });
+                    Err(errors)
+                }
+            },
+            Some(ParseKind::Repeat { source, mut block }) => {
+                // the until line is parsed after the body is complete
+                match block.parse_line(line) {
+                    Ok(Some(body)) => self.kind = Some(ParseKind::Until { source, body }),
+                    Ok(None) => self.kind = Some(ParseKind::Repeat { source, block }),
+                    Err(errors) => {
+                        self.kind = Some(ParseKind::Repeat { source, block });
+                        return Err(errors);
+                    }
+                }
+
+                Ok(None)
+            }
+            Some(ParseKind::Until { source, body }) => line.guard_else(
+                |line| {
+                    // parse the until keyword and condition
+                    line.take_exact(Some(&Token::Until)).map_err(|e| vec![e])?;
+                    let cond = expr::parse(line)?;
+                    line::parse_close(line)?;
+
+                    // the source spans from the repeat keyword to the condition
+                    let source = line.build_source(source.start()..cond.source.end());
+                    Ok(Some(Statement::Repeat { body, cond }.build_node(source)))
+                },
+                |errors| errors.consume_line(),
+            ),
             Some(ParseKind::Func {
                 source,
+                vis,
                 name,
                 params,
+                output,
                 mut block,
             }) => {
                 let result = match block.parse_line(line) {
                     Ok(None) => Ok(None),
                     Err(errors) => Err(errors),
                     Ok(Some(body)) => {
-                        let func = Func { params, body }.build_node(source.clone());
+                        let func = Func {
+                            vis,
+                            params,
+                            output,
+                            body,
+                        }
+                        .build_node(source.clone());
                         return Ok(Some(
                             Statement::Assign {
                                 init: true,
                                 lhs: Node::new(Expr::Var(name), source.clone()),
+                                ty: None,
                                 rhs: Node::new(Expr::Func(func), source.clone()),
                             }
                             .build_node(source),
@@ -98,8 +250,10 @@ impl<Source: SourceSpan> StatementParser<Source> {
 
                 self.kind = Some(ParseKind::Func {
                     source,
+                    vis,
                     name,
                     params,
+                    output,
                     block,
                 });
 
@@ -111,18 +265,39 @@ impl<Source: SourceSpan> StatementParser<Source> {
 
 pub fn start_parsing<T: TokenStream>(
     line: &mut TokenLine<T>,
+) -> Result<StatementType<T::Source>, Vec<PError<T>>> {
+    let statement = parse_statement(line)?;
+
+    // comments left on the line trail the statement
+    Ok(statement.attach(Comments {
+        leading: Vec::new(),
+        trailing: line.take_comments(),
+    }))
+}
+
+fn parse_statement<T: TokenStream>(
+    line: &mut TokenLine<T>,
 ) -> Result<StatementType<T::Source>, Vec<PError<T>>> {
     line.guard_else(
         |line| match line.peek_token() {
-            // LET STATEMENTS
-            Some(Ok(Token::Let)) => {
-                // consume the let token
-                line.consume_token();
+            // LET AND CONST STATEMENTS
+            Some(Ok(Token::Let | Token::Const)) => {
+                // consume the let or const token
+                let constant = matches!(line.take_token(), Some(Ok(Token::Const)));
                 let start = line.token_start();
 
                 // parse the lhs
                 let lhs = expr::parse(line)?;
 
+                // parse an optional type annotation for a single variable
+                let ty = match (&lhs.item, line.peek_token()) {
+                    (Expr::Var(_), Some(Ok(Token::Colon))) => {
+                        line.consume_token();
+                        Some(ty::parse(line)?)
+                    }
+                    _ => None,
+                };
+
                 // parse the assign symbol
                 line.take_exact(Some(&Token::Assign)).map_err(|e| vec![e])?;
 
@@ -135,15 +310,66 @@ pub fn start_parsing<T: TokenStream>(
                 // create source and build statement
                 let source = line.build_source(start..rhs.source.end());
                 Ok(StatementType::SingleLine(
-                    Statement::Assign {
-                        init: true,
-                        lhs,
-                        rhs,
+                    match constant {
+                        false => Statement::Assign {
+                            init: true,
+                            lhs,
+                            ty,
+                            rhs,
+                        },
+                        true => Statement::Const { lhs, ty, rhs },
                     }
                     .build_node(source),
                 ))
             }
 
+            // MODULE IMPORTS
+            Some(Ok(Token::Use)) => {
+                // consume the use token
+                line.consume_token();
+                let start = line.token_start();
+
+                // parse the module path
+                let path = line.take_guard(|token, line| match token {
+                    Some(Token::String(path)) => Ok(Node::new(path, line.token_source())),
+                    token => Err(vec![ParseError::UnexpectedInput {
+                        expect: "module path string".into(),
+                        found: token,
+                        source: line.token_source(),
+                    }]),
+                })?;
+
+                // parse an optional import of a single value
+                let import = match line.peek_token() {
+                    Some(Ok(Token::DoubleColon)) => {
+                        line.consume_token();
+                        let name = parse_ident(line)?;
+                        let alias = match line.peek_token() {
+                            Some(Ok(Token::As)) => {
+                                line.consume_token();
+                                Some(parse_ident(line)?)
+                            }
+                            _ => None,
+                        };
+                        Some(Import { name, alias })
+                    }
+                    _ => None,
+                };
+
+                // parse line close
+                line::parse_close(line)?;
+
+                // create source and build statement
+                let end = match &import {
+                    Some(import) => import.binding().source.end(),
+                    None => path.source.end(),
+                };
+                let source = line.build_source(start..end);
+                Ok(StatementType::SingleLine(
+                    Statement::Use { path, import }.build_node(source),
+                ))
+            }
+
             // WHILE LOOP
             Some(Ok(Token::While)) => {
                 // consume the while token
@@ -166,184 +392,89 @@ pub fn start_parsing<T: TokenStream>(
                         cond,
                         block,
                     }),
+                    comments: Comments::new(),
                 }))
             }
 
-            Some(Ok(Token::If)) => {
-                // consume the if token
+            // REPEAT LOOP
+            Some(Ok(Token::Repeat)) => {
+                // consume the repeat token
                 line.consume_token();
-                let start = line.token_start();
-
-                // parse condition
-                let cond = expr::parse(line)?;
-
-                // build source for if header
-                let _source = line.build_source(start..cond.source.end());
+                let source = line.token_source();
 
                 // parse the block header
-                // match block::parse_header(line)? {
-                //     block::Header::Complete(statement) => Ok(State::Complete(
-                //         Statement::If {
-                //             cond,
-                //             pass: vec![statement],
-                //             fail: vec![],
-                //         }
-                //         .build_node(source),
-                //     )),
-                //     block::Header::Incomplete(block_source) => Ok(State::Block(BlockStatement {
-                //         kind: BlockKind::If(cond),
-                //         block_source,
-                //         source,
-                //     })),
-                // }
-
-                todo!()
+                let block = block::start_parsing(line)?;
+
+                // return the repeat parser
+                Ok(StatementType::MultiLine(StatementParser {
+                    kind: Some(ParseKind::Repeat { source, block }),
+                    comments: Comments::new(),
+                }))
             }
 
-            Some(Ok(Token::Fn)) => {
-                // consume the fn token
+            Some(Ok(Token::If)) => {
+                // consume the if token
                 line.consume_token();
                 let start = line.token_start();
 
-                // parse the function ident
-                let name = match line.take_some("identifier").map_err(|e| vec![e])? {
-                    Token::Ident(ident) => ident,
-                    token => {
-                        return Err(vec![ParseError::UnexpectedInput {
-                            expect: "identifier".into(),
-                            found: Some(token),
-                            source: line.token_source(),
-                        }])
-                    }
-                };
-
-                // parse the open paren
-                line.take_exact(Some(&Token::OpenParen))
-                    .map_err(|e| vec![e])?;
-
-                // parse the parameters
-                let mut params = Vec::new();
-                let end = line.guard_else(
-                    |line| loop {
-                        // parse closing paren or ident
-                        match line.take_some("identifier or ')'").map_err(|e| vec![e])? {
-                            Token::CloseParen => break Ok(line.token_end()),
-                            Token::Ident(ident) => params.push(ident),
-                            token => {
-                                return Err(vec![ParseError::UnexpectedInput {
-                                    expect: "identifier or ')'".into(),
-                                    found: Some(token),
-                                    source: line.token_source(),
-                                }])
-                            }
-                        }
+                // parse condition
+                let cond = expr::parse(line)?;
 
-                        // parse comma or closing paren
-                        match line.take_some("',' or ')'").map_err(|e| vec![e])? {
-                            Token::Comma => continue,
-                            Token::CloseParen => break Ok(line.token_end()),
-                            token => {
-                                break Err(vec![ParseError::UnexpectedInput {
-                                    expect: "',' or ')'".into(),
-                                    found: Some(token),
-                                    source: line.token_source(),
-                                }])
-                            }
-                        }
-                    },
-                    |errors| {
-                        // consume until the end of braces
-                        match errors.consume_until(|t| match t {
-                            Token::CloseParen => ConsumeFlag::Inclusive,
-                            _ => ConsumeFlag::Ignore,
-                        }) {
-                            // if the error found a closing paren, then finish
-                            ConsumeEnd::Inclusive(_) => {}
-                            // otherwise, push an unclosed brace error too
-                            _ => errors.push(ParseError::UnclosedBrace {
-                                open: errors.line().build_source(start..start + 1),
-                                end: errors.line().token_end_source(),
-                            }),
-                        }
-                    },
-                )?;
+                // an if followed by 'then' is an if expression
+                if let Some(Ok(Token::Then)) = line.peek_token() {
+                    let expr = expr::parse_if_tail(start, cond, line)?;
+                    let expr = expr::parse_with_lhs(expr, line)?;
+                    return parse_expr_statement(expr, line);
+                }
 
-                // build source for function header
-                let source = line.build_source(start..end);
+                // build source for if header
+                let source = line.build_source(start..cond.source.end());
 
                 // parse the block header
                 let block = block::start_parsing(line)?;
 
-                // return the function parser
+                // return the if parser
                 Ok(StatementType::MultiLine(StatementParser {
-                    kind: Some(ParseKind::Func {
+                    kind: Some(ParseKind::If {
                         source,
-                        name,
-                        params,
+                        cond,
                         block,
                     }),
+                    comments: Comments::new(),
                 }))
             }
 
+            // FUNCTIONS
+            Some(Ok(Token::Fn)) => {
+                // consume the fn token
+                line.consume_token();
+
+                // functions without a visibility modifier are private
+                let vis = Visibility::Private.build_node(line.token_start_source());
+                parse_func(line, vis)
+            }
+
+            // VISIBILITY MODIFIERS
+            Some(Ok(Token::Pub | Token::Priv)) => {
+                // parse the visibility
+                let vis = match line.take_token() {
+                    Some(Ok(Token::Pub)) => Visibility::Public,
+                    _ => Visibility::Private,
+                };
+                let vis = vis.build_node(line.token_source());
+
+                // only definitions may have a visibility
+                line.take_exact(Some(&Token::Fn)).map_err(|e| vec![e])?;
+                parse_func(line, vis)
+            }
+
             // ASSIGNMENT OR EXPRESSION
             Some(_) => {
                 // parse initial expression
                 let expr = expr::parse(line)?;
 
                 // parse into either an assignment or expression
-                line.take_guard(|token, line| match token {
-                    // OPEN EXPRESSION
-                    Some(Token::Newline) | None => {
-                        // create source and build open expression
-                        let source = line.build_source(expr.source.span());
-                        Ok(StatementType::SingleLine(
-                            Statement::Expr {
-                                expr,
-                                closed: false,
-                            }
-                            .build_node(source),
-                        ))
-                    }
-
-                    // CLOSED EXPRESSION
-                    Some(Token::SemiColon) => {
-                        // parse line end
-                        line.take_exact(None).map_err(|e| vec![e])?;
-
-                        // create source and build closed expression
-                        let source = line.build_source(expr.source.span());
-                        Ok(StatementType::SingleLine(
-                            Statement::Expr { expr, closed: true }.build_node(source),
-                        ))
-                    }
-
-                    // ASSIGNMENT
-                    Some(Token::Assign) => {
-                        // parse rhs expression
-                        let rhs = expr::parse(line)?;
-
-                        // parse line close
-                        line::parse_close(line)?;
-
-                        // create source and build assignment
-                        let source = line.build_source(expr.source.start()..rhs.source.end());
-                        Ok(StatementType::SingleLine(
-                            Statement::Assign {
-                                init: false,
-                                lhs: expr,
-                                rhs,
-                            }
-                            .build_node(source),
-                        ))
-                    }
-
-                    // FAILURE CASE
-                    token => Err(vec![ParseError::UnexpectedInput {
-                        expect: "'=', ';', or end of line".into(),
-                        found: token,
-                        source: line.token_source(),
-                    }]),
-                })
+                parse_expr_statement(expr, line)
             }
 
             // FAILURE CASE
@@ -359,3 +490,303 @@ pub fn start_parsing<T: TokenStream>(
         },
     )
 }
+
+/// Parses the rest of a statement that starts with an expression
+fn parse_expr_statement<T: TokenStream>(
+    expr: ExprNode<T::Source>,
+    line: &mut TokenLine<T>,
+) -> Result<StatementType<T::Source>, Vec<PError<T>>> {
+    line.take_guard(|token, line| match token {
+        // OPEN EXPRESSION
+        Some(Token::Newline) | None => {
+            // create source and build open expression
+            let source = line.build_source(expr.source.span());
+            Ok(StatementType::SingleLine(
+                Statement::Expr {
+                    expr,
+                    closed: false,
+                }
+                .build_node(source),
+            ))
+        }
+
+        // CLOSED EXPRESSION
+        Some(Token::SemiColon) => {
+            // parse line end
+            line.take_exact(None).map_err(|e| vec![e])?;
+
+            // create source and build closed expression
+            let source = line.build_source(expr.source.span());
+            Ok(StatementType::SingleLine(
+                Statement::Expr { expr, closed: true }.build_node(source),
+            ))
+        }
+
+        // ASSIGNMENT
+        Some(Token::Assign) => {
+            // parse rhs expression
+            let rhs = expr::parse(line)?;
+
+            // parse line close
+            line::parse_close(line)?;
+
+            // create source and build assignment
+            let source = line.build_source(expr.source.start()..rhs.source.end());
+            Ok(StatementType::SingleLine(
+                Statement::Assign {
+                    init: false,
+                    lhs: expr,
+                    ty: None,
+                    rhs,
+                }
+                .build_node(source),
+            ))
+        }
+
+        // FAILURE CASE
+        token => Err(vec![ParseError::UnexpectedInput {
+            expect: "'=', ';', or end of line".into(),
+            found: token,
+            source: line.token_source(),
+        }]),
+    })
+}
+
+/// Parses a single identifier into a symbol
+fn parse_ident<T: TokenStream>(
+    line: &mut TokenLine<T>,
+) -> Result<Node<Symbol, T::Source>, Vec<PError<T>>> {
+    match line.take_some("identifier").map_err(|e| vec![e])? {
+        Token::Ident(ident) => Ok(Node::new(Symbol::from(ident), line.token_source())),
+        token => Err(vec![ParseError::UnexpectedInput {
+            expect: "identifier".into(),
+            found: Some(token),
+            source: line.token_source(),
+        }]),
+    }
+}
+
+/// Parses a function definition after its `fn` token has been consumed
+fn parse_func<T: TokenStream>(
+    line: &mut TokenLine<T>,
+    vis: Node<Visibility, T::Source>,
+) -> Result<StatementType<T::Source>, Vec<PError<T>>> {
+    // the function source starts at its visibility
+    let start = vis.source.start();
+
+    // parse the function ident
+    let name = match line.take_some("identifier").map_err(|e| vec![e])? {
+        Token::Ident(ident) => Symbol::from(ident),
+        token => {
+            return Err(vec![ParseError::UnexpectedInput {
+                expect: "identifier".into(),
+                found: Some(token),
+                source: line.token_source(),
+            }])
+        }
+    };
+
+    // parse the open paren
+    line.take_exact(Some(&Token::OpenParen))
+        .map_err(|e| vec![e])?;
+
+    // parse the parameters
+    let mut params = Vec::new();
+    let end = line.guard_else(
+        |line| loop {
+            // parse closing paren or ident
+            match line.take_some("identifier or ')'").map_err(|e| vec![e])? {
+                Token::CloseParen => break Ok(line.token_end()),
+                Token::Ident(ident) => params.push(ident.into()),
+                token => {
+                    return Err(vec![ParseError::UnexpectedInput {
+                        expect: "identifier or ')'".into(),
+                        found: Some(token),
+                        source: line.token_source(),
+                    }])
+                }
+            }
+
+            // parse comma or closing paren
+            match line.take_some("',' or ')'").map_err(|e| vec![e])? {
+                Token::Comma => continue,
+                Token::CloseParen => break Ok(line.token_end()),
+                token => {
+                    break Err(vec![ParseError::UnexpectedInput {
+                        expect: "',' or ')'".into(),
+                        found: Some(token),
+                        source: line.token_source(),
+                    }])
+                }
+            }
+        },
+        |errors| {
+            // consume until the end of braces
+            match errors.consume_until(|t| match t {
+                Token::CloseParen => ConsumeFlag::Inclusive,
+                _ => ConsumeFlag::Ignore,
+            }) {
+                // if the error found a closing paren, then finish
+                ConsumeEnd::Inclusive(_) => {}
+                // otherwise, push an unclosed brace error too
+                _ => errors.push(ParseError::UnclosedBrace {
+                    open: errors.line().build_source(start..start + 1),
+                    end: errors.line().token_end_source(),
+                }),
+            }
+        },
+    )?;
+
+    // parse an optional output type
+    let output = match line.peek_token() {
+        Some(Ok(Token::Arrow)) => {
+            line.consume_token();
+            Some(ty::parse(line)?)
+        }
+        _ => None,
+    };
+
+    // build source for function header
+    let end = output.as_ref().map_or(end, |output| output.source.end());
+    let source = line.build_source(start..end);
+
+    // parse the block header
+    let block = block::start_parsing(line)?;
+
+    // return the function parser
+    Ok(StatementType::MultiLine(StatementParser {
+        kind: Some(ParseKind::Func {
+            source,
+            vis,
+            name,
+            params,
+            output,
+            block,
+        }),
+        comments: Comments::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parsers::test_util::{TestSource, TestStream},
+        token::Span,
+    };
+
+    use super::*;
+
+    /// Parses `prefix` followed by the header and body of `fn f(): none`
+    fn func_vis(prefix: Vec<Token>) -> Result<Node<Visibility, TestSource>, ()> {
+        let mut tokens = prefix;
+        tokens.extend([
+            Token::Fn,
+            Token::Ident("f".into()),
+            Token::OpenParen,
+            Token::CloseParen,
+            Token::Colon,
+            Token::Newline,
+            Token::Indent,
+            Token::None,
+            Token::Newline,
+            Token::Dedent,
+        ]);
+        let mut stream = TestStream::new(tokens);
+
+        let mut line = TokenLine::new(&mut stream);
+        let Ok(StatementType::MultiLine(mut parser)) = start_parsing(&mut line) else {
+            return Err(());
+        };
+
+        let statement = loop {
+            let mut line = TokenLine::new(&mut stream);
+            if let Some(statement) = parser.parse_line(&mut line).expect("valid line") {
+                break statement;
+            }
+        };
+
+        let Statement::Assign { rhs, .. } = statement.item else {
+            panic!("expected a function assignment");
+        };
+        let Expr::Func(func) = rhs.item else {
+            panic!("expected a function");
+        };
+        Ok(func.vis.clone())
+    }
+
+    #[test]
+    fn function_visibility() {
+        // functions are private unless marked otherwise
+        let vis = func_vis(vec![]).expect("valid function");
+        assert_eq!(vis.item, Visibility::Private);
+
+        let vis = func_vis(vec![Token::Pub]).expect("valid function");
+        assert_eq!(vis.item, Visibility::Public);
+        assert_eq!(vis.source, TestSource(Span::from(0..1)));
+
+        let vis = func_vis(vec![Token::Priv]).expect("valid function");
+        assert_eq!(vis.item, Visibility::Private);
+    }
+
+    #[test]
+    fn visibility_needs_a_definition() {
+        // pub let x = 1
+        let mut stream = TestStream::new(vec![
+            Token::Pub,
+            Token::Let,
+            Token::Ident("x".into()),
+            Token::Assign,
+            Token::Int(1.into()),
+            Token::Newline,
+        ]);
+
+        let mut line = TokenLine::new(&mut stream);
+        let Err(errors) = start_parsing(&mut line) else {
+            panic!("expected a parse error");
+        };
+        assert!(matches!(
+            errors.as_slice(),
+            [ParseError::UnexpectedInput {
+                found: Some(Token::Let),
+                source: TestSource(span),
+                ..
+            }] if *span == Span::from(1..2)
+        ));
+    }
+
+    #[test]
+    fn if_block() {
+        // if a:
+        //     1
+        let mut stream = TestStream::new(vec![
+            Token::If,
+            Token::Ident("a".into()),
+            Token::Colon,
+            Token::Newline,
+            Token::Indent,
+            Token::Int(1.into()),
+            Token::Newline,
+            Token::Dedent,
+        ]);
+
+        let mut line = TokenLine::new(&mut stream);
+        let Ok(StatementType::MultiLine(mut parser)) = start_parsing(&mut line) else {
+            panic!("expected an if block");
+        };
+
+        let statement = loop {
+            let mut line = TokenLine::new(&mut stream);
+            if let Some(statement) = parser.parse_line(&mut line).expect("valid line") {
+                break statement;
+            }
+        };
+
+        assert_eq!(statement.source, TestSource(Span::from(0..2)));
+        let Statement::If { cond, pass, fail } = statement.item else {
+            panic!("expected an if statement");
+        };
+        assert!(matches!(cond.item, Expr::Var(_)));
+        assert_eq!(pass.len(), 1);
+        assert!(fail.is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{stream::SourceSpan, token::Span, Token, TokenStream};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSource(pub Span);
+
+impl SourceSpan for TestSource {
+    fn start(&self) -> usize {
+        self.0.start
+    }
+
+    fn end(&self) -> usize {
+        self.0.end
+    }
+
+    fn build(&self, span: impl Into<Span>) -> Self {
+        Self(span.into())
+    }
+}
+
+/// Streams tokens, each spanning its index
+pub struct TestStream {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl TestStream {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, index: 0 }
+    }
+}
+
+impl Iterator for TestStream {
+    type Item = Result<Token, ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.get(self.index)?.clone();
+        self.index += 1;
+        Some(Ok(token))
+    }
+}
+
+impl TokenStream for TestStream {
+    type Error = ();
+    type Source = TestSource;
+
+    fn token_start(&self) -> usize {
+        self.index.saturating_sub(1)
+    }
+
+    fn token_end(&self) -> usize {
+        self.index
+    }
+
+    fn build_source(&self, span: impl Into<Span>) -> Self::Source {
+        TestSource(span.into())
+    }
+}
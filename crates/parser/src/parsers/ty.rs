@@ -0,0 +1,25 @@
+use boba_script_core::ast::{node::Builder, ty::Type, Node};
+
+use crate::{error::PError, ParseError, Token, TokenLine, TokenStream};
+
+pub fn parse<T: TokenStream>(
+    line: &mut TokenLine<T>,
+) -> Result<Node<Type, T::Source>, Vec<PError<T>>> {
+    line.take_guard(|token, line| {
+        let ty = match &token {
+            Some(Token::None) => Some(Type::None),
+            Some(Token::Fn) => Some(Type::Func),
+            Some(Token::Ident(name)) => Type::from_name(name),
+            _ => None,
+        };
+
+        match ty {
+            Some(ty) => Ok(ty.build_node(line.token_source())),
+            None => Err(vec![ParseError::UnexpectedInput {
+                expect: "type".into(),
+                found: token,
+                source: line.token_source(),
+            }]),
+        }
+    })
+}
@@ -0,0 +1,29 @@
+use crate::Token;
+
+/// The associativity of a binary operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+/// Returns the precedence and associativity of a binary operator token.
+///
+/// Higher precedence operators bind tighter than lower ones.
+/// Returns `None` if the token is not a binary operator.
+/// There are no prefix operators in the grammar yet, so none are listed.
+pub fn precedence(token: &Token) -> Option<(u8, Assoc)> {
+    match token {
+        Token::Walrus => Some((1, Assoc::Right)),
+        Token::Question => Some((2, Assoc::Right)),
+        Token::Or => Some((3, Assoc::Left)),
+        Token::And => Some((4, Assoc::Left)),
+        Token::Eq | Token::Lt | Token::Gt | Token::NEq | Token::LtEq | Token::GtEq => {
+            Some((5, Assoc::Left))
+        }
+        Token::Add | Token::Sub => Some((6, Assoc::Left)),
+        Token::Mul | Token::Div | Token::Modulo => Some((7, Assoc::Left)),
+        Token::Pow => Some((8, Assoc::Right)),
+        _ => None,
+    }
+}
@@ -10,9 +10,9 @@ pub struct Span {
     pub end: usize,
 }
 
-impl Into<Range<usize>> for Span {
-    fn into(self) -> Range<usize> {
-        self.start..self.end
+impl From<Span> for Range<usize> {
+    fn from(val: Span) -> Self {
+        val.start..val.end
     }
 }
 
@@ -47,6 +47,10 @@ pub enum Token {
     Indent,
     #[display(fmt = "dedent")]
     Dedent,
+    #[display(fmt = "{}", _0)]
+    Whitespace(String),
+    #[display(fmt = "#{}", _0)]
+    Comment(String),
 
     // IDENTIFIERS
     #[display(fmt = "{}", _0)]
@@ -59,9 +63,9 @@ pub enum Token {
     Bool(bool),
     #[display(fmt = "{}", _0)]
     Int(IBig),
-    #[display(fmt = "{}", _0)]
+    #[display(fmt = "{}", "display_float(*_0)")]
     Float(f64),
-    #[display(fmt = "'{}'", _0)]
+    #[display(fmt = "'{}'", "_0.escape_default()")]
     String(String),
 
     // OPERATORS
@@ -107,10 +111,16 @@ pub enum Token {
     Assign,
     #[display(fmt = ":")]
     Colon,
+    #[display(fmt = "::")]
+    DoubleColon,
     #[display(fmt = ";")]
     SemiColon,
     #[display(fmt = "?")]
     Question,
+    #[display(fmt = "?.")]
+    QuestionPeriod,
+    #[display(fmt = "@")]
+    At,
     #[display(fmt = "(")]
     OpenParen,
     #[display(fmt = ")")]
@@ -135,14 +145,41 @@ pub enum Token {
     Fn,
     #[display(fmt = "if")]
     If,
+    #[display(fmt = "then")]
+    Then,
     #[display(fmt = "else")]
     Else,
     #[display(fmt = "while")]
     While,
+    #[display(fmt = "repeat")]
+    Repeat,
+    #[display(fmt = "until")]
+    Until,
+    #[display(fmt = "try")]
+    Try,
+    #[display(fmt = "catch")]
+    Catch,
     #[display(fmt = "static")]
     Static,
     #[display(fmt = "const")]
     Const,
+    #[display(fmt = "pub")]
+    Pub,
+    #[display(fmt = "priv")]
+    Priv,
+    #[display(fmt = "use")]
+    Use,
+    #[display(fmt = "as")]
+    As,
+}
+
+/// Displays a float so that it lexes back into a float.
+fn display_float(float: f64) -> String {
+    let float = float.to_string();
+    match float.contains('.') {
+        true => float,
+        false => format!("{float}.0"),
+    }
 }
 
 impl Token {
@@ -157,10 +194,19 @@ impl Token {
             "let" => Token::Let,
             "fn" => Token::Fn,
             "if" => Token::If,
+            "then" => Token::Then,
             "else" => Token::Else,
             "while" => Token::While,
+            "repeat" => Token::Repeat,
+            "until" => Token::Until,
+            "try" => Token::Try,
+            "catch" => Token::Catch,
             "static" => Token::Static,
             "const" => Token::Const,
+            "pub" => Token::Pub,
+            "priv" => Token::Priv,
+            "use" => Token::Use,
+            "as" => Token::As,
         };
 
         match KEYWORDS.get(str.as_ref()) {
@@ -1,6 +1,6 @@
 use std::{
     alloc::{alloc, dealloc, realloc, Layout},
-    mem::transmute,
+    ptr,
 };
 
 use derive_more::Display;
@@ -34,7 +34,7 @@ impl PtrVec {
     /// Creates a new [`PtrVec`] without allocating
     pub fn new(layout: Layout) -> Self {
         Self {
-            ptr: unsafe { transmute(layout.align()) },
+            ptr: ptr::without_provenance_mut(layout.align()),
             cap: match layout.size() {
                 0 => usize::MAX,
                 _ => 0,
@@ -114,12 +114,12 @@ impl PtrVec {
     }
 
     /// Returns an iterator over every pointer in the vec
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         self.iter_from(0)
     }
 
     /// Returns an iterator over all pointers in the vec starting at the `start` index
-    pub fn iter_from(&self, start: usize) -> Iter {
+    pub fn iter_from(&self, start: usize) -> Iter<'_> {
         Iter {
             vec: self,
             index: start,
@@ -1,6 +1,13 @@
-use std::path::PathBuf;
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
 
 use boba::{run, shell::RunState, Shell};
+use boba_script::{
+    ariadne::{ErrorCode, ReportStyle},
+    core::Engine,
+};
 use clap::Parser;
 
 #[derive(Parser)]
@@ -8,14 +15,48 @@ use clap::Parser;
 #[command(propagate_version = true)]
 struct BobaCli {
     file: Option<PathBuf>,
+
+    /// Run a script for every line of stdin, with `line` and `line_num` bound
+    #[arg(short = 'n', value_name = "SCRIPT", conflicts_with = "file")]
+    lines: Option<String>,
+
+    /// Print a longer description of an error code, like R-003
+    #[arg(long, value_name = "CODE", conflicts_with_all = ["file", "lines"])]
+    explain: Option<String>,
+
+    /// Print errors without color, which is the default when stderr is not a terminal
+    #[arg(long)]
+    no_color: bool,
+
+    /// Start without the prelude, so no builtins like 'print' are defined
+    #[arg(long)]
+    no_prelude: bool,
 }
 
 fn main() {
     let cli = BobaCli::parse();
+    if let Some(code) = cli.explain {
+        return match ErrorCode::from_code(&code) {
+            Some(code) => println!("[{code}] {}\n\n{}", code.title(), code.explanation()),
+            None => eprintln!("Unknown error code {code}"),
+        };
+    }
+
+    let color = !cli.no_color && io::stderr().is_terminal();
+    let style = ReportStyle::default().with_color(color);
+    let engine = match cli.no_prelude {
+        false => Engine::new(),
+        true => Engine::empty(),
+    };
+    if let Some(script) = cli.lines {
+        return run::lines(&script, style, engine);
+    }
+
     match cli.file {
         Some(path) => run::file(path),
         None => {
-            let mut shell = Shell::new();
+            let mut shell = Shell::with_engine(engine);
+            shell.set_report_style(style);
             loop {
                 match shell.read_line() {
                     Err(error) => panic!("{error}"),
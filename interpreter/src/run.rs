@@ -1,4 +1,28 @@
-use std::{fs, path::PathBuf};
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, BufRead},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use boba_script::{
+    ariadne::{Diagnostics, ReportStyle, SourceCache, ToAriadne},
+    core::{
+        ast::StatementNode,
+        engine::{Capabilities, Value},
+        Engine,
+    },
+    parser::{
+        parsers::statement::{self, StatementParser, StatementType},
+        TokenLine,
+    },
+};
+
+use crate::shell::{
+    stream::{ShellId, ShellSource},
+    ShellStream,
+};
 
 pub fn file(path: PathBuf) {
     let name = path.to_string_lossy();
@@ -12,3 +36,167 @@ pub fn file(path: PathBuf) {
 
     println!("{text}")
 }
+
+/// Runs a script once for every line read from stdin.
+///
+/// Each line is bound to `line` without its line ending,
+/// and its one-based position is bound to `line_num`.
+/// Other variables persist between lines until stdin ends.
+pub fn lines(script: &str, style: ReportStyle, mut engine: Engine<ShellSource>) {
+    let mut tokens = ShellStream::new();
+    let Some(statements) = parse_script(script, &mut tokens, style) else {
+        return;
+    };
+
+    // imported modules add their text to the cache so their errors can be shown
+    let sources = Sources::default();
+    sources.borrow_mut().insert(tokens.id(), tokens.source());
+
+    // check the script once before reading any input
+    engine.set_capabilities(Capabilities::all());
+    load_modules(&mut engine, &sources, style);
+    let debugged = sources.clone();
+    engine.set_debug_output(move |source: &ShellSource, value| {
+        let sources = debugged.borrow();
+        let text = sources.get(&source.id()).unwrap_or_default();
+        let (line, column) = source.position(text, style.tab_width());
+        eprintln!("[{}:{line}:{column}] value = {}", source.id(), value.repr());
+    });
+    for statement in statements.iter() {
+        let errors = engine.check(statement);
+        if !errors.is_empty() {
+            let mut cache = tokens.build_cache();
+            for error in errors {
+                error.to_ariadne_with(style).eprint(&mut cache).unwrap();
+            }
+            return;
+        }
+    }
+
+    for (index, line) in io::stdin().lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("Failed to read stdin: {err}");
+                return;
+            }
+        };
+
+        engine.vars_mut().init_local("line", Value::String(line));
+        engine
+            .vars_mut()
+            .init_local("line_num", Value::Int((index + 1).into()));
+        for statement in statements.iter() {
+            match engine.eval(statement) {
+                Ok(Value::None) => {} // do nothing
+                Ok(value) => println!("{value}"),
+                Err(error) => {
+                    let cache = &mut *sources.borrow_mut();
+                    error.to_ariadne_with(style).eprint(cache).unwrap();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// The text of the shell and of every module it imported, used to render reports.
+pub type Sources = Rc<RefCell<SourceCache<ShellId>>>;
+
+/// Lets `engine` import modules from files, adding the text of each one to `sources`.
+///
+/// Syntax errors in a module are printed with `style` when it is loaded.
+pub fn load_modules(engine: &mut Engine<ShellSource>, sources: &Sources, style: ReportStyle) {
+    let sources = sources.clone();
+    engine.set_module_loader(move |path| {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut tokens = ShellStream::with_id(ShellId::module(path.to_string_lossy()));
+        let statements = parse_script(&text, &mut tokens, style);
+        sources.borrow_mut().insert(tokens.id(), tokens.source());
+        statements.ok_or_else(|| "the module has syntax errors".into())
+    });
+}
+
+/// Parses every line of a script, printing all errors if any are found
+fn parse_script(
+    script: &str,
+    tokens: &mut ShellStream,
+    style: ReportStyle,
+) -> Option<Vec<StatementNode<ShellSource>>> {
+    let mut statements = Vec::new();
+    let mut diagnostics = Diagnostics::new(style);
+    let mut pending = StatementParser::none();
+
+    // a trailing blank line closes any open blocks
+    for text in script.lines().chain([""]) {
+        tokens.load(text);
+        while !tokens.is_empty() {
+            let mut line = TokenLine::new(tokens);
+            let result = match pending.is_none() {
+                false => pending.parse_line(&mut line),
+                true => match statement::start_parsing(&mut line) {
+                    Ok(StatementType::SingleLine(statement)) => Ok(Some(statement)),
+                    Ok(StatementType::MultiLine(parser)) => {
+                        pending = parser;
+                        Ok(None)
+                    }
+                    Err(errors) => Err(errors),
+                },
+            };
+
+            match result {
+                Ok(Some(statement)) => statements.push(statement),
+                Ok(None) => {}
+                Err(line_errors) => diagnostics.extend(line_errors),
+            }
+        }
+    }
+
+    if diagnostics.is_empty() {
+        return Some(statements);
+    }
+
+    eprint!("{}", diagnostics.render(tokens.build_cache()));
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use boba_script::{
+        ariadne::render_to_string,
+        core::engine::{Capability, EvalError},
+    };
+
+    use super::*;
+
+    #[test]
+    fn module_error_lines() {
+        let dir = std::env::temp_dir().join(format!("boba-modules-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("m.boba");
+        fs::write(&path, "pub fn f():\n    1\n\nmissing()\n").unwrap();
+
+        let style = ReportStyle::default().with_color(false);
+        let sources = Sources::default();
+        let mut engine = Engine::new();
+        engine.allow(Capability::Io, true);
+        load_modules(&mut engine, &sources, style);
+
+        let script = format!("use \"{}\"", path.display());
+        let statements = parse_script(&script, &mut ShellStream::new(), style).unwrap();
+        let error = engine.eval(&statements[0]).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let EvalError::UnknownFunction { source, .. } = &error else {
+            panic!("expected an unknown function, found {error:?}");
+        };
+        let sources = &mut *sources.borrow_mut();
+        let text = sources.get(&source.id()).unwrap();
+        assert_eq!(source.position(text, 4), (4, 1));
+
+        let report = error.to_ariadne_with(style);
+        let rendered = render_to_string([&report], sources);
+        assert!(rendered.contains(&format!("{}:4:1", path.display())));
+        assert!(rendered.contains(" 4 │ missing()"));
+    }
+}
@@ -1,8 +1,11 @@
 use std::io;
 
-use boba_script::ariadne::ToAriadne;
+use boba_script::ariadne::{ReportStyle, ToAriadne};
 use boba_script::{
-    core::{engine::Value, Engine},
+    core::{
+        engine::{Capabilities, Value},
+        Engine,
+    },
     parser::{
         parsers::statement::{self, StatementParser, StatementType},
         TokenLine,
@@ -11,6 +14,7 @@ use boba_script::{
 use reedline::{DefaultPrompt, DefaultPromptSegment, Reedline, Signal};
 
 use super::{stream::ShellSource, ShellStream};
+use crate::run::{self, Sources};
 
 pub enum RunState {
     Parsed,
@@ -25,30 +29,53 @@ pub struct Shell {
     tokens: ShellStream,
     engine: Engine<ShellSource>,
     pending: StatementParser<ShellSource>,
+    style: ReportStyle,
+    sources: Sources,
 }
 
 impl Default for Shell {
     fn default() -> Self {
+        Self::with_engine(Engine::new())
+    }
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a shell that runs every statement with `engine`.
+    pub fn with_engine(mut engine: Engine<ShellSource>) -> Self {
+        // the standalone interpreter is trusted with side effects
+        engine.set_capabilities(Capabilities::all());
+        let style = ReportStyle::default();
+        let sources = Sources::default();
+        run::load_modules(&mut engine, &sources, style);
+
         Self {
             editor: Reedline::create(),
             normal_prompt: DefaultPrompt::new(
-                DefaultPromptSegment::Basic(format!("boba ")),
+                DefaultPromptSegment::Basic("boba ".into()),
                 DefaultPromptSegment::Empty,
             ),
             pending_prompt: DefaultPrompt::new(
-                DefaultPromptSegment::Basic(format!("  ...")),
+                DefaultPromptSegment::Basic("  ...".into()),
                 DefaultPromptSegment::Empty,
             ),
             tokens: ShellStream::new(),
-            engine: Engine::new(),
+            engine,
             pending: StatementParser::none(),
+            style,
+            sources,
         }
     }
-}
 
-impl Shell {
-    pub fn new() -> Self {
-        Self::default()
+    /// Sets the style used to draw error reports.
+    pub fn set_report_style(&mut self, style: ReportStyle) {
+        self.style = style;
+
+        // modules print their syntax errors in the same style
+        run::load_modules(&mut self.engine, &self.sources, style);
     }
 
     pub fn read_line(&mut self) -> io::Result<RunState> {
@@ -101,18 +128,41 @@ impl Shell {
 
             // execute the completed statement
             match statement {
-                Ok(statement) => match self.engine.eval(statement) {
-                    Ok(Value::None) => {} // do nothing
-                    Ok(value) => println!("{value}"),
-                    Err(error) => error
-                        .to_ariadne()
-                        .eprint(self.tokens.build_cache())
-                        .unwrap(),
-                },
+                Ok(statement) => {
+                    // check for type errors before running anything
+                    let errors = self.engine.check(&statement);
+                    match errors.is_empty() {
+                        false => {
+                            let mut cache = self.tokens.build_cache();
+                            for error in errors {
+                                error
+                                    .to_ariadne_with(self.style)
+                                    .eprint(&mut cache)
+                                    .unwrap();
+                            }
+                        }
+                        true => match self.engine.eval(statement) {
+                            Ok(Value::None) => {} // do nothing
+                            Ok(value) => println!("{value}"),
+                            Err(error) => {
+                                // errors may point into the shell or any imported module
+                                let mut sources = self.sources.borrow_mut();
+                                sources.insert(self.tokens.id(), self.tokens.source());
+                                error
+                                    .to_ariadne_with(self.style)
+                                    .eprint(&mut *sources)
+                                    .unwrap()
+                            }
+                        },
+                    }
+                }
                 Err(errors) => {
                     let mut cache = self.tokens.build_cache();
                     for error in errors {
-                        error.to_ariadne().eprint(&mut cache).unwrap();
+                        error
+                            .to_ariadne_with(self.style)
+                            .eprint(&mut cache)
+                            .unwrap();
                     }
                 }
             }
@@ -1,13 +1,38 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+};
 
 use boba_script::{
     ariadne,
-    lexer::{LexError, Lexer},
+    lexer::{split_lines, LexError, LexTokens, Lexer},
     parser::{stream::SourceSpan, token::Span, Token, TokenStream},
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct ShellId;
+/// Names the text a source points into, either the shell itself or an imported module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShellId(&'static str);
+
+impl Default for ShellId {
+    fn default() -> Self {
+        Self("shell")
+    }
+}
+
+impl Display for ShellId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl ShellId {
+    /// Creates an id for a module.
+    ///
+    /// The name is leaked, which is fine as each module is only loaded once.
+    pub fn module(name: impl Into<String>) -> Self {
+        Self(Box::leak(name.into().into_boxed_str()))
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct ShellSource {
@@ -15,6 +40,23 @@ pub struct ShellSource {
     span: Span,
 }
 
+impl ShellSource {
+    pub fn id(&self) -> ShellId {
+        self.id
+    }
+
+    /// Returns the one-based line and column this source starts at in the `text` of its stream.
+    ///
+    /// The column is the display column with tabs expanded to `tab_width`.
+    pub fn position(&self, text: &str, tab_width: usize) -> (usize, usize) {
+        let before = &text[..self.span.start.min(text.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        let column = ariadne::display_column(&before[line_start..], tab_width) + 1;
+        (line, column)
+    }
+}
+
 impl ariadne::Span for ShellSource {
     type SourceId = ShellId;
 
@@ -42,13 +84,14 @@ impl SourceSpan for ShellSource {
 
     fn build(&self, span: impl Into<Span>) -> Self {
         Self {
-            id: ShellId,
+            id: self.id,
             span: span.into(),
         }
     }
 }
 
 pub struct ShellStream {
+    id: ShellId,
     tokens: VecDeque<(Result<Token, LexError>, Span)>,
     source: String,
     lexer: Lexer,
@@ -79,15 +122,27 @@ impl TokenStream for ShellStream {
 
     fn build_source(&self, span: impl Into<Span>) -> Self::Source {
         ShellSource {
-            id: ShellId,
+            id: self.id,
             span: span.into(),
         }
     }
 }
 
+impl Default for ShellStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ShellStream {
     pub fn new() -> Self {
+        Self::with_id(ShellId::default())
+    }
+
+    /// Creates a stream whose sources point into the text named by `id`.
+    pub fn with_id(id: ShellId) -> Self {
         Self {
+            id,
             tokens: VecDeque::new(),
             source: String::new(),
             lexer: Lexer::new(),
@@ -95,6 +150,10 @@ impl ShellStream {
         }
     }
 
+    pub fn id(&self) -> ShellId {
+        self.id
+    }
+
     pub fn source(&self) -> &str {
         &self.source
     }
@@ -103,43 +162,62 @@ impl ShellStream {
         self.tokens.is_empty()
     }
 
-    pub fn build_cache(&self) -> AriadneCache {
+    pub fn build_cache(&self) -> AriadneCache<'_> {
         AriadneCache {
             source: ariadne::Source::from(self.source.as_str()),
         }
     }
 
+    /// Loads the tokens of one or more lines of text.
+    ///
+    /// Blank text is still loaded as a line, so it can close any open blocks.
     pub fn load(&mut self, text: impl AsRef<str>) {
-        // start lexing the text
-        let text = text.as_ref();
-        let mut tokens = self.lexer.lex(text);
-
-        // load all the tokens
-        let mut loaded = false;
-        let span_offset = self.source.len() + 1;
-        while let Some(result) = tokens.next() {
-            let mut span = tokens.token_span();
-            span.start += span_offset;
-            span.end += span_offset;
-            self.tokens.push_back((result, span));
-            loaded = true;
+        // split on every line ending, including a lone carriage return
+        let mut lines = split_lines(text.as_ref()).peekable();
+        if lines.peek().is_none() {
+            return self.load_line("");
+        }
+
+        for line in lines {
+            self.load_line(line.trim_end_matches(['\r', '\n']));
         }
+    }
 
-        // if there were no tokens
-        // reset the indent and try loading the dedent tokens
+    fn load_line(&mut self, text: &str) {
+        // lex and load all the tokens
+        let span_offset = self.source.len();
+        let tokens = self.lexer.lex(text);
+        let loaded = load_tokens(tokens, span_offset, &mut self.tokens);
+
+        // if there were no tokens, then the line was blank
+        // lex it again as the final line to close all open blocks
         if !loaded {
-            for _ in 0..self.lexer.close_blocks() {
-                let end = self.span.end;
-                let span = Span::from(end..end);
-                self.tokens.push_back((Ok(Token::Dedent), span))
-            }
+            let tokens = self.lexer.lex_final(text);
+            load_tokens(tokens, span_offset, &mut self.tokens);
         }
 
-        // load the text into the source
-        self.source.push_str(&format!("\n{text}"));
+        // load the text into the source, so each line starts where the last one ended
+        self.source.push_str(text);
+        self.source.push('\n');
     }
 }
 
+fn load_tokens(
+    mut tokens: LexTokens,
+    span_offset: usize,
+    store: &mut VecDeque<(Result<Token, LexError>, Span)>,
+) -> bool {
+    let mut loaded = false;
+    while let Some(result) = tokens.next() {
+        let mut span = tokens.token_span();
+        span.start += span_offset;
+        span.end += span_offset;
+        store.push_back((result, span));
+        loaded = true;
+    }
+    loaded
+}
+
 pub struct AriadneCache<'a> {
     source: ariadne::Source<&'a str>,
 }
@@ -154,7 +232,7 @@ impl<'a> ariadne::Cache<ShellId> for AriadneCache<'a> {
         Ok(&self.source)
     }
 
-    fn display<'b>(&self, _: &'b ShellId) -> Option<Box<dyn std::fmt::Display + 'b>> {
-        Some(Box::new("shell"))
+    fn display<'b>(&self, id: &'b ShellId) -> Option<Box<dyn std::fmt::Display + 'b>> {
+        Some(Box::new(id))
     }
 }